@@ -12,6 +12,9 @@ TARGET_DIR="target"
 LAMBDA_DIR="lambda-package"
 BOOTSTRAP_NAME="bootstrap"
 PACKAGE_NAME="lambda-deployment.zip"
+REDIRECTS_SOURCE="${REDIRECTS_SOURCE:-_redirects}"  # Optional rules file, packaged as /var/task/_redirects
+CONTENT_SOURCE="${CONTENT_SOURCE:-public}"  # Optional static asset directory, packaged as /var/task/public
+ARCHIVE_SOURCE="${ARCHIVE_SOURCE:-site.tar.zst}"  # Optional site bundle, packaged under its own name in /var/task
 
 # Colors for output
 RED='\033[0;31m'
@@ -347,11 +350,39 @@ create_package() {
     local binary_size=$(stat -f%z "$LAMBDA_DIR/$BOOTSTRAP_NAME" 2>/dev/null || stat -c%s "$LAMBDA_DIR/$BOOTSTRAP_NAME" 2>/dev/null || echo "unknown")
     log_info "Bootstrap binary size: $binary_size bytes"
     
+    # Include the redirect rules file if the project has one
+    # Set REDIRECTS_FILE=/var/task/_redirects on the function to enable it
+    local package_files=("$BOOTSTRAP_NAME")
+    if [[ -f "$REDIRECTS_SOURCE" ]]; then
+        log_info "Including redirect rules: $REDIRECTS_SOURCE"
+        cp "$REDIRECTS_SOURCE" "$LAMBDA_DIR/_redirects"
+        package_files+=("_redirects")
+    fi
+    
+    # Include the static asset directory (e.g. a React build output) if present
+    # Set CONTENT_DIR=/var/task/public on the function to serve it
+    if [[ -d "$CONTENT_SOURCE" ]]; then
+        log_info "Including static assets: $CONTENT_SOURCE"
+        rm -rf "$LAMBDA_DIR/public"
+        cp -R "$CONTENT_SOURCE" "$LAMBDA_DIR/public"
+        package_files+=("public")
+    fi
+    
+    # Include a site bundle (zip, tar or tar.zst from CI) if present
+    # Set CONTENT_ARCHIVE=/var/task/<name> on the function to serve it
+    if [[ -f "$ARCHIVE_SOURCE" ]]; then
+        local archive_name
+        archive_name=$(basename "$ARCHIVE_SOURCE")
+        log_info "Including site bundle: $ARCHIVE_SOURCE (set CONTENT_ARCHIVE=/var/task/$archive_name)"
+        cp "$ARCHIVE_SOURCE" "$LAMBDA_DIR/$archive_name"
+        package_files+=("$archive_name")
+    fi
+    
     # Create ZIP package
     log_info "Creating ZIP package: $PACKAGE_NAME"
     
     # Change to lambda directory to avoid including directory structure in ZIP
-    (cd "$LAMBDA_DIR" && zip -r "../$PACKAGE_NAME" "$BOOTSTRAP_NAME")
+    (cd "$LAMBDA_DIR" && zip -r "../$PACKAGE_NAME" "${package_files[@]}")
     
     if [[ -f "$PACKAGE_NAME" ]]; then
         local package_size=$(stat -f%z "$PACKAGE_NAME" 2>/dev/null || stat -c%s "$PACKAGE_NAME" 2>/dev/null || echo "unknown")
@@ -0,0 +1,316 @@
+// Access log in Common/Combined Log Format or a custom template
+// This module writes one line per request for log analyzers such as GoAccess, apart from the diagnostic logs
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use lambda_http::request::RequestContext as LambdaRequestContext;
+use lambda_http::{Request, RequestExt};
+
+use crate::canonical::request_host;
+use crate::config::ServerConfig;
+use crate::redact::{self, Redactor};
+
+/// Variables available in `ACCESS_LOG` templates
+pub const TEMPLATE_VARIABLES: [&str; 19] = [
+    "remote_addr",
+    "remote_user",
+    "time_local",
+    "time_iso8601",
+    "request",
+    "request_method",
+    "request_uri",
+    "uri",
+    "args",
+    "server_protocol",
+    "status",
+    "bytes",
+    "body_bytes_sent",
+    "http_referer",
+    "http_user_agent",
+    "request_time",
+    "latency_ms",
+    "request_id",
+    "host",
+];
+
+/// Layout of access log lines
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessLogFormat {
+    /// Common Log Format: `host - - [time] "request" status bytes`
+    Common,
+    /// Combined Log Format: Common plus `"referer" "user-agent"`
+    Combined,
+    /// nginx-style template such as `$remote_addr $status $bytes $request_time`
+    Template(String),
+}
+
+impl AccessLogFormat {
+    /// Parses an `ACCESS_LOG` value
+    ///
+    /// `off` (or empty) disables the access log, `common` (alias `clf`) and
+    /// `combined` select the standard formats, and anything containing `$` is
+    /// a template. Templates are checked here so a typo in a variable name is
+    /// reported at startup instead of appearing in every line.
+    pub fn parse(value: &str) -> Result<Option<AccessLogFormat>, String> {
+        let trimmed = value.trim();
+        match trimmed.to_ascii_lowercase().as_str() {
+            "" | "off" | "false" | "none" => return Ok(None),
+            "common" | "clf" => return Ok(Some(AccessLogFormat::Common)),
+            "combined" => return Ok(Some(AccessLogFormat::Combined)),
+            _ => {}
+        }
+        if !trimmed.contains('$') {
+            return Err(format!("ACCESS_LOG must be off, common, combined or a $variable template, got '{}'", trimmed));
+        }
+        for variable in template_variables(trimmed) {
+            if !TEMPLATE_VARIABLES.contains(&variable) {
+                return Err(format!("Unknown ACCESS_LOG variable ${}", variable));
+            }
+        }
+        Ok(Some(AccessLogFormat::Template(trimmed.to_string())))
+    }
+}
+
+/// What the access log records about one request
+///
+/// Captured from the request before the pipeline consumes it; the handler
+/// fills in `status`, `bytes` and `latency` once the response is built.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessLogEntry {
+    /// Client IP address (see `client_ip`)
+    pub remote_addr: Option<String>,
+    /// When the request arrived
+    pub time: DateTime<Utc>,
+    /// Request method
+    pub method: String,
+    /// Path and query as requested
+    pub uri: String,
+    /// `HTTP/1.1`, `HTTP/2.0`, ...
+    pub protocol: String,
+    /// Requested host, as used for host validation
+    pub host: Option<String>,
+    /// `Referer` header
+    pub referer: Option<String>,
+    /// `User-Agent` header
+    pub user_agent: Option<String>,
+    /// Request ID, as in the diagnostic logs and `x-request-id`
+    pub request_id: String,
+    /// Response status
+    pub status: u16,
+    /// Response body size
+    pub bytes: u64,
+    /// Time spent in the handler
+    pub latency: Duration,
+}
+
+impl AccessLogEntry {
+    /// Captures the request side of an entry
+    pub fn from_request(request: &Request, config: &ServerConfig, request_id: &str) -> AccessLogEntry {
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        AccessLogEntry {
+            remote_addr: client_ip(request, config),
+            time: Utc::now(),
+            method: request.method().to_string(),
+            uri: request
+                .uri()
+                .path_and_query()
+                .map(|path| path.as_str().to_string())
+                .unwrap_or_else(|| "/".to_string()),
+            protocol: format!("{:?}", request.version()),
+            host: request_host(request, config),
+            referer: header("referer"),
+            user_agent: header("user-agent"),
+            request_id: request_id.to_string(),
+            status: 0,
+            bytes: 0,
+            latency: Duration::ZERO,
+        }
+    }
+
+    /// Returns the entry with personal data masked for logging
+    ///
+    /// The client IP is truncated or hashed per `LOG_CLIENT_IP`; the URI,
+    /// referer and user agent go through the same redaction as the
+    /// diagnostic logs (secret query parameters, emails, tokens).
+    pub fn redacted(&self, redactor: &Redactor) -> AccessLogEntry {
+        let keep = [self.request_id.as_str()];
+        AccessLogEntry {
+            remote_addr: self.remote_addr.as_deref().and_then(|ip| redactor.client_ip(ip)),
+            uri: redactor.redact(&self.uri, &keep),
+            referer: self.referer.as_deref().map(|referer| redactor.redact(referer, &keep)),
+            user_agent: self.user_agent.as_deref().map(|agent| redactor.redact(agent, &keep)),
+            ..self.clone()
+        }
+    }
+
+    /// Formats the entry as one log line
+    ///
+    /// Missing values are written as `-`, and `"`, `\` and non-printable
+    /// characters in request data are escaped (`\"`, `\\`, `\x0A`) as nginx
+    /// does, so a crafted header can't break the line apart.
+    pub fn render(&self, format: &AccessLogFormat) -> String {
+        match format {
+            AccessLogFormat::Common => self.render_template(COMMON_TEMPLATE),
+            AccessLogFormat::Combined => self.render_template(COMBINED_TEMPLATE),
+            AccessLogFormat::Template(template) => self.render_template(template),
+        }
+    }
+
+    /// Returns the value of one template variable
+    fn variable(&self, name: &str) -> String {
+        let or_dash = |value: Option<&str>| value.map(escape).unwrap_or_else(|| "-".to_string());
+        let (path, query) = match self.uri.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (self.uri.as_str(), None),
+        };
+        match name {
+            "remote_addr" => or_dash(self.remote_addr.as_deref()),
+            "remote_user" => "-".to_string(),
+            "time_local" => self.time.format("%d/%b/%Y:%H:%M:%S +0000").to_string(),
+            "time_iso8601" => self.time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            "request" => escape(&format!("{} {} {}", self.method, self.uri, self.protocol)),
+            "request_method" => escape(&self.method),
+            "request_uri" => escape(&self.uri),
+            "uri" => escape(path),
+            "args" => or_dash(query),
+            "server_protocol" => self.protocol.clone(),
+            "status" => self.status.to_string(),
+            // CLF writes `-` for an empty body
+            "bytes" if self.bytes == 0 => "-".to_string(),
+            "bytes" => self.bytes.to_string(),
+            "body_bytes_sent" => self.bytes.to_string(),
+            "http_referer" => or_dash(self.referer.as_deref()),
+            "http_user_agent" => or_dash(self.user_agent.as_deref()),
+            "request_time" => format!("{:.3}", self.latency.as_secs_f64()),
+            "latency_ms" => self.latency.as_millis().to_string(),
+            "request_id" => escape(&self.request_id),
+            "host" => or_dash(self.host.as_deref()),
+            _ => String::new(),
+        }
+    }
+
+    /// Substitutes `$name` and `${name}` variables in a template
+    fn render_template(&self, template: &str) -> String {
+        let mut line = String::with_capacity(template.len() + 64);
+        let mut rest = template;
+        while let Some(start) = rest.find('$') {
+            line.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            match parse_variable(after) {
+                Some((name, consumed)) => {
+                    line.push_str(&self.variable(name));
+                    rest = &after[consumed..];
+                }
+                None => {
+                    line.push('$');
+                    rest = after;
+                }
+            }
+        }
+        line.push_str(rest);
+        line
+    }
+}
+
+/// `%h %l %u %t "%r" %>s %b`
+const COMMON_TEMPLATE: &str = "$remote_addr - $remote_user [$time_local] \"$request\" $status $bytes";
+
+/// `%h %l %u %t "%r" %>s %b "%{Referer}i" "%{User-agent}i"`
+const COMBINED_TEMPLATE: &str =
+    "$remote_addr - $remote_user [$time_local] \"$request\" $status $bytes \"$http_referer\" \"$http_user_agent\"";
+
+/// Returns the client IP address of a request
+///
+/// ## Sources (in order of preference):
+///
+/// 1. **`X-Forwarded-For`**, only with `TRUST_FORWARDED_FOR=true`: the
+///    first (leftmost) address, which CloudFront or a load balancer in front
+///    of the function sets to the viewer. Anyone can send the header, so it
+///    is only trusted when a proxy is known to be in front.
+/// 2. **Request context**: `sourceIp` of the Function URL / API Gateway
+///    event (for the local server, the peer address of the connection).
+///    Behind CloudFront this is an edge address, not the viewer.
+/// 3. **`X-Forwarded-For`** when there is no request context (ALB events).
+pub fn client_ip(request: &Request, config: &ServerConfig) -> Option<String> {
+    let forwarded = request
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .map(str::to_string);
+    if config.trust_forwarded_for && forwarded.is_some() {
+        return forwarded;
+    }
+
+    let source_ip = match request.request_context_ref() {
+        Some(LambdaRequestContext::ApiGatewayV2(context)) => context.http.source_ip.clone(),
+        Some(LambdaRequestContext::ApiGatewayV1(context)) => context.identity.source_ip.clone(),
+        Some(LambdaRequestContext::WebSocket(context)) => context.identity.source_ip.clone(),
+        Some(LambdaRequestContext::Alb(_)) | None => None,
+    };
+    source_ip.filter(|ip| !ip.is_empty()).or(forwarded)
+}
+
+/// Writes one access log line to stdout
+///
+/// Access log lines bypass the logger: analyzers expect the bare format, and
+/// `RUST_LOG` (tuned for diagnostics) must not drop them. They are redacted
+/// with the logger's redactor (see `AccessLogEntry::redacted`).
+pub fn write(entry: &AccessLogEntry, format: &AccessLogFormat) {
+    println!("{}", entry.redacted(redact::global()).render(format));
+}
+
+/// Parses a variable name after `$`: `name` or `{name}`
+///
+/// Returns the name and the number of bytes consumed.
+fn parse_variable(after: &str) -> Option<(&str, usize)> {
+    if let Some(braced) = after.strip_prefix('{') {
+        let end = braced.find('}')?;
+        return Some((&braced[..end], end + 2));
+    }
+    let end = after
+        .find(|c: char| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'))
+        .unwrap_or(after.len());
+    (end > 0).then(|| (&after[..end], end))
+}
+
+/// Returns the names of all variables in a template
+fn template_variables(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('$') {
+        let after = &rest[start + 1..];
+        match parse_variable(after) {
+            Some((name, consumed)) => {
+                names.push(name);
+                rest = &after[consumed..];
+            }
+            None => rest = after,
+        }
+    }
+    names
+}
+
+/// Escapes request data for a log line the way nginx does
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'"' => escaped.push_str("\\\""),
+            b'\\' => escaped.push_str("\\\\"),
+            0x20..=0x7e => escaped.push(byte as char),
+            _ => escaped.push_str(&format!("\\x{:02X}", byte)),
+        }
+    }
+    escaped
+}
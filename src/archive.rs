@@ -0,0 +1,286 @@
+// Site bundle content source
+// This module serves site content from a zip or tar(.zst) archive indexed in memory
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use log::info;
+use sha2::{Digest, Sha256};
+
+use crate::content::{content_type_for, Asset};
+use crate::listing::DirectoryEntry;
+
+/// Site bundle compiled in with the `embedded-archive` feature
+///
+/// Build with `SITE_ARCHIVE=/abs/path/site.tar.zst cargo build --features
+/// embedded-archive` to ship the site inside the `bootstrap` binary.
+#[cfg(feature = "embedded-archive")]
+pub static EMBEDDED_ARCHIVE: Option<&[u8]> = Some(include_bytes!(env!("SITE_ARCHIVE")));
+
+/// Site bundle compiled in with the `embedded-archive` feature (not enabled)
+#[cfg(not(feature = "embedded-archive"))]
+pub static EMBEDDED_ARCHIVE: Option<&[u8]> = None;
+
+/// Archive formats, detected from the leading magic bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarZstd,
+}
+
+impl ArchiveFormat {
+    /// Detects the format of an archive from its contents
+    ///
+    /// The file name is not used: CI artifacts are often renamed, and the
+    /// embedded bundle has no name at all.
+    pub fn detect(bytes: &[u8]) -> Option<ArchiveFormat> {
+        if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+            Some(ArchiveFormat::Zip)
+        } else if bytes.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            Some(ArchiveFormat::TarZstd)
+        } else if bytes.len() > 262 && &bytes[257..262] == b"ustar" {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// One file of the bundle, with its ETag computed at load time
+#[derive(Debug)]
+struct ArchiveEntry {
+    body: Vec<u8>,
+    etag: String,
+    last_modified: Option<DateTime<Utc>>,
+}
+
+/// Site content stored in a zip, tar or tar.zst bundle
+///
+/// ## Configuration:
+///
+/// - `CONTENT_ARCHIVE`: Path to the bundle, e.g. `/var/task/site.tar.zst`
+/// - Or build with the `embedded-archive` feature to compile the bundle in;
+///   it is used when no other content source is configured
+///
+/// The whole archive is decompressed and indexed once at startup, so every
+/// request is a map lookup with no I/O. Each file's ETag is the SHA-256 of
+/// its contents, which stays the same across containers and deploys as long
+/// as the file doesn't change, so CDN and browser revalidation keep working
+/// when a new bundle ships with mostly unchanged files.
+///
+/// Entry names are normalized to root-relative paths: a leading `./` (as
+/// written by `tar -C dist -cf site.tar .`) is removed, and entries with
+/// `..` segments or absolute paths are skipped.
+#[derive(Clone)]
+pub struct ArchiveSource {
+    /// Where the bundle came from (a path or `embedded`), for logs
+    pub origin: String,
+    entries: Arc<BTreeMap<String, ArchiveEntry>>,
+}
+
+// Compared by origin and index size; bundles are immutable once loaded
+impl PartialEq for ArchiveSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.origin == other.origin && self.entries.len() == other.entries.len()
+    }
+}
+
+impl fmt::Debug for ArchiveSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArchiveSource")
+            .field("origin", &self.origin)
+            .field("files", &self.entries.len())
+            .finish()
+    }
+}
+
+impl ArchiveSource {
+    /// Reads and indexes the bundle at `path`
+    pub fn load(path: &Path) -> Result<ArchiveSource, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        ArchiveSource::from_bytes(&path.display().to_string(), &bytes)
+    }
+
+    /// Indexes a bundle held in memory, e.g. one embedded with `include_bytes!`
+    pub fn from_bytes(origin: &str, bytes: &[u8]) -> Result<ArchiveSource, String> {
+        let start = std::time::Instant::now();
+        let format = ArchiveFormat::detect(bytes)
+            .ok_or_else(|| format!("{} is not a zip, tar or tar.zst archive", origin))?;
+
+        let files = match format {
+            ArchiveFormat::Zip => read_zip(bytes),
+            ArchiveFormat::Tar => read_tar(bytes),
+            ArchiveFormat::TarZstd => read_tar(&decompress_zstd(bytes)?),
+        }
+        .map_err(|e| format!("Failed to read {}: {}", origin, e))?;
+
+        let mut entries = BTreeMap::new();
+        let mut total_bytes = 0;
+        for (name, body, last_modified) in files {
+            let Some(path) = normalize_entry_name(&name) else {
+                continue;
+            };
+            total_bytes += body.len();
+            let etag = content_etag(&body);
+            entries.insert(path, ArchiveEntry { body, etag, last_modified });
+        }
+
+        info!("[{}] [ARCHIVE] Indexed {}: format={:?} files={} bytes={} duration={}ms",
+              Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+              origin,
+              format,
+              entries.len(),
+              total_bytes,
+              start.elapsed().as_millis());
+
+        Ok(ArchiveSource { origin: origin.to_string(), entries: Arc::new(entries) })
+    }
+
+    /// Returns the file at a sanitized, root-relative path
+    pub fn get(&self, path: &str) -> Option<Asset> {
+        self.entries.get(path).map(|entry| Asset {
+            path: path.to_string(),
+            body: entry.body.clone(),
+            content_type: content_type_for(path),
+            etag: Some(entry.etag.clone()),
+            last_modified: entry.last_modified,
+        })
+    }
+
+    /// Checks whether a file exists at a path
+    pub fn exists(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    /// Lists the files and subdirectories directly under a path
+    ///
+    /// Archives don't need explicit directory entries, so directories are
+    /// inferred from the file paths. Returns `None` when nothing is below
+    /// the path.
+    pub fn list(&self, path: &str) -> Option<Vec<DirectoryEntry>> {
+        let prefix = if path.ends_with('/') { path.to_string() } else { format!("{}/", path) };
+
+        let mut entries: Vec<DirectoryEntry> = Vec::new();
+        for (file_path, entry) in self.entries.range(prefix.clone()..) {
+            let Some(rest) = file_path.strip_prefix(prefix.as_str()) else {
+                break;
+            };
+            let entry = match rest.split_once('/') {
+                Some((dir, _)) => DirectoryEntry { name: dir.to_string(), is_dir: true, size: 0, modified: None },
+                None => DirectoryEntry {
+                    name: rest.to_string(),
+                    is_dir: false,
+                    size: entry.body.len() as u64,
+                    modified: entry.last_modified,
+                },
+            };
+            if entry.name.starts_with('.') || entries.last().is_some_and(|last| last.name == entry.name) {
+                continue;
+            }
+            entries.push(entry);
+        }
+
+        if entries.is_empty() {
+            None
+        } else {
+            Some(entries)
+        }
+    }
+
+    /// Iterates over every file path in the bundle, in sorted order
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|path| path.as_str())
+    }
+}
+
+/// A decoded archive member: name, contents and modification time
+type ArchiveFile = (String, Vec<u8>, Option<DateTime<Utc>>);
+
+/// Reads every regular file of a zip archive
+fn read_zip(bytes: &[u8]) -> Result<Vec<ArchiveFile>, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let mut files = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).map_err(|e| e.to_string())?;
+        if !file.is_file() {
+            continue;
+        }
+        let name = file.name().to_string();
+        let last_modified = file.last_modified().and_then(|time| {
+            NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?
+                .and_hms_opt(time.hour().into(), time.minute().into(), time.second().into())
+                .map(|naive| naive.and_utc())
+        });
+        let mut body = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut body).map_err(|e| format!("{}: {}", name, e))?;
+        files.push((name, body, last_modified));
+    }
+    Ok(files)
+}
+
+/// Reads every regular file of an uncompressed tar archive
+fn read_tar(bytes: &[u8]) -> Result<Vec<ArchiveFile>, String> {
+    let mut archive = tar::Archive::new(bytes);
+    let mut files = Vec::new();
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path().map_err(|e| e.to_string())?.to_string_lossy().into_owned();
+        let last_modified = entry
+            .header()
+            .mtime()
+            .ok()
+            .and_then(|mtime| DateTime::from_timestamp(mtime as i64, 0));
+        let mut body = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut body).map_err(|e| format!("{}: {}", name, e))?;
+        files.push((name, body, last_modified));
+    }
+    Ok(files)
+}
+
+/// Decompresses a zstd stream, which may consist of several frames
+fn decompress_zstd(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut input = bytes;
+    let mut output = Vec::with_capacity(bytes.len() * 4);
+    while !input.is_empty() {
+        let mut decoder = ruzstd::decoding::StreamingDecoder::new(&mut input)
+            .map_err(|e| format!("invalid zstd frame: {}", e))?;
+        decoder
+            .read_to_end(&mut output)
+            .map_err(|e| format!("zstd decompression failed: {}", e))?;
+    }
+    Ok(output)
+}
+
+/// Turns an archive member name into a root-relative request path
+///
+/// Returns `None` for names that would escape the site root.
+fn normalize_entry_name(name: &str) -> Option<String> {
+    let mut segments = Vec::new();
+    for segment in name.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return None,
+            _ => segments.push(segment),
+        }
+    }
+    if segments.is_empty() || name.starts_with('/') {
+        return None;
+    }
+    Some(format!("/{}", segments.join("/")))
+}
+
+/// Computes a strong ETag from file contents
+fn content_etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex: String = digest.iter().take(16).map(|byte| format!("{:02x}", byte)).collect();
+    format!("\"{}\"", hex)
+}
@@ -2,7 +2,6 @@
 // This calls the real handler function to ensure we're testing the actual Lambda logic
 
 use lambda_http::{Error, Body};
-use tokio;
 
 // Import the actual handler function from the library
 use static_web_lambda::function_handler;
@@ -0,0 +1,284 @@
+// In-memory cache for content sources
+// This module keeps recently served assets in memory across warm invocations
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use log::info;
+
+use crate::content::{Asset, ContentError, ContentSource, Revalidation};
+
+/// Default `CACHE_MAX_BYTES`: a quarter of the smallest Lambda memory size
+pub const DEFAULT_CACHE_MAX_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Default `CACHE_TTL_SECONDS`
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Default `CACHE_NEGATIVE_TTL_SECONDS`
+pub const DEFAULT_CACHE_NEGATIVE_TTL: Duration = Duration::from_secs(10);
+
+/// Bytes charged for an entry on top of its body, so negative entries and
+/// tiny files still count against the limit
+const ENTRY_OVERHEAD_BYTES: u64 = 256;
+
+/// A bounded LRU cache in front of a content source
+///
+/// ## Behavior:
+///
+/// - **Fresh hits**: Entries younger than the TTL are served from memory
+///   without touching the source
+/// - **Revalidation**: Expired entries with an ETag are revalidated with a
+///   conditional request (`If-None-Match` on S3, a metadata check on disk);
+///   an unchanged asset is kept and its TTL restarted without re-downloading
+/// - **Negative caching**: Missing paths are remembered for the (shorter)
+///   negative TTL, so SPA routes and scanners probing `/wp-login.php` don't
+///   cost a source round trip every time
+/// - **Eviction**: When the total size exceeds the byte limit, the least
+///   recently used entries are dropped. Assets larger than the whole limit
+///   are never cached.
+///
+/// Source errors are never cached: the next request retries.
+///
+/// The cache lives in `ServerConfig`, which is a process-wide singleton, so
+/// it survives across invocations of a warm Lambda container and is shared
+/// by all connections of the local server.
+pub struct ContentCache {
+    /// Upper bound on the cached bytes (bodies plus per-entry overhead)
+    pub max_bytes: u64,
+    /// How long an asset is served without revalidation
+    pub ttl: Duration,
+    /// How long a missing path is remembered
+    pub negative_ttl: Duration,
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    revalidations: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// Entries plus their recency order
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    /// Recency tick → path; the first entry is the least recently used
+    recency: BTreeMap<u64, String>,
+    next_tick: u64,
+    bytes: u64,
+}
+
+/// A cached lookup result; `asset: None` records a miss at the source
+struct CacheEntry {
+    asset: Option<Asset>,
+    size: u64,
+    stored_at: Instant,
+    tick: u64,
+}
+
+/// What a lookup found in the cache
+enum Lookup {
+    Fresh(Option<Asset>),
+    /// Expired; the cached asset is served if the source confirms it unchanged
+    Stale { etag: String, asset: Asset },
+    Absent,
+}
+
+/// A point-in-time copy of the cache counters
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CacheStats {
+    /// Lookups answered from memory, including negative and revalidated entries
+    pub hits: u64,
+    /// Lookups that had to fetch from the source
+    pub misses: u64,
+    /// Expired entries confirmed unchanged by a conditional request
+    pub revalidations: u64,
+    /// Entries dropped to stay under the byte limit
+    pub evictions: u64,
+    /// Entries currently cached
+    pub entries: usize,
+    /// Bytes currently charged against the limit
+    pub bytes: u64,
+}
+
+impl fmt::Debug for ContentCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContentCache")
+            .field("max_bytes", &self.max_bytes)
+            .field("ttl", &self.ttl)
+            .field("negative_ttl", &self.negative_ttl)
+            .finish()
+    }
+}
+
+impl ContentCache {
+    /// Creates an empty cache
+    pub fn new(max_bytes: u64, ttl: Duration, negative_ttl: Duration) -> ContentCache {
+        ContentCache {
+            max_bytes,
+            ttl,
+            negative_ttl,
+            state: Mutex::new(CacheState::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            revalidations: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Reads an asset through the cache
+    ///
+    /// The lock is never held across a source call, so a slow S3 request
+    /// doesn't block lookups for other paths. Two concurrent misses for the
+    /// same path may both fetch it; the second store simply wins.
+    pub async fn get(&self, source: &ContentSource, path: &str) -> Result<Option<Asset>, ContentError> {
+        match self.lookup(path) {
+            Lookup::Fresh(asset) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                self.log_lookup(if asset.is_some() { "hit" } else { "negative_hit" }, path);
+                Ok(asset)
+            }
+            // The entry may be evicted or cleared while the source is asked, so the
+            // asset confirmed unchanged is the copy taken before the request
+            Lookup::Stale { etag, asset } => match source.revalidate(path, &etag).await? {
+                Revalidation::NotModified => {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    self.revalidations.fetch_add(1, Ordering::Relaxed);
+                    self.refresh(path, &etag);
+                    self.log_lookup("revalidated", path);
+                    Ok(Some(asset))
+                }
+                Revalidation::Modified(asset) => {
+                    self.misses.fetch_add(1, Ordering::Relaxed);
+                    self.store(path, asset.clone());
+                    self.log_lookup("changed", path);
+                    Ok(asset)
+                }
+            },
+            Lookup::Absent => {
+                let asset = source.get(path).await?;
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                self.store(path, asset.clone());
+                self.log_lookup("miss", path);
+                Ok(asset)
+            }
+        }
+    }
+
+    /// Checks whether an asset exists, answering from the cache when possible
+    ///
+    /// Existence checks don't download the asset, so a source answer is not
+    /// stored; only entries already cached by `get` are used.
+    pub async fn exists(&self, source: &ContentSource, path: &str) -> Result<bool, ContentError> {
+        if let Lookup::Fresh(asset) = self.lookup(path) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(asset.is_some());
+        }
+        source.exists(path).await
+    }
+
+    /// Drops every entry, e.g. after the local content directory changed
+    ///
+    /// Counters are kept so the log lines stay cumulative.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.entries.clear();
+        state.recency.clear();
+        state.bytes = 0;
+    }
+
+    /// Returns the current counters
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            revalidations: self.revalidations.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: state.entries.len(),
+            bytes: state.bytes,
+        }
+    }
+
+    /// Finds a path and marks it as recently used
+    fn lookup(&self, path: &str) -> Lookup {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let tick = state.next_tick;
+        let (result, old_tick) = match state.entries.get_mut(path) {
+            None => return Lookup::Absent,
+            Some(entry) => {
+                let ttl = if entry.asset.is_some() { self.ttl } else { self.negative_ttl };
+                let old_tick = entry.tick;
+                entry.tick = tick;
+                let result = if entry.stored_at.elapsed() < ttl {
+                    Lookup::Fresh(entry.asset.clone())
+                } else {
+                    match entry.asset.clone().and_then(|asset| Some((asset.etag.clone()?, asset))) {
+                        Some((etag, asset)) => Lookup::Stale { etag, asset },
+                        None => Lookup::Absent,
+                    }
+                };
+                (result, old_tick)
+            }
+        };
+        state.recency.remove(&old_tick);
+        state.recency.insert(tick, path.to_string());
+        state.next_tick += 1;
+        result
+    }
+
+    /// Restarts the TTL of an entry confirmed unchanged, if it is still cached
+    fn refresh(&self, path: &str, etag: &str) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = state.entries.get_mut(path) {
+            if entry.asset.as_ref().and_then(|asset| asset.etag.as_deref()) == Some(etag) {
+                entry.stored_at = Instant::now();
+            }
+        }
+    }
+
+    /// Inserts or replaces an entry, evicting least recently used entries
+    fn store(&self, path: &str, asset: Option<Asset>) {
+        let size = asset.as_ref().map_or(0, |asset| asset.body.len() as u64) + path.len() as u64 + ENTRY_OVERHEAD_BYTES;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(old) = state.entries.remove(path) {
+            state.recency.remove(&old.tick);
+            state.bytes -= old.size;
+        }
+        if size > self.max_bytes {
+            return;
+        }
+
+        while state.bytes + size > self.max_bytes {
+            let Some((_, oldest)) = state.recency.pop_first() else { break };
+            if let Some(evicted) = state.entries.remove(&oldest) {
+                state.bytes -= evicted.size;
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let tick = state.next_tick;
+        state.next_tick += 1;
+        state.recency.insert(tick, path.to_string());
+        state.bytes += size;
+        state.entries.insert(path.to_string(), CacheEntry { asset, size, stored_at: Instant::now(), tick });
+    }
+
+    /// Logs a lookup together with the running counters
+    fn log_lookup(&self, result: &str, path: &str) {
+        let stats = self.stats();
+        info!("[{}] [CACHE] result={} path={} hits={} misses={} revalidations={} evictions={} entries={} bytes={}",
+              Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+              result,
+              path,
+              stats.hits,
+              stats.misses,
+              stats.revalidations,
+              stats.evictions,
+              stats.entries,
+              stats.bytes);
+    }
+}
@@ -0,0 +1,205 @@
+// URL canonicalization for incoming requests
+// This module decides when a request should be redirected to its canonical URL
+
+use lambda_http::Request;
+use crate::config::{normalize_host, ServerConfig, TrailingSlash};
+
+/// A redirect decision produced by the canonicalization rules
+///
+/// The handler turns this into an HTTP response with a `Location` header
+/// (see `response::create_redirect_response`). The `rule` name is logged so
+/// operators can see which canonicalization fired for a given request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    /// HTTP status code (301 or 308)
+    pub status: u16,
+    /// Absolute or root-relative URL for the `Location` header
+    pub location: String,
+    /// Names of the rules that produced the redirect, joined with `+` (for logging)
+    pub rule: String,
+}
+
+/// Extracts the normalized host name a request was sent to
+///
+/// ## Host Sources (in order of preference):
+///
+/// 1. **X-Forwarded-Host**: Only when `TRUST_FORWARDED_HOST` is enabled. CloudFront
+///    rewrites `Host` to the origin's Function URL domain, so the viewer's host
+///    has to be forwarded in a separate header.
+/// 2. **Host header**: The standard HTTP/1.1 host header
+/// 3. **Request URI authority**: `lambda_http` builds absolute URIs from the
+///    event's domain name when no `Host` header is present
+///
+/// The returned value is lowercase and has any `:port` suffix removed.
+pub fn request_host(request: &Request, config: &ServerConfig) -> Option<String> {
+    let header_value = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            // Proxies may append to the header: "viewer.example.com, proxy.internal"
+            .and_then(|value| value.split(',').next())
+            .map(normalize_host)
+            .filter(|host| !host.is_empty())
+    };
+
+    if config.trust_forwarded_host {
+        if let Some(host) = header_value("x-forwarded-host") {
+            return Some(host);
+        }
+    }
+
+    header_value("host").or_else(|| request.uri().host().map(normalize_host))
+}
+
+/// Returns the scheme the client used, as reported by the edge
+///
+/// Function URLs only accept HTTPS, but CloudFront may reach the origin over
+/// HTTPS on behalf of a viewer that used plain HTTP. CloudFront (and ALB)
+/// report the viewer's scheme in `X-Forwarded-Proto`. Without that header we
+/// assume HTTPS.
+pub fn request_scheme(request: &Request) -> String {
+    request
+        .headers()
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|value| value == "http" || value == "https")
+        .unwrap_or_else(|| "https".to_string())
+}
+
+/// Returns true when the last path segment looks like a file name (`app.js`)
+///
+/// Used to keep trailing-slash rules away from asset URLs: `/app.js/` is
+/// never what anyone meant.
+pub fn looks_like_file(path: &str) -> bool {
+    path.rsplit('/')
+        .next()
+        .map(|segment| segment.contains('.'))
+        .unwrap_or(false)
+}
+
+/// Applies the configured path rules and returns the canonical path
+///
+/// Rules are applied in a fixed order so their results compose:
+///
+/// 1. **Collapse slashes** (`COLLAPSE_SLASHES`): `/docs//intro` → `/docs/intro`
+/// 2. **Lowercase** (`LOWERCASE_PATHS`): `/Docs/Intro` → `/docs/intro`
+/// 3. **Trailing slash** (`TRAILING_SLASH`): `add` turns `/docs` into `/docs/`,
+///    `strip` turns `/docs/` into `/docs`
+///
+/// The returned list names the rules that changed the path, for logging.
+pub fn canonical_path(path: &str, config: &ServerConfig) -> (String, Vec<&'static str>) {
+    let mut canonical = path.to_string();
+    let mut rules = Vec::new();
+
+    if config.collapse_slashes && canonical.contains("//") {
+        let mut collapsed = String::with_capacity(canonical.len());
+        for c in canonical.chars() {
+            if !(c == '/' && collapsed.ends_with('/')) {
+                collapsed.push(c);
+            }
+        }
+        canonical = collapsed;
+        rules.push("collapse_slashes");
+    }
+
+    if config.lowercase_paths && canonical.chars().any(|c| c.is_ascii_uppercase()) {
+        canonical = canonical.to_ascii_lowercase();
+        rules.push("lowercase");
+    }
+
+    match config.trailing_slash {
+        TrailingSlash::Ignore => {}
+        TrailingSlash::Add => {
+            if !canonical.ends_with('/') && !looks_like_file(&canonical) {
+                canonical.push('/');
+                rules.push("trailing_slash");
+            }
+        }
+        TrailingSlash::Strip => {
+            if canonical.len() > 1 && canonical.ends_with('/') {
+                let trimmed = canonical.trim_end_matches('/');
+                canonical = if trimmed.is_empty() { "/".to_string() } else { trimmed.to_string() };
+                rules.push("trailing_slash");
+            }
+        }
+    }
+
+    (canonical, rules)
+}
+
+/// Returns a redirect to the canonical URL when the request deviates from it
+///
+/// All canonicalization rules are evaluated together so the client gets a
+/// single redirect instead of a chain:
+///
+/// - **Canonical host** (`CANONICAL_HOST`): a request to
+///   `https://abc123.lambda-url.eu-west-1.on.aws/blog?page=2` is answered with
+///   `Location: https://www.example.com/blog?page=2`
+/// - **HTTPS** (`FORCE_HTTPS`): `X-Forwarded-Proto: http` is upgraded to `https`,
+///   as long as the request names a host to redirect to
+/// - **Path rules**: see `canonical_path`
+///
+/// The query string is always preserved exactly so deep links keep working.
+/// The status code is `REDIRECT_STATUS` (301 by default, or 308).
+///
+/// This runs after `sanitize_path`, so `path` is known to be free of traversal
+/// sequences. Redirects that only change the path use a root-relative
+/// `Location`; host and scheme changes always target `https`.
+pub fn canonical_redirect(
+    host: Option<&str>,
+    scheme: &str,
+    path: &str,
+    query: Option<&str>,
+    config: &ServerConfig,
+) -> Option<Redirect> {
+    let mut rules = Vec::new();
+
+    let canonical_host = match (host, config.canonical_host.as_deref()) {
+        (Some(host), Some(canonical)) if host != canonical => {
+            rules.push("canonical_host");
+            Some(canonical)
+        }
+        _ => None,
+    };
+
+    // A scheme change needs an absolute URL; without a host there is nothing to build
+    // it from, and a root-relative Location would send the client straight back here
+    let upgrade_scheme = config.force_https && scheme == "http" && host.is_some();
+    if upgrade_scheme {
+        rules.push("https");
+    }
+
+    let (target_path, path_rules) = canonical_path(path, config);
+    rules.extend(path_rules);
+
+    if rules.is_empty() {
+        return None;
+    }
+
+    // Only build an absolute URL when the origin changes; path-only redirects stay
+    // root-relative so they work behind any proxy and on the local dev server's port
+    let location = match (canonical_host.or(host), canonical_host.is_some() || upgrade_scheme) {
+        (Some(target_host), true) => build_location("https", target_host, &target_path, query),
+        _ => match query {
+            Some(query) if !query.is_empty() => format!("{}?{}", target_path, query),
+            _ => target_path,
+        },
+    };
+
+    Some(Redirect {
+        status: config.redirect_status,
+        location,
+        rule: rules.join("+"),
+    })
+}
+
+/// Builds an absolute URL from its parts, appending the query string if present
+fn build_location(scheme: &str, host: &str, path: &str, query: Option<&str>) -> String {
+    match query {
+        Some(query) if !query.is_empty() => format!("{}://{}{}?{}", scheme, host, path, query),
+        _ => format!("{}://{}{}", scheme, host, path),
+    }
+}
@@ -0,0 +1,504 @@
+// Runtime configuration for the static web server
+// This module reads deployment settings from environment variables once per container
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use log::{error, info, warn};
+
+use crate::access_log::AccessLogFormat;
+use crate::archive::{ArchiveSource, EMBEDDED_ARCHIVE};
+use crate::cache::{ContentCache, DEFAULT_CACHE_MAX_BYTES, DEFAULT_CACHE_NEGATIVE_TTL, DEFAULT_CACHE_TTL};
+use crate::content::{Candidate, ContentSource};
+use crate::logging::LogSampling;
+use crate::metrics::DEFAULT_NAMESPACE;
+use crate::rules::RuleSet;
+use crate::s3::{S3Source, DEFAULT_MAX_OBJECT_BYTES};
+use crate::slo::{SlowRequests, DEFAULT_LATENCY_WINDOW, DEFAULT_SLOW_REQUEST, DEFAULT_SUMMARY_INTERVAL};
+
+/// Server configuration loaded from environment variables
+///
+/// AWS Lambda functions are configured through environment variables set in
+/// `terraform/lambda.tf`, and the same variables work for `--mode local`.
+/// The configuration is read once per container (see `ServerConfig::global`)
+/// so warm invocations don't pay the parsing cost again.
+///
+/// ## Environment Variables:
+///
+/// - `ALLOWED_HOSTS`: Comma-separated list of hosts this server answers for.
+///   Entries may use a leading wildcard (`*.example.com`). Empty means any host.
+/// - `CANONICAL_HOST`: Primary domain. Requests for any other host are
+///   redirected here with 301, preserving path and query.
+/// - `TRUST_FORWARDED_HOST`: When `true`, the `X-Forwarded-Host` header set by
+///   CloudFront takes precedence over `Host`. Only enable this behind a proxy
+///   that overwrites the header.
+/// - `FORCE_HTTPS`: When `true`, requests marked `X-Forwarded-Proto: http` are
+///   redirected to `https`.
+/// - `TRAILING_SLASH`: `add` to enforce a trailing slash on directory-style
+///   paths, `strip` to remove it, or `ignore` (default).
+/// - `LOWERCASE_PATHS`: When `true`, paths containing uppercase letters are
+///   redirected to their lowercase form.
+/// - `COLLAPSE_SLASHES`: When `true`, repeated slashes (`//`) are collapsed.
+/// - `REDIRECT_STATUS`: Status code for canonicalization redirects, `301`
+///   (default) or `308`.
+/// - `REDIRECTS_FILE`: Path to a Netlify-style `_redirects` rules file
+///   (see `rules::RuleSet`). Loaded once at startup.
+/// - `CONTENT_DIR`: Directory to serve static assets from. Unset means only
+///   the embedded `index.html` is served.
+/// - `CONTENT_S3_BUCKET`, `CONTENT_S3_PREFIX`, `CONTENT_S3_ENDPOINT`,
+///   `S3_MAX_OBJECT_BYTES`: Serve content from S3 instead (see `s3::S3Source`).
+///   Cannot be combined with `CONTENT_DIR`.
+/// - `CONTENT_ARCHIVE`: Path to a zip, tar or tar.zst site bundle (see
+///   `archive::ArchiveSource`). A bundle compiled in with the
+///   `embedded-archive` feature is used when no content source is set.
+/// - `CACHE_MAX_BYTES`: Memory budget of the content cache for `CONTENT_DIR`
+///   and S3 content (default 32 MiB, `0` disables it; see `cache::ContentCache`).
+/// - `CACHE_TTL_SECONDS`: How long cached assets are served before being
+///   revalidated (default 60).
+/// - `CACHE_NEGATIVE_TTL_SECONDS`: How long missing paths are remembered
+///   (default 10).
+/// - `INDEX_FILE`: File served for directory paths (default `index.html`).
+/// - `RESOLUTION_ORDER`: Comma-separated clean URL lookup order for paths
+///   without a trailing slash: `exact`, `html` (`/about.html`) and `index`
+///   (`/about/index.html`). Default `exact,html,index`.
+/// - `DIRECTORY_LISTING`: Comma-separated path prefixes whose directories are
+///   listed when they have no index (e.g. `/artifacts`). Empty (default) disables
+///   listings.
+/// - `SPA_FALLBACK`: When `true` (default), unknown paths that don't look like
+///   files are answered with `SPA_INDEX` and 200 (see `content::resolve`).
+/// - `SPA_INDEX`: Entry point served by the SPA fallback (default `/index.html`).
+/// - `SPA_EXCLUDE`: Comma-separated path prefixes that never fall back
+///   (e.g. `/api,/static`); they get 404 instead.
+/// - `EMF_METRICS`: When `true`, every request writes a CloudWatch Embedded
+///   Metric Format document to stdout (see `metrics::emf_document`).
+/// - `METRICS_NAMESPACE`: CloudWatch namespace of those metrics (default
+///   `StaticWebLambda`).
+/// - `ACCESS_LOG`: `common`, `combined` or a `$variable` template to write
+///   one access log line per request to stdout (see `access_log`). Off by
+///   default.
+/// - `TRUST_FORWARDED_FOR`: When `true`, the first `X-Forwarded-For` address
+///   is the client IP in the access log. Only enable this behind a proxy
+///   that sets the header.
+/// - `HEALTH_CHECKS`: When `true` (default), `/healthz` and `/readyz` are
+///   answered by the server itself (see `health`). Set to `false` if the site
+///   has its own pages at those paths.
+/// - `LOG_SAMPLE_RATE`: Share of requests whose info-level lines are written,
+///   as a fraction (`0.01`) or percentage (`1%`). Default `1` (every request;
+///   see `logging::LogSampling`).
+/// - `LOG_SUPPRESS_PATHS`: Comma-separated path prefixes whose info-level
+///   lines are never written (e.g. `/favicon.ico,/assets`).
+/// - `SERVER_TIMING`: When `true`, every response carries a `Server-Timing`
+///   header with stage durations (see `server_timing`).
+/// - `PREWARM`: When `true`, the root page and SPA index are loaded during the
+///   init phase instead of on the first request (see `lifecycle::prewarm`).
+/// - `SLOW_REQUEST_MS`: Requests slower than this are logged as slow
+///   (default 1000).
+/// - `SLOW_REQUEST_ROUTES`: Comma-separated `prefix=ms` thresholds overriding
+///   `SLOW_REQUEST_MS` (e.g. `/assets=5,/=20`; see `slo::SlowRequests`).
+/// - `LATENCY_WINDOW`: Requests kept for the rolling p50/p95/p99 summary
+///   (default 1000, `0` disables it; see `slo::LatencyWindow`).
+/// - `LATENCY_SUMMARY_SECONDS`: How often the local server logs that summary
+///   (default 60).
+///
+/// ## Defaults:
+///
+/// Every option defaults to "off" so an unconfigured deployment behaves exactly
+/// like the original single-page server. SPA fallback defaults to on, since the
+/// original server answered every path with its page, and so do the health
+/// check routes, since load balancers need them before anyone configures them.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Lowercase host names (without port) accepted by the server
+    pub allowed_hosts: Vec<String>,
+    /// Lowercase primary host that other hosts are redirected to
+    pub canonical_host: Option<String>,
+    /// Whether `X-Forwarded-Host` is trusted to carry the viewer's host
+    pub trust_forwarded_host: bool,
+    /// Redirect `http` requests (per `X-Forwarded-Proto`) to `https`
+    pub force_https: bool,
+    /// Trailing slash policy for request paths
+    pub trailing_slash: TrailingSlash,
+    /// Redirect paths with uppercase letters to lowercase
+    pub lowercase_paths: bool,
+    /// Collapse repeated slashes in request paths
+    pub collapse_slashes: bool,
+    /// Status code used for canonicalization redirects (301 or 308)
+    pub redirect_status: u16,
+    /// Redirect and rewrite rules from `REDIRECTS_FILE`
+    pub redirect_rules: RuleSet,
+    /// Where static assets are read from
+    pub content_source: ContentSource,
+    /// In-memory cache in front of `content_source`, shared by clones of this config
+    pub content_cache: Option<Arc<ContentCache>>,
+    /// File name served for directory paths
+    pub index_file: String,
+    /// Clean URL lookup order for paths without a trailing slash
+    pub resolution_order: Vec<Candidate>,
+    /// Path prefixes where directories without an index are listed
+    pub listing_prefixes: Vec<String>,
+    /// Serve `spa_index` for unknown non-file paths
+    pub spa_fallback: bool,
+    /// Root-relative path of the SPA entry point
+    pub spa_index: String,
+    /// Path prefixes excluded from SPA fallback
+    pub spa_exclude: Vec<String>,
+    /// CloudWatch namespace for EMF metrics; `None` disables them
+    pub metrics_namespace: Option<String>,
+    /// Answer `/healthz` and `/readyz` before the request pipeline
+    pub health_checks: bool,
+    /// Access log line format; `None` disables the access log
+    pub access_log: Option<AccessLogFormat>,
+    /// Whether `X-Forwarded-For` is trusted to carry the client IP
+    pub trust_forwarded_for: bool,
+    /// Which requests have their info-level log lines written
+    pub log_sampling: LogSampling,
+    /// Add a `Server-Timing` header with stage durations to every response
+    pub server_timing: bool,
+    /// Load the root page (and SPA index) during the init phase
+    pub prewarm: bool,
+    /// Latency above which a request is logged as slow, per path prefix
+    pub slow_requests: SlowRequests,
+    /// Requests kept for the rolling latency summary; 0 disables it
+    pub latency_window: usize,
+    /// How often the local server logs the latency summary
+    pub latency_summary_interval: Duration,
+}
+
+/// Trailing slash canonicalization policy
+///
+/// Serving both `/docs` and `/docs/` splits link equity between two URLs.
+/// Pick one form and redirect the other to it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TrailingSlash {
+    /// Leave paths as requested (default)
+    #[default]
+    Ignore,
+    /// Redirect `/docs` to `/docs/` (paths that look like files are left alone)
+    Add,
+    /// Redirect `/docs/` to `/docs` (the root path `/` is left alone)
+    Strip,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            allowed_hosts: Vec::new(),
+            canonical_host: None,
+            trust_forwarded_host: false,
+            force_https: false,
+            trailing_slash: TrailingSlash::Ignore,
+            lowercase_paths: false,
+            collapse_slashes: false,
+            redirect_status: 301,
+            redirect_rules: RuleSet::default(),
+            content_source: ContentSource::Embedded,
+            content_cache: None,
+            index_file: "index.html".to_string(),
+            resolution_order: DEFAULT_RESOLUTION_ORDER.to_vec(),
+            listing_prefixes: Vec::new(),
+            spa_fallback: true,
+            spa_index: "/index.html".to_string(),
+            spa_exclude: Vec::new(),
+            metrics_namespace: None,
+            health_checks: true,
+            access_log: None,
+            trust_forwarded_for: false,
+            log_sampling: LogSampling::default(),
+            server_timing: false,
+            prewarm: false,
+            slow_requests: SlowRequests::default(),
+            latency_window: DEFAULT_LATENCY_WINDOW,
+            latency_summary_interval: DEFAULT_SUMMARY_INTERVAL,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Builds the configuration from the process environment
+    ///
+    /// Invalid scalar values are logged and replaced by their defaults rather
+    /// than failing the cold start, since a misconfigured optional feature
+    /// should never take the site down. The redirect rules file and content
+    /// directory are the exception: a broken file or missing directory is an
+    /// error, because silently dropping redirects or content would break every
+    /// link that depends on them.
+    pub fn from_env() -> Result<Self, String> {
+        let redirect_rules = match env_string("REDIRECTS_FILE") {
+            None => RuleSet::default(),
+            Some(path) => RuleSet::load(Path::new(&path)).map_err(|errors| {
+                let details: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                format!("Invalid redirect rules in {}: {}", path, details.join("; "))
+            })?,
+        };
+
+        let content_source = match (
+            env_string("CONTENT_DIR"),
+            env_string("CONTENT_S3_BUCKET"),
+            env_string("CONTENT_ARCHIVE"),
+        ) {
+            (None, None, None) => match EMBEDDED_ARCHIVE {
+                Some(bytes) => ContentSource::Archive(ArchiveSource::from_bytes("embedded", bytes)?),
+                None => ContentSource::Embedded,
+            },
+            (Some(dir), None, None) if Path::new(&dir).is_dir() => ContentSource::Directory(PathBuf::from(dir)),
+            (Some(dir), None, None) => return Err(format!("CONTENT_DIR {} is not a directory", dir)),
+            (None, None, Some(path)) => ContentSource::Archive(ArchiveSource::load(Path::new(&path))?),
+            (None, Some(bucket), None) => {
+                let max_object_bytes = match env_string("S3_MAX_OBJECT_BYTES").map(|v| v.parse::<u64>()) {
+                    None => DEFAULT_MAX_OBJECT_BYTES,
+                    Some(Ok(bytes)) if bytes > 0 => bytes,
+                    Some(_) => {
+                        warn!("Ignoring invalid S3_MAX_OBJECT_BYTES value (using {})", DEFAULT_MAX_OBJECT_BYTES);
+                        DEFAULT_MAX_OBJECT_BYTES
+                    }
+                };
+                ContentSource::S3(S3Source::new(
+                    &bucket,
+                    &env_string("CONTENT_S3_PREFIX").unwrap_or_default(),
+                    env_string("CONTENT_S3_ENDPOINT"),
+                    max_object_bytes,
+                ))
+            }
+            _ => {
+                return Err("CONTENT_DIR, CONTENT_S3_BUCKET and CONTENT_ARCHIVE are mutually exclusive".to_string());
+            }
+        };
+
+        // Embedded content and bundles are already in memory; only disk and S3 benefit from caching
+        let content_cache = match content_source {
+            ContentSource::Embedded | ContentSource::Archive(_) => None,
+            _ => {
+                let max_bytes = env_u64("CACHE_MAX_BYTES", DEFAULT_CACHE_MAX_BYTES);
+                let ttl = env_u64("CACHE_TTL_SECONDS", DEFAULT_CACHE_TTL.as_secs());
+                let negative_ttl = env_u64("CACHE_NEGATIVE_TTL_SECONDS", DEFAULT_CACHE_NEGATIVE_TTL.as_secs());
+                (max_bytes > 0).then(|| {
+                    Arc::new(ContentCache::new(max_bytes, Duration::from_secs(ttl), Duration::from_secs(negative_ttl)))
+                })
+            }
+        };
+
+        let spa_index = env_string("SPA_INDEX").unwrap_or_else(|| "/index.html".to_string());
+        if !spa_index.starts_with('/') {
+            return Err(format!("SPA_INDEX {} must start with /", spa_index));
+        }
+
+        let index_file = match env_string("INDEX_FILE") {
+            Some(name) if name.contains('/') || name == ".." => {
+                warn!("Ignoring invalid INDEX_FILE value: {} (using index.html)", name);
+                "index.html".to_string()
+            }
+            Some(name) => name,
+            None => "index.html".to_string(),
+        };
+
+        let resolution_order = match env_string("RESOLUTION_ORDER") {
+            None => DEFAULT_RESOLUTION_ORDER.to_vec(),
+            Some(value) => {
+                let parsed: Option<Vec<Candidate>> = value.split(',').map(Candidate::parse).collect();
+                match parsed {
+                    Some(order) if !order.is_empty() => order,
+                    _ => {
+                        warn!("Ignoring invalid RESOLUTION_ORDER value: {} (using exact,html,index)", value);
+                        DEFAULT_RESOLUTION_ORDER.to_vec()
+                    }
+                }
+            }
+        };
+
+        let config = ServerConfig {
+            allowed_hosts: env_list("ALLOWED_HOSTS")
+                .into_iter()
+                .map(|host| normalize_host(&host))
+                .collect(),
+            canonical_host: env_string("CANONICAL_HOST").map(|host| normalize_host(&host)),
+            trust_forwarded_host: env_bool("TRUST_FORWARDED_HOST", false),
+            force_https: env_bool("FORCE_HTTPS", false),
+            trailing_slash: match env_string("TRAILING_SLASH").map(|v| v.to_ascii_lowercase()).as_deref() {
+                None | Some("ignore") => TrailingSlash::Ignore,
+                Some("add") | Some("enforce") => TrailingSlash::Add,
+                Some("strip") | Some("remove") => TrailingSlash::Strip,
+                Some(other) => {
+                    warn!("Ignoring invalid TRAILING_SLASH value: {} (using ignore)", other);
+                    TrailingSlash::Ignore
+                }
+            },
+            lowercase_paths: env_bool("LOWERCASE_PATHS", false),
+            collapse_slashes: env_bool("COLLAPSE_SLASHES", false),
+            redirect_status: match env_string("REDIRECT_STATUS").as_deref() {
+                None | Some("301") => 301,
+                Some("308") => 308,
+                Some(other) => {
+                    warn!("Ignoring invalid REDIRECT_STATUS value: {} (using 301)", other);
+                    301
+                }
+            },
+            redirect_rules,
+            content_source,
+            content_cache,
+            index_file,
+            resolution_order,
+            listing_prefixes: env_list("DIRECTORY_LISTING"),
+            spa_fallback: env_bool("SPA_FALLBACK", true),
+            spa_index,
+            spa_exclude: env_list("SPA_EXCLUDE"),
+            metrics_namespace: env_bool("EMF_METRICS", false)
+                .then(|| env_string("METRICS_NAMESPACE").unwrap_or_else(|| DEFAULT_NAMESPACE.to_string())),
+            health_checks: env_bool("HEALTH_CHECKS", true),
+            access_log: env_string("ACCESS_LOG").and_then(|value| {
+                AccessLogFormat::parse(&value).unwrap_or_else(|e| {
+                    warn!("Ignoring invalid ACCESS_LOG value: {} (access log disabled)", e);
+                    None
+                })
+            }),
+            trust_forwarded_for: env_bool("TRUST_FORWARDED_FOR", false),
+            log_sampling: LogSampling {
+                rate: match env_string("LOG_SAMPLE_RATE") {
+                    None => 1.0,
+                    Some(value) => LogSampling::parse_rate(&value).unwrap_or_else(|e| {
+                        warn!("Ignoring invalid LOG_SAMPLE_RATE value: {} (logging every request)", e);
+                        1.0
+                    }),
+                },
+                suppress: env_list("LOG_SUPPRESS_PATHS"),
+            },
+            server_timing: env_bool("SERVER_TIMING", false),
+            prewarm: env_bool("PREWARM", false),
+            slow_requests: SlowRequests {
+                default: Duration::from_millis(env_u64("SLOW_REQUEST_MS", DEFAULT_SLOW_REQUEST.as_millis() as u64)),
+                routes: env_string("SLOW_REQUEST_ROUTES")
+                    .map(|value| {
+                        SlowRequests::parse_routes(&value).unwrap_or_else(|e| {
+                            warn!("Ignoring invalid SLOW_REQUEST_ROUTES entry: {} (using SLOW_REQUEST_MS for every path)", e);
+                            Vec::new()
+                        })
+                    })
+                    .unwrap_or_default(),
+            },
+            latency_window: env_u64("LATENCY_WINDOW", DEFAULT_LATENCY_WINDOW as u64) as usize,
+            latency_summary_interval: Duration::from_secs(env_u64("LATENCY_SUMMARY_SECONDS", DEFAULT_SUMMARY_INTERVAL.as_secs()).max(1)),
+        };
+
+        info!("Loaded server configuration: {:?}", config);
+
+        Ok(config)
+    }
+
+    /// Loads the process-wide configuration at startup
+    ///
+    /// `main` calls this before starting the Lambda runtime or the local server
+    /// so configuration errors stop the process during the init phase instead
+    /// of surfacing on the first request.
+    pub fn init_global() -> Result<&'static ServerConfig, String> {
+        if let Some(config) = GLOBAL_CONFIG.get() {
+            return Ok(config);
+        }
+        let config = ServerConfig::from_env()?;
+        Ok(GLOBAL_CONFIG.get_or_init(|| config))
+    }
+
+    /// Returns the process-wide configuration, loading it on first use
+    ///
+    /// Lambda keeps the process alive between invocations of a warm container,
+    /// so the configuration is parsed once (normally by `init_global` at
+    /// startup) and reused afterwards. If loading fails here, the error is
+    /// logged and the defaults are used so requests are still answered.
+    pub fn global() -> &'static ServerConfig {
+        GLOBAL_CONFIG.get_or_init(|| {
+            ServerConfig::from_env().unwrap_or_else(|e| {
+                error!("Failed to load server configuration, using defaults: {}", e);
+                GLOBAL_FALLBACK.store(true, Ordering::Relaxed);
+                ServerConfig::default()
+            })
+        })
+    }
+}
+
+/// Returns true when the process-wide configuration failed to load and the
+/// defaults are being served instead (see `ServerConfig::global`)
+///
+/// `/readyz` reports this so a broken deploy is taken out of rotation
+/// instead of serving the placeholder page.
+pub fn global_fallback() -> bool {
+    GLOBAL_FALLBACK.load(Ordering::Relaxed)
+}
+
+/// Clean URL lookup order used when `RESOLUTION_ORDER` is unset
+const DEFAULT_RESOLUTION_ORDER: [Candidate; 3] = [Candidate::Exact, Candidate::Html, Candidate::Index];
+
+/// Process-wide configuration shared by all invocations in a container
+static GLOBAL_CONFIG: OnceLock<ServerConfig> = OnceLock::new();
+
+/// Set when `ServerConfig::global` fell back to the defaults
+static GLOBAL_FALLBACK: AtomicBool = AtomicBool::new(false);
+
+/// Lowercases a host and strips any `:port` suffix for comparison
+///
+/// Host names are case-insensitive (RFC 3986 section 3.2.2), and the local
+/// development server sees ports in the `Host` header (`127.0.0.1:3000`).
+/// IPv6 literals such as `[::1]:3000` keep their brackets.
+pub fn normalize_host(host: &str) -> String {
+    let host = host.trim().to_ascii_lowercase();
+
+    if host.starts_with('[') {
+        // IPv6 literal: keep everything up to the closing bracket
+        return match host.find(']') {
+            Some(end) => host[..=end].to_string(),
+            None => host,
+        };
+    }
+
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name.to_string(),
+        _ => host,
+    }
+}
+
+/// Reads a non-empty, trimmed string environment variable
+fn env_string(name: &str) -> Option<String> {
+    env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Reads a comma-separated list environment variable, skipping empty entries
+fn env_list(name: &str) -> Vec<String> {
+    env_string(name)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads a non-negative integer environment variable
+fn env_u64(name: &str, default: u64) -> u64 {
+    match env_string(name) {
+        None => default,
+        Some(value) => value.parse().unwrap_or_else(|_| {
+            warn!("Ignoring invalid value for {}: {} (using {})", name, value, default);
+            default
+        }),
+    }
+}
+
+/// Reads a boolean environment variable (`true/false`, `1/0`, `yes/no`, `on/off`)
+fn env_bool(name: &str, default: bool) -> bool {
+    match env_string(name) {
+        None => default,
+        Some(value) => match value.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => true,
+            "false" | "0" | "no" | "off" => false,
+            other => {
+                warn!("Ignoring invalid boolean value for {}: {} (using {})", name, other, default);
+                default
+            }
+        },
+    }
+}
@@ -0,0 +1,461 @@
+// Static asset lookup for the web server
+// This module maps sanitized request paths to files, directory listings and SPA fallback
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::archive::ArchiveSource;
+use crate::canonical::looks_like_file;
+use crate::config::ServerConfig;
+use crate::listing::DirectoryEntry;
+use crate::response::{ApplicationError, HTML_CONTENT};
+use crate::s3::S3Source;
+
+/// A file ready to be served
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asset {
+    /// Root-relative path the asset was found at (`/assets/app.js`)
+    pub path: String,
+    /// Raw file contents
+    pub body: Vec<u8>,
+    /// MIME type derived from the file extension
+    pub content_type: &'static str,
+    /// Entity tag reported by the source, sent as `ETag`
+    pub etag: Option<String>,
+    /// Modification time reported by the source, sent as `Last-Modified`
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// A failure reading from a content source
+///
+/// "Not found" is not an error (sources return `Ok(None)`); these are the
+/// cases where the answer is unknown, so the handler must not serve a 404 or
+/// fall back to the SPA entry point.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentError {
+    /// Local I/O failure (permissions, disk errors)
+    Io { path: String, cause: String },
+    /// Remote source failure (S3 errors, throttling, timeouts)
+    Unavailable { path: String, reason: String, retry_after: Option<u32> },
+    /// The object exceeds the size that can be returned through Lambda
+    TooLarge { path: String, size: u64 },
+}
+
+impl ContentError {
+    /// Converts the failure into the error response the client should see
+    ///
+    /// - **Io**, **TooLarge**: 500 Internal Server Error
+    /// - **Unavailable**: 503 Service Unavailable with `Retry-After`
+    pub fn to_application_error(&self) -> ApplicationError {
+        match self {
+            ContentError::Io { path, cause } => ApplicationError::InternalError {
+                details: format!("Failed to read content for {}", path),
+                cause: Some(cause.clone()),
+            },
+            ContentError::Unavailable { reason, retry_after, .. } => ApplicationError::ServiceUnavailable {
+                reason: reason.clone(),
+                retry_after: *retry_after,
+            },
+            ContentError::TooLarge { path, size } => ApplicationError::InternalError {
+                details: format!("Content for {} is too large to serve", path),
+                cause: Some(format!("{} bytes", size)),
+            },
+        }
+    }
+
+    /// Wraps a local I/O error
+    fn io(path: &str, error: io::Error) -> ContentError {
+        ContentError::Io { path: path.to_string(), cause: error.to_string() }
+    }
+}
+
+/// The answer to a conditional read (see `ContentSource::revalidate`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Revalidation {
+    /// The asset still has the given ETag
+    NotModified,
+    /// The asset changed or disappeared; this is its current state
+    Modified(Option<Asset>),
+}
+
+/// Where static assets are read from
+///
+/// ## Sources:
+///
+/// - **Embedded** (default): Only the `index.html` compiled into the binary,
+///   which is what the server has always shipped with
+/// - **Directory** (`CONTENT_DIR`): A directory on disk, e.g. a React build
+///   output packaged next to `bootstrap` in the Lambda ZIP
+/// - **S3** (`CONTENT_S3_BUCKET`): A bucket and prefix, so a site deploy is
+///   an upload instead of a code deploy (see `s3::S3Source`)
+/// - **Archive** (`CONTENT_ARCHIVE` or the `embedded-archive` feature): A zip
+///   or tar(.zst) bundle indexed in memory at startup (see
+///   `archive::ArchiveSource`)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ContentSource {
+    #[default]
+    Embedded,
+    Directory(PathBuf),
+    S3(S3Source),
+    Archive(ArchiveSource),
+}
+
+impl ContentSource {
+    /// Reads the asset at a sanitized, root-relative path
+    ///
+    /// Returns `Ok(None)` when the path doesn't exist or names a directory.
+    /// Other failures (permissions, disk failures, S3 outages) are returned
+    /// so the handler can answer 500/503 instead of pretending the file is
+    /// missing.
+    ///
+    /// The path must already have passed `security::sanitize_path`; it is
+    /// joined segment by segment and `.`/`..` segments are skipped anyway
+    /// so a lookup can never leave the content root.
+    pub async fn get(&self, path: &str) -> Result<Option<Asset>, ContentError> {
+        match self {
+            ContentSource::Embedded => {
+                if path == "/index.html" {
+                    Ok(Some(Asset {
+                        path: path.to_string(),
+                        body: HTML_CONTENT.as_bytes().to_vec(),
+                        content_type: "text/html",
+                        etag: None,
+                        last_modified: None,
+                    }))
+                } else {
+                    Ok(None)
+                }
+            }
+            ContentSource::Directory(root) => {
+                let file_path = join_content_path(root, path);
+                match fs::read(&file_path) {
+                    Ok(body) => {
+                        let metadata = fs::metadata(&file_path).ok();
+                        Ok(Some(Asset {
+                            path: path.to_string(),
+                            body,
+                            content_type: content_type_for(path),
+                            etag: metadata.as_ref().map(file_etag),
+                            last_modified: metadata
+                                .and_then(|metadata| metadata.modified().ok())
+                                .map(DateTime::<Utc>::from),
+                        }))
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                    // Reading a directory fails with an OS-specific error; treat it as missing
+                    Err(_) if file_path.is_dir() => Ok(None),
+                    Err(e) => Err(ContentError::io(path, e)),
+                }
+            }
+            ContentSource::S3(source) => source.get(path).await,
+            ContentSource::Archive(archive) => Ok(archive.get(path)),
+        }
+    }
+
+    /// Reads an asset only if it no longer has the given ETag
+    ///
+    /// Used by `cache::ContentCache` to revalidate expired entries: S3 gets a
+    /// conditional GetObject (`If-None-Match`), files on disk a metadata
+    /// check, so an unchanged asset is never downloaded again.
+    pub async fn revalidate(&self, path: &str, etag: &str) -> Result<Revalidation, ContentError> {
+        match self {
+            ContentSource::Directory(root) => match fs::metadata(join_content_path(root, path)) {
+                Ok(metadata) if metadata.is_file() && file_etag(&metadata) == etag => Ok(Revalidation::NotModified),
+                _ => Ok(Revalidation::Modified(self.get(path).await?)),
+            },
+            ContentSource::S3(source) => source.revalidate(path, etag).await,
+            ContentSource::Embedded | ContentSource::Archive(_) => Ok(Revalidation::Modified(self.get(path).await?)),
+        }
+    }
+
+    /// Checks whether an asset exists without reading it
+    ///
+    /// Used for directory indexes that only trigger a redirect, so S3 can
+    /// answer with a HeadObject instead of a full download.
+    pub async fn exists(&self, path: &str) -> Result<bool, ContentError> {
+        match self {
+            ContentSource::Embedded => Ok(path == "/index.html"),
+            ContentSource::Directory(root) => Ok(join_content_path(root, path).is_file()),
+            ContentSource::S3(source) => source.exists(path).await,
+            ContentSource::Archive(archive) => Ok(archive.exists(path)),
+        }
+    }
+
+    /// Lists the entries of a directory at a sanitized, root-relative path
+    ///
+    /// Returns `Ok(None)` when the path is not a directory. Dotfiles are
+    /// hidden so `.env` or `.git` left in a build output aren't advertised.
+    pub async fn list(&self, path: &str) -> Result<Option<Vec<DirectoryEntry>>, ContentError> {
+        match self {
+            ContentSource::Embedded => Ok(None),
+            ContentSource::Directory(root) => {
+                let dir_path = join_content_path(root, path);
+                let read_dir = match fs::read_dir(&dir_path) {
+                    Ok(read_dir) => read_dir,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                    Err(_) if !dir_path.is_dir() => return Ok(None),
+                    Err(e) => return Err(ContentError::io(path, e)),
+                };
+
+                let mut entries = Vec::new();
+                for entry in read_dir {
+                    let entry = entry.map_err(|e| ContentError::io(path, e))?;
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    if name.starts_with('.') {
+                        continue;
+                    }
+                    let metadata = entry.metadata().map_err(|e| ContentError::io(path, e))?;
+                    entries.push(DirectoryEntry {
+                        name,
+                        is_dir: metadata.is_dir(),
+                        size: if metadata.is_dir() { 0 } else { metadata.len() },
+                        modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+                    });
+                }
+                Ok(Some(entries))
+            }
+            ContentSource::S3(source) => source.list(path).await,
+            ContentSource::Archive(archive) => Ok(archive.list(path)),
+        }
+    }
+
+    /// Returns a URL the client can fetch a too-large asset from directly
+    ///
+    /// Only S3 supports this (presigned GetObject URLs); other sources
+    /// return `None`.
+    pub async fn external_url(&self, path: &str) -> Option<String> {
+        match self {
+            ContentSource::S3(source) => source.presign(path).await.ok(),
+            _ => None,
+        }
+    }
+}
+
+/// One step of clean URL resolution (see `resolve`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Candidate {
+    /// The path as requested: `/about` → `/about`
+    Exact,
+    /// The path with `.html` appended: `/about` → `/about.html`
+    Html,
+    /// The directory index: `/about` → `/about/index.html`
+    Index,
+}
+
+impl Candidate {
+    /// Parses a `RESOLUTION_ORDER` entry
+    pub fn parse(value: &str) -> Option<Candidate> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "exact" => Some(Candidate::Exact),
+            "html" => Some(Candidate::Html),
+            "index" => Some(Candidate::Index),
+            _ => None,
+        }
+    }
+}
+
+/// The outcome of looking up a request path
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// The path matched an asset
+    Found(Asset),
+    /// The path names a directory but lacks the trailing slash; redirect here
+    /// so relative links inside the directory's index resolve correctly
+    DirectoryRedirect(String),
+    /// The path is a directory without an index under a `DIRECTORY_LISTING` prefix
+    Listing(Vec<DirectoryEntry>),
+    /// The path matched nothing and the SPA entry point is served instead
+    SpaFallback(Asset),
+    /// Nothing to serve
+    NotFound,
+}
+
+/// Resolves a sanitized request path to an asset, applying SPA fallback
+///
+/// ## Resolution Order:
+///
+/// 1. Paths ending in `/` are looked up as `<path><INDEX_FILE>` only
+/// 2. Other paths try each step of `RESOLUTION_ORDER` (default
+///    `exact,html,index`), so `/about` can be served by `/about`,
+///    `/about.html` or `/about/index.html`. File-like paths (`/app.js`) only
+///    try `exact`. When `index` matches, the client is sent to `/about/`
+///    instead (unless `redirect_directories` is false), because relative
+///    links in `/about/index.html` would otherwise resolve against `/`.
+/// 3. **Directory listing** (`DIRECTORY_LISTING`): directories without an
+///    index under a configured prefix are listed (off by default)
+/// 4. **SPA fallback** (`SPA_FALLBACK`): client-side routes such as
+///    `/dashboard/settings` get `SPA_INDEX` with 200 so the app's router can
+///    handle them. Paths that look like files (`/missing.js`) and paths under
+///    `SPA_EXCLUDE` prefixes (`/api/`) still get 404, otherwise a missing
+///    bundle would be answered with HTML and fail with a confusing MIME error.
+///
+/// `redirect_directories` is false for rewritten requests (the client never
+/// saw the rewrite target) and when `TRAILING_SLASH=strip` is configured,
+/// which would otherwise bounce the client between `/about` and `/about/`.
+pub async fn resolve(path: &str, config: &ServerConfig, redirect_directories: bool) -> Result<Resolution, ContentError> {
+    // Authority-form URIs (`example.com`) have an empty path; treat it as the root
+    let path = if path.is_empty() { "/" } else { path };
+
+    if path.ends_with('/') {
+        let index_path = format!("{}{}", path, config.index_file);
+        if let Some(asset) = fetch(&index_path, config).await? {
+            return Ok(Resolution::Found(asset));
+        }
+        if listing_applies(path, config) {
+            if let Some(entries) = config.content_source.list(path).await? {
+                return Ok(Resolution::Listing(entries));
+            }
+        }
+    } else {
+        for candidate in &config.resolution_order {
+            let lookup_path = match candidate {
+                Candidate::Exact => path.to_string(),
+                Candidate::Html if !looks_like_file(path) => format!("{}.html", path),
+                Candidate::Index if !looks_like_file(path) => format!("{}/{}", path, config.index_file),
+                _ => continue,
+            };
+
+            // A directory index that only triggers a redirect doesn't need to be downloaded
+            if *candidate == Candidate::Index && redirect_directories {
+                if probe(&lookup_path, config).await? {
+                    return Ok(Resolution::DirectoryRedirect(format!("{}/", path)));
+                }
+                continue;
+            }
+
+            if let Some(asset) = fetch(&lookup_path, config).await? {
+                return Ok(Resolution::Found(asset));
+            }
+        }
+
+        if !looks_like_file(path) && listing_applies(path, config) {
+            if let Some(entries) = config.content_source.list(path).await? {
+                if redirect_directories {
+                    return Ok(Resolution::DirectoryRedirect(format!("{}/", path)));
+                }
+                return Ok(Resolution::Listing(entries));
+            }
+        }
+    }
+
+    if spa_fallback_applies(path, config) {
+        if let Some(asset) = fetch(&config.spa_index, config).await? {
+            return Ok(Resolution::SpaFallback(asset));
+        }
+    }
+
+    Ok(Resolution::NotFound)
+}
+
+/// Reads an asset through the content cache, when one is configured
+async fn fetch(path: &str, config: &ServerConfig) -> Result<Option<Asset>, ContentError> {
+    match &config.content_cache {
+        Some(cache) => cache.get(&config.content_source, path).await,
+        None => config.content_source.get(path).await,
+    }
+}
+
+/// Checks whether an asset exists, consulting the content cache first
+async fn probe(path: &str, config: &ServerConfig) -> Result<bool, ContentError> {
+    match &config.content_cache {
+        Some(cache) => cache.exists(&config.content_source, path).await,
+        None => config.content_source.exists(path).await,
+    }
+}
+
+/// Returns true when a missing path should be answered with the SPA entry point
+pub fn spa_fallback_applies(path: &str, config: &ServerConfig) -> bool {
+    config.spa_fallback
+        && !looks_like_file(path)
+        && !config.spa_exclude.iter().any(|prefix| matches_prefix(path, prefix))
+}
+
+/// Returns true when directories at this path may be listed
+pub fn listing_applies(path: &str, config: &ServerConfig) -> bool {
+    config.listing_prefixes.iter().any(|prefix| matches_prefix(path, prefix))
+}
+
+/// Matches a path against an `SPA_EXCLUDE`, `DIRECTORY_LISTING` or `LOG_SUPPRESS_PATHS` entry
+///
+/// `/api` matches `/api` and everything below it but not `/apis`;
+/// a trailing `/` or `/*` in the entry means the same thing.
+pub(crate) fn matches_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('*').trim_end_matches('/');
+    if prefix.is_empty() {
+        return true;
+    }
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Joins a root-relative path onto the content root without escaping it
+///
+/// Request paths arrive here already percent-decoded (see `security::decode_path`).
+fn join_content_path(root: &Path, path: &str) -> PathBuf {
+    let mut file_path = root.to_path_buf();
+    for segment in path.split('/') {
+        if segment.is_empty() || segment == "." || segment == ".." {
+            continue;
+        }
+        file_path.push(segment);
+    }
+    file_path
+}
+
+/// Builds a weak ETag for a file from its size and modification time
+///
+/// Hashing every file would cost a full read; size plus nanosecond mtime
+/// changes whenever a deploy replaces the file, which is all revalidation
+/// needs.
+fn file_etag(metadata: &fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_nanos())
+        .unwrap_or(0);
+    format!("W/\"{:x}-{:x}\"", metadata.len(), modified)
+}
+
+/// Returns the MIME type for a path based on its extension
+///
+/// Browsers refuse to execute scripts and stylesheets served with the wrong
+/// type when `X-Content-Type-Options: nosniff` is set, so this has to be
+/// right for every asset a typical front-end build produces.
+pub fn content_type_for(path: &str) -> &'static str {
+    let extension = path
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "application/javascript",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "txt" => "text/plain",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
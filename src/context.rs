@@ -0,0 +1,258 @@
+// Request-scoped context
+// This module carries the request and trace IDs of one invocation through the handler and its log lines
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use chrono::Utc;
+use lambda_http::{Request, RequestExt};
+
+use crate::lifecycle::ColdStart;
+
+/// Counter that keeps generated IDs unique within one process
+static LOCAL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Longest inbound `X-Request-Id` that is adopted
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    /// Context of the request being handled by the current task
+    static CURRENT: RequestContext;
+}
+
+/// Identifiers of the request being handled
+///
+/// Built once per request in `handle_request` and passed down explicitly;
+/// the same IDs are used in every log line of the request, in error
+/// response bodies and in the `x-request-id` and `traceparent` response
+/// headers.
+///
+/// ## Request ID Sources (in order of preference):
+///
+/// 1. **Inbound `X-Request-Id`**: assigned by CloudFront or a gateway in
+///    front of us, adopted when it is at most 128 characters of
+///    `[A-Za-z0-9._:=+/@-]`, so one ID follows the request across the edge
+///    and the Lambda. Anything else is ignored, never truncated or escaped.
+/// 2. **Lambda invocation context**: `aws_request_id` from the Runtime API
+///    response for this invocation. It matches the `RequestId` in the
+///    `START`/`END`/`REPORT` lines CloudWatch writes for the invocation.
+/// 3. **Generated**: `local-<timestamp>-<counter>` when there is no Lambda
+///    context (local mode, tests, `export`).
+///
+/// Process environment variables (`_X_AMZN_TRACE_ID`,
+/// `AWS_LAMBDA_LOG_STREAM_NAME`) are deliberately not used: they are either
+/// stale across warm invocations or shared by every request of a container.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestContext {
+    /// ID used to correlate logs, error messages and the response header
+    pub request_id: String,
+    /// W3C trace context of this hop
+    pub trace: TraceContext,
+    /// X-Ray trace header (`Root=1-...;Parent=...;Sampled=...`), if present
+    pub xray_trace_id: Option<String>,
+    /// Set by `handle_request` on the first request of the container
+    pub cold_start: Option<ColdStart>,
+}
+
+/// W3C Trace Context (`traceparent`) for the span this Lambda handles
+///
+/// ## Trace ID Sources (in order of preference):
+///
+/// 1. **Inbound `traceparent`**: the trace ID and sampled flag are kept and
+///    the caller's span becomes our parent
+/// 2. **X-Ray**: the `Root` of the X-Ray trace header, which is a W3C trace
+///    ID in disguise (`1-5e1b4151-5ac6c58f...` → `5e1b41515ac6c58f...`)
+/// 3. **Generated**: a random trace ID
+///
+/// The span ID is always new: this invocation is its own span, and the
+/// `traceparent` echoed in the response names it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceContext {
+    /// 32 lowercase hex characters
+    pub trace_id: String,
+    /// 16 lowercase hex characters identifying this invocation
+    pub span_id: String,
+    /// Span ID of the caller, from an inbound `traceparent`
+    pub parent_span_id: Option<String>,
+    /// Trace flags; bit 0 is "sampled"
+    pub flags: u8,
+}
+
+impl RequestContext {
+    /// Builds the context from the request headers and Lambda invocation
+    pub fn from_request(request: &Request) -> RequestContext {
+        let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok());
+        let lambda = request.lambda_context_ref();
+
+        let request_id = header("x-request-id")
+            .map(str::trim)
+            .filter(|id| is_valid_request_id(id))
+            .map(str::to_string)
+            .or_else(|| {
+                lambda
+                    .map(|context| context.request_id.trim())
+                    .filter(|id| !id.is_empty())
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(generate_local_id);
+
+        // The trace ID comes from the invocation context, falling back to the
+        // `x-amzn-trace-id` header that Function URLs and API Gateway forward
+        let xray_trace_id = lambda
+            .and_then(|context| context.xray_trace_id.clone())
+            .or_else(|| header("x-amzn-trace-id").map(str::to_string))
+            .filter(|trace| !trace.is_empty());
+
+        let trace = header("traceparent")
+            .and_then(TraceContext::from_traceparent)
+            .or_else(|| xray_trace_id.as_deref().and_then(TraceContext::from_xray))
+            .unwrap_or_else(TraceContext::new_root);
+
+        RequestContext { request_id, trace, xray_trace_id, cold_start: None }
+    }
+
+    /// Builds a context with generated IDs, for work outside a request
+    pub fn local() -> RequestContext {
+        RequestContext { request_id: generate_local_id(), trace: TraceContext::new_root(), xray_trace_id: None, cold_start: None }
+    }
+
+    /// Runs `future` with this context as the current one
+    ///
+    /// Code without access to the context (content sources, the cache, the
+    /// logger) can read it through `RequestContext::current`.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    /// Returns a copy of the context of the request handled by the current task
+    pub fn current() -> Option<RequestContext> {
+        CURRENT.try_with(|context| context.clone()).ok()
+    }
+
+    /// Returns the request ID of the request handled by the current task
+    pub fn current_request_id() -> Option<String> {
+        CURRENT.try_with(|context| context.request_id.clone()).ok()
+    }
+}
+
+impl TraceContext {
+    /// Starts a new sampled trace with a random trace ID
+    pub fn new_root() -> TraceContext {
+        TraceContext { trace_id: random_hex(2), span_id: random_hex(1), parent_span_id: None, flags: 1 }
+    }
+
+    /// Continues the trace of a valid inbound `traceparent` header
+    ///
+    /// Follows the W3C rules: lowercase hex, version `ff` and all-zero IDs
+    /// are invalid, and version `00` has exactly four fields. Later versions
+    /// may append fields, which are ignored. Returns `None` for anything
+    /// invalid, so a malformed header starts a new trace instead.
+    pub fn from_traceparent(header: &str) -> Option<TraceContext> {
+        let mut fields = header.trim().split('-');
+        let version = fields.next()?;
+        let trace_id = fields.next()?;
+        let parent_span_id = fields.next()?;
+        let flags = fields.next()?;
+
+        let valid = is_lower_hex(version, 2)
+            && version != "ff"
+            && (version != "00" || fields.next().is_none())
+            && is_lower_hex(trace_id, 32)
+            && is_lower_hex(parent_span_id, 16)
+            && is_lower_hex(flags, 2)
+            && trace_id.bytes().any(|b| b != b'0')
+            && parent_span_id.bytes().any(|b| b != b'0');
+        if !valid {
+            return None;
+        }
+
+        Some(TraceContext {
+            trace_id: trace_id.to_string(),
+            span_id: random_hex(1),
+            parent_span_id: Some(parent_span_id.to_string()),
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        })
+    }
+
+    /// Uses the `Root` of an X-Ray trace header as the trace ID
+    ///
+    /// The X-Ray `Sampled` decision is kept; its `Parent` segment isn't a
+    /// W3C span, so the new span has no parent.
+    pub fn from_xray(header: &str) -> Option<TraceContext> {
+        let mut root = None;
+        let mut sampled = true;
+        for part in header.split(';') {
+            match part.trim().split_once('=') {
+                Some(("Root", value)) => root = Some(value),
+                Some(("Sampled", value)) => sampled = value != "0",
+                _ => {}
+            }
+        }
+
+        let mut parts = root?.split('-');
+        let (version, epoch, unique) = (parts.next()?, parts.next()?, parts.next()?);
+        let trace_id = format!("{}{}", epoch, unique).to_ascii_lowercase();
+        if version != "1" || parts.next().is_some() || !is_lower_hex(&trace_id, 32) {
+            return None;
+        }
+
+        Some(TraceContext { trace_id, span_id: random_hex(1), parent_span_id: None, flags: sampled as u8 })
+    }
+
+    /// Formats the `traceparent` header naming this span
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.span_id, self.flags)
+    }
+}
+
+/// Returns true for an inbound request ID that is safe to adopt
+///
+/// The character set covers UUIDs, CloudFront IDs (base64) and typical
+/// gateway formats, and excludes whitespace, quotes and control characters
+/// so the ID can't forge log fields or break the response header.
+pub fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b"._:=+/@-".contains(&b))
+}
+
+/// Returns true for exactly `len` lowercase hex characters
+fn is_lower_hex(value: &str, len: usize) -> bool {
+    value.len() == len && value.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Generates a unique ID for requests without a Lambda context
+///
+/// The timestamp keeps IDs readable and sortable; the counter keeps
+/// concurrent requests in the same millisecond apart.
+fn generate_local_id() -> String {
+    let counter = LOCAL_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("local-{}-{:04}", Utc::now().format("%Y%m%d-%H%M%S-%3f"), counter % 10_000)
+}
+
+/// Generates `words * 16` random lowercase hex characters
+///
+/// `RandomState` is seeded from the operating system's random source, which
+/// is enough for trace and span IDs and CSP nonces; it avoids pulling in a
+/// random number crate. A process-wide counter keeps two values generated
+/// in the same instant apart.
+pub fn random_hex(words: u8) -> String {
+    let counter = LOCAL_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or(0);
+
+    (0..words)
+        .map(|round| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(now);
+            hasher.write_u64(counter);
+            hasher.write_u8(round);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
@@ -0,0 +1,87 @@
+// Differences between the Lambda event sources that can invoke the handler
+// This module undoes the API Gateway stage prefix lambda_http adds to request paths
+
+use std::env;
+
+use lambda_http::http::uri::{PathAndQuery, Uri};
+use lambda_http::request::RequestContext as LambdaRequestContext;
+use lambda_http::{Request, RequestExt};
+
+/// Returns the API Gateway stage of the request, if it has a named one
+///
+/// REST APIs (v1) always have a stage; HTTP APIs (v2) usually use the
+/// `$default` stage, which never appears in URLs. Function URLs, ALB target
+/// groups and the local server have no stage.
+pub fn stage(request: &Request) -> Option<&str> {
+    let stage = match request.request_context_ref()? {
+        LambdaRequestContext::ApiGatewayV1(context) => context.stage.as_deref(),
+        LambdaRequestContext::ApiGatewayV2(context) => context.stage.as_deref(),
+        LambdaRequestContext::WebSocket(context) => context.stage.as_deref(),
+        LambdaRequestContext::Alb(_) => None,
+    }?;
+    (!stage.is_empty() && stage != "$default").then_some(stage)
+}
+
+/// Removes the API Gateway stage from the request path
+///
+/// `lambda_http` builds the request URI of API Gateway events as
+/// `/{stage}{path}` unless the event path already starts with `/{stage}/`,
+/// so a REST API deployed to `prod` sees `/prod/about` for `/about`, even
+/// behind a custom domain. Content, redirect rules and health probes are
+/// all keyed by the site path, so the handler undoes this before anything
+/// else looks at the URI:
+///
+/// 1. The `/{stage}` that `lambda_http` prepended is removed again (the
+///    event's own path is kept in the `RawHttpPath` extension).
+/// 2. For HTTP APIs (v2), whose `rawPath` carries the stage on the default
+///    `execute-api` URL, a leading `/{stage}` segment is removed too. REST
+///    API paths never contain the stage, so there `/prod/...` is a site path.
+///
+/// Function URL, ALB and local server requests are left alone, and so is
+/// everything when `AWS_LAMBDA_HTTP_IGNORE_STAGE_IN_PATH` is set.
+///
+/// ## Redirects:
+///
+/// `Location` headers are root-relative, so on the default `execute-api`
+/// URL (which has the stage in its path) redirects lose the stage. Serve
+/// sites through a custom domain with a base path mapping.
+pub fn strip_stage(request: &mut Request) {
+    if env::var_os("AWS_LAMBDA_HTTP_IGNORE_STAGE_IN_PATH").is_some() {
+        return;
+    }
+    let Some(stage) = stage(request) else {
+        return;
+    };
+    let prefix = format!("/{}", stage);
+    let mut path = request.uri().path();
+    if !request.raw_http_path().starts_with(&format!("{}/", prefix)) {
+        path = without_segment(path, &prefix);
+    }
+    if matches!(request.request_context_ref(), Some(LambdaRequestContext::ApiGatewayV2(_))) {
+        path = without_segment(path, &prefix);
+    }
+    if path.len() == request.uri().path().len() {
+        return;
+    }
+    let path_and_query = match request.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+    let Ok(path_and_query) = PathAndQuery::try_from(path_and_query) else {
+        return;
+    };
+    let mut parts = request.uri().clone().into_parts();
+    parts.path_and_query = Some(path_and_query);
+    if let Ok(uri) = Uri::from_parts(parts) {
+        *request.uri_mut() = uri;
+    }
+}
+
+/// Removes `prefix` from `path` when it is the whole first segment
+fn without_segment<'a>(path: &'a str, prefix: &str) -> &'a str {
+    match path.strip_prefix(prefix) {
+        Some("") => "/",
+        Some(rest) if rest.starts_with('/') => rest,
+        _ => path,
+    }
+}
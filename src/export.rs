@@ -0,0 +1,273 @@
+// Static site export
+// This module renders every route through the request handler and writes the results to disk
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use lambda_http::http::{HeaderMap, Request};
+use lambda_http::{Body, Response};
+use log::{info, warn};
+
+use crate::canonical::looks_like_file;
+use crate::config::ServerConfig;
+use crate::content::{listing_applies, ContentSource};
+use crate::handler::handle_request;
+use crate::listing::{encode_path, escape_html};
+
+/// Path requested to capture the 404 page; it can never be an asset
+const NOT_FOUND_PROBE: &str = "/__static-web-lambda-export-404.html";
+
+/// Deepest directory level followed when enumerating content
+const MAX_EXPORT_DEPTH: usize = 32;
+
+/// Headers that describe one specific body and never go into `_headers`
+const PER_RESPONSE_HEADERS: [&str; 7] =
+    ["content-type", "content-length", "etag", "last-modified", "location", "x-request-id", "traceparent"];
+
+/// What an export wrote
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportSummary {
+    /// Assets written (files and directory listings)
+    pub files: usize,
+    /// Meta refresh pages written for redirect rules
+    pub redirects: usize,
+    /// Routes that didn't render with 200 and were left out
+    pub skipped: Vec<String>,
+}
+
+/// Renders the configured site into `out` for a plain CDN or static host
+///
+/// Every route goes through `handle_request` with the given configuration,
+/// so the output is exactly what the Lambda would serve:
+///
+/// - **Assets**: every file of the content source, at the same path
+/// - **Listings**: directories under `DIRECTORY_LISTING` prefixes without an
+///   index, as `<dir>/index.html`
+/// - **Redirects**: rules with a literal `from` path become meta refresh
+///   pages (`<from>/index.html`, or `<from>` itself for file-like paths) for
+///   hosts that ignore `_redirects`
+/// - **`_redirects`**: all rules in Netlify syntax, followed by the SPA
+///   fallback (`SPA_EXCLUDE` prefixes as 404s, then `/* /index.html 200`)
+/// - **`404.html`**: the not-found page
+/// - **`_headers`**: the headers sent with every response under `/*`,
+///   plus per-path blocks for headers only some routes send
+///
+/// Existing files in `out` are overwritten but never deleted.
+pub async fn export_site(config: &ServerConfig, out: &Path) -> Result<ExportSummary, String> {
+    if let ContentSource::Directory(root) = &config.content_source {
+        let root = root.canonicalize().unwrap_or_else(|_| root.clone());
+        fs::create_dir_all(out).map_err(|e| format!("Failed to create {}: {}", out.display(), e))?;
+        let out_canonical = out.canonicalize().unwrap_or_else(|_| out.to_path_buf());
+        if out_canonical.starts_with(&root) || root.starts_with(&out_canonical) {
+            return Err(format!("Export directory {} overlaps CONTENT_DIR {}", out.display(), root.display()));
+        }
+    }
+
+    let mut summary = ExportSummary::default();
+    let mut headers: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    let (files, listings) = enumerate_routes(config).await?;
+
+    for route in files.iter().chain(listings.iter()) {
+        // Routes are file names; the handler decodes the request path again
+        let response = render(config, &encode_path(route)).await?;
+        if response.status() != 200 {
+            warn!("Skipping {}: rendered with status {}", route, response.status());
+            summary.skipped.push(route.clone());
+            continue;
+        }
+        let file = if route.ends_with('/') { format!("{}index.html", route) } else { route.clone() };
+        headers.insert(route.clone(), exportable_headers(response.headers()));
+        write_file(out, &file, body_bytes(response.body()))?;
+        summary.files += 1;
+    }
+
+    for rule in config.redirect_rules.rules() {
+        let is_literal = !rule.from.contains('*') && !rule.from.contains(':') && rule.query.is_empty();
+        if !is_literal || !(300..400).contains(&rule.status) || files.contains(&rule.from) {
+            continue;
+        }
+        let response = render(config, &rule.from).await?;
+        let Some(location) = response.headers().get("location").and_then(|value| value.to_str().ok()) else {
+            continue;
+        };
+        let file = if looks_like_file(&rule.from) {
+            rule.from.clone()
+        } else {
+            format!("{}/index.html", rule.from.trim_end_matches('/'))
+        };
+        write_file(out, &file, redirect_page(location).as_bytes())?;
+        summary.redirects += 1;
+    }
+
+    let not_found = render(config, NOT_FOUND_PROBE).await?;
+    write_file(out, "/404.html", body_bytes(not_found.body()))?;
+
+    write_file(out, "/_redirects", redirects_file(config).as_bytes())?;
+    write_file(out, "/_headers", headers_file(&headers).as_bytes())?;
+
+    info!("[{}] [EXPORT] Wrote {}: files={} redirects={} skipped={}",
+          Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+          out.display(),
+          summary.files,
+          summary.redirects,
+          summary.skipped.len());
+
+    Ok(summary)
+}
+
+/// Lists every asset path plus the directories that render as listings
+async fn enumerate_routes(config: &ServerConfig) -> Result<(Vec<String>, Vec<String>), String> {
+    let mut files = Vec::new();
+    let mut listings = Vec::new();
+    let mut pending = vec![("/".to_string(), 0)];
+
+    while let Some((dir, depth)) = pending.pop() {
+        let entries = match config.content_source.list(&dir).await {
+            Ok(Some(entries)) => entries,
+            Ok(None) => continue,
+            Err(e) => return Err(format!("Failed to list {}: {:?}", dir, e)),
+        };
+
+        let has_index = entries.iter().any(|entry| !entry.is_dir && entry.name == config.index_file);
+        if !has_index && listing_applies(&dir, config) {
+            listings.push(dir.clone());
+        }
+
+        for entry in entries {
+            let path = format!("{}{}", dir, entry.name);
+            if !entry.is_dir {
+                files.push(path);
+            } else if depth < MAX_EXPORT_DEPTH {
+                pending.push((format!("{}/", path), depth + 1));
+            }
+        }
+    }
+
+    // The embedded page can't be listed
+    if files.is_empty() {
+        if let Ok(true) = config.content_source.exists(&config.spa_index).await {
+            files.push(config.spa_index.clone());
+        }
+    }
+
+    files.sort();
+    listings.sort();
+    Ok((files, listings))
+}
+
+/// Sends one GET request through the handler, as the canonical host over HTTPS
+async fn render(config: &ServerConfig, path: &str) -> Result<Response<Body>, String> {
+    let host = config
+        .canonical_host
+        .clone()
+        .or_else(|| config.allowed_hosts.iter().find(|host| !host.starts_with('*')).cloned())
+        .unwrap_or_else(|| "localhost".to_string());
+
+    let request = Request::builder()
+        .method("GET")
+        .uri(path)
+        .header("host", host)
+        .header("x-forwarded-proto", "https")
+        .header("accept", "text/html")
+        .body(Body::Empty)
+        .map_err(|e| format!("Invalid route {}: {}", path, e))?;
+
+    handle_request(request, config)
+        .await
+        .map_err(|e| format!("Failed to render {}: {}", path, e))
+}
+
+/// Returns the raw bytes of a response body
+fn body_bytes(body: &Body) -> &[u8] {
+    match body {
+        Body::Empty => &[],
+        Body::Text(text) => text.as_bytes(),
+        Body::Binary(bytes) => bytes,
+    }
+}
+
+/// Keeps the headers that a static host should send for this route
+fn exportable_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter(|(name, _)| !PER_RESPONSE_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+/// Writes a file below `out`, creating parent directories
+fn write_file(out: &Path, route: &str, contents: &[u8]) -> Result<(), String> {
+    let mut path: PathBuf = out.to_path_buf();
+    for segment in route.split('/') {
+        if segment.is_empty() || segment == "." || segment == ".." {
+            continue;
+        }
+        path.push(segment);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Builds an HTML page that sends the browser to `location`
+pub fn redirect_page(location: &str) -> String {
+    let location = escape_html(location);
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n\
+         <title>Redirecting</title>\n<meta http-equiv=\"refresh\" content=\"0; url={0}\">\n\
+         <link rel=\"canonical\" href=\"{0}\">\n<meta name=\"robots\" content=\"noindex\">\n</head>\n\
+         <body>\n<p>This page has moved to <a href=\"{0}\">{0}</a>.</p>\n</body>\n</html>\n",
+        location
+    )
+}
+
+/// Writes the redirect rules and SPA fallback in Netlify `_redirects` syntax
+pub fn redirects_file(config: &ServerConfig) -> String {
+    let mut lines = vec!["# Generated by static-web-lambda export".to_string()];
+    for rule in config.redirect_rules.rules() {
+        let query: Vec<String> = rule.query.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        let mut line = rule.from.clone();
+        if !query.is_empty() {
+            line.push(' ');
+            line.push_str(&query.join(" "));
+        }
+        lines.push(format!("{} {} {}{}", line, rule.to, rule.status, if rule.force { "!" } else { "" }));
+    }
+
+    if config.spa_fallback {
+        for prefix in &config.spa_exclude {
+            lines.push(format!("{}/* /404.html 404", prefix.trim_end_matches('*').trim_end_matches('/')));
+        }
+        lines.push(format!("/* {} 200", config.spa_index));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Writes `_headers`: shared headers under `/*`, the rest per path
+pub fn headers_file(routes: &BTreeMap<String, Vec<(String, String)>>) -> String {
+    let mut common: Vec<(String, String)> = routes.values().next().cloned().unwrap_or_default();
+    for headers in routes.values() {
+        common.retain(|header| headers.contains(header));
+    }
+
+    let mut output = String::from("# Generated by static-web-lambda export\n/*\n");
+    for (name, value) in &common {
+        output.push_str(&format!("  {}: {}\n", name, value));
+    }
+
+    for (route, headers) in routes {
+        let specific: Vec<&(String, String)> = headers.iter().filter(|header| !common.contains(header)).collect();
+        if specific.is_empty() {
+            continue;
+        }
+        output.push_str(&format!("{}\n", route));
+        for (name, value) in specific {
+            output.push_str(&format!("  {}: {}\n", name, value));
+        }
+    }
+    output
+}
@@ -2,11 +2,28 @@
 // This module contains the core business logic for handling HTTP requests
 
 use lambda_http::{Error, Request, Response, Body};
-use crate::response::{create_html_response, create_generic_error_response, ApplicationError};
-use crate::security::{sanitize_path, validate_request_size, validate_http_method};
+use lambda_http::http::HeaderValue;
+use crate::access_log::{self, AccessLogEntry};
+use crate::canonical::{canonical_redirect, request_host, request_scheme};
+use crate::config::{ServerConfig, TrailingSlash};
+use crate::context::RequestContext;
+use crate::event_source;
+use crate::content::{resolve, ContentError, Resolution};
+use crate::health::{is_probe, probe_response};
+use crate::lifecycle;
+use crate::listing::{render_listing, wants_json};
+use crate::logging::{self, json_event, log_format, LogFormat};
+use crate::metrics::{self, content_route, record_route};
+use crate::prometheus;
+use crate::response::{create_asset_response, create_generic_error_response, create_redirect_response, ApplicationError};
+use crate::security::{decode_path, sanitize_path, validate_request_size, validate_http_method, validate_host};
+use crate::server_timing;
+use crate::slo;
+use crate::telemetry::{stage, stage_async, RequestSpan};
 
 // Import logging functionality for structured request logging
-use log::{info, warn};
+use log::{debug, info, warn, Level};
+use serde_json::json;
 
 // Import chrono for timestamp generation in structured logging
 use chrono::{DateTime, Utc};
@@ -38,6 +55,8 @@ fn extract_user_agent(request: &Request) -> String {
 /// - Response status code: HTTP status code returned to client
 /// - Processing time: Time taken to process the request in milliseconds
 /// - Request path: The requested URL path for correlation with request logs
+/// - Matched rule: The redirects-file rule (`line:pattern`) that handled the request, if any
+/// - Cold start: `cold_start=true` and `init_duration_ms` on the first request of a container
 /// 
 /// Security considerations:
 /// - All logged data is sanitized to prevent log injection attacks
@@ -55,7 +74,9 @@ fn extract_user_agent(request: &Request) -> String {
 /// - `status_code`: HTTP status code of the response (200, 400, 405, etc.)
 /// - `processing_time`: Duration taken to process the request
 /// - `request_path`: The requested URL path for correlation
-fn log_outgoing_response(status_code: u16, processing_time: std::time::Duration, request_path: &str) {
+/// - `matched_rule`: Label of the redirect rule that matched, if any
+/// - `config`: Server configuration, for the path's slow request threshold
+fn log_outgoing_response(status_code: u16, processing_time: std::time::Duration, request_path: &str, matched_rule: Option<&str>, config: &ServerConfig) {
     // Generate timestamp in ISO 8601 format for consistent logging
     let timestamp: DateTime<Utc> = Utc::now();
     
@@ -71,24 +92,76 @@ fn log_outgoing_response(status_code: u16, processing_time: std::time::Duration,
         .filter(|c| *c != '\n' && *c != '\r')
         .collect::<String>();
     
+    // Rule labels come from our own config file, but they are sanitized the same way
+    // as paths since they contain user-authored patterns
+    let rule_field = matched_rule
+        .map(|rule| {
+            let sanitized_rule = rule.chars().filter(|c| c.is_ascii_graphic()).collect::<String>();
+            format!(" rule={}", sanitized_rule)
+        })
+        .unwrap_or_default();
+    
+    // The first request of a container also reports the cold start and init phase
+    let cold_start = RequestContext::current().and_then(|context| context.cold_start);
+    let init_duration_ms = cold_start.and_then(|cold_start| cold_start.init_duration).map(|init| init.as_millis() as u64);
+    let cold_start_field = match (cold_start, init_duration_ms) {
+        (Some(_), Some(init)) => format!(" cold_start=true init_duration_ms={}", init),
+        (Some(_), None) => " cold_start=true".to_string(),
+        (None, _) => String::new(),
+    };
+    
+    // Slow means over the path's SLOW_REQUEST_ROUTES threshold, or SLOW_REQUEST_MS
+    let slow_threshold = config.slow_requests.threshold(request_path);
+    
+    // With LOG_FORMAT=json the same events are emitted as typed JSON fields
+    if log_format() == LogFormat::Json {
+        info!(json_event = true; "{}", json_event(Level::Info, "response", json!({
+            "status": status_code,
+            "duration_ms": processing_time_ms as u64,
+            "path": request_path,
+            "rule": matched_rule,
+            "cold_start": cold_start.map(|_| true),
+            "init_duration_ms": init_duration_ms,
+        })));
+        if processing_time > slow_threshold {
+            warn!(json_event = true; "{}", json_event(Level::Warn, "slow_request", json!({
+                "status": status_code,
+                "duration_ms": processing_time_ms as u64,
+                "threshold_ms": slow_threshold.as_millis() as u64,
+                "path": request_path,
+            })));
+        }
+        if status_code >= 400 {
+            warn!(json_event = true; "{}", json_event(Level::Warn, "error_response", json!({
+                "status": status_code,
+                "duration_ms": processing_time_ms as u64,
+                "path": request_path,
+            })));
+        }
+        return;
+    }
+    
     // Log the response with structured format
-    // Format: [TIMESTAMP] [RESPONSE] status=STATUS_CODE processing_time_ms=TIME path=PATH
+    // Format: [TIMESTAMP] [RESPONSE] status=STATUS_CODE processing_time_ms=TIME path=PATH [rule=LINE:PATTERN] [cold_start=true init_duration_ms=MS]
     info!(
-        "[{}] [RESPONSE] status={} processing_time_ms={} path={}",
+        "[{}] [RESPONSE] status={} processing_time_ms={} path={}{}{}",
         timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
         status_code,
         processing_time_ms,
-        sanitized_path
+        sanitized_path,
+        rule_field,
+        cold_start_field
     );
     
     // Additional performance monitoring for slow requests
     // Log warnings for requests that take longer than expected
     // This helps identify performance issues and potential optimization opportunities
-    if processing_time_ms > 1000 {  // More than 1 second
+    if processing_time > slow_threshold {
         warn!(
-            "[{}] [PERFORMANCE] Slow request detected: processing_time_ms={} status={} path={}",
+            "[{}] [PERFORMANCE] Slow request detected: processing_time_ms={} threshold_ms={} status={} path={}",
             timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
             processing_time_ms,
+            slow_threshold.as_millis(),
             status_code,
             sanitized_path
         );
@@ -147,6 +220,34 @@ fn log_incoming_request(request: &Request) {
         .filter(|c| *c != '\n' && *c != '\r')
         .collect::<String>();
     
+    // With LOG_FORMAT=json the same events are emitted as typed JSON fields
+    // (serde_json escapes control characters, so the unsanitized user agent is safe)
+    if log_format() == LogFormat::Json {
+        info!(json_event = true; "{}", json_event(Level::Info, "request", json!({
+            "method": method,
+            "path": path,
+            "user_agent": user_agent,
+        })));
+        if method != "GET" {
+            warn!(json_event = true; "{}", json_event(Level::Warn, "security", json!({
+                "reason": "non_get_method",
+                "method": method,
+                "path": path,
+                "user_agent": user_agent,
+            })));
+        }
+        if let Some(pattern) = suspicious_user_agent(&user_agent) {
+            warn!(json_event = true; "{}", json_event(Level::Warn, "security", json!({
+                "reason": "suspicious_user_agent",
+                "pattern": pattern,
+                "method": method,
+                "path": path,
+                "user_agent": user_agent,
+            })));
+        }
+        return;
+    }
+    
     // Log the request with structured format
     // Format: [TIMESTAMP] [LEVEL] [REQUEST] method=METHOD path=PATH user_agent=USER_AGENT
     info!(
@@ -170,23 +271,24 @@ fn log_incoming_request(request: &Request) {
     }
     
     // Log suspicious user agents that might indicate automated attacks
-    let suspicious_patterns = ["sqlmap", "nikto", "nmap", "masscan", "dirb"];
-    let user_agent_lower = sanitized_user_agent.to_lowercase();
-    
-    for pattern in &suspicious_patterns {
-        if user_agent_lower.contains(pattern) {
-            warn!(
-                "[{}] [SECURITY] Suspicious user agent detected: pattern={} user_agent={} path={}",
-                timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
-                pattern,
-                sanitized_user_agent,
-                path
-            );
-            break;
-        }
+    if let Some(pattern) = suspicious_user_agent(&sanitized_user_agent) {
+        warn!(
+            "[{}] [SECURITY] Suspicious user agent detected: pattern={} user_agent={} path={}",
+            timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            pattern,
+            sanitized_user_agent,
+            path
+        );
     }
 }
 
+/// Returns the scanner signature found in a User-Agent, if any
+fn suspicious_user_agent(user_agent: &str) -> Option<&'static str> {
+    let suspicious_patterns = ["sqlmap", "nikto", "nmap", "masscan", "dirb"];
+    let user_agent_lower = user_agent.to_lowercase();
+    suspicious_patterns.into_iter().find(|pattern| user_agent_lower.contains(pattern))
+}
+
 /// Lambda handler function - the core of our serverless application
 /// 
 /// This is an ASYNC function, which is a key concept in Rust for handling I/O operations:
@@ -248,7 +350,134 @@ fn log_incoming_request(request: &Request) {
 /// This function will be called once for each HTTP request to our Lambda Function URL.
 /// Lambda handles the infrastructure, scaling, and request routing - we just need to
 /// process the request and return an appropriate response.
+/// 
+/// The server configuration is read from the environment once per container
+/// (see `ServerConfig::global`); use `handle_request` to supply one explicitly.
 pub async fn function_handler(request: Request) -> Result<Response<Body>, Error> {
+    handle_request(request, ServerConfig::global()).await
+}
+
+/// Processes a request using an explicit server configuration
+/// 
+/// This is the body of `function_handler`. It is separate so tests and tools
+/// can exercise configuration-dependent behavior (host allowlists, redirects)
+/// without mutating process-wide environment variables.
+pub async fn handle_request(mut request: Request, config: &ServerConfig) -> Result<Response<Body>, Error> {
+    // API Gateway events arrive with the stage in front of the path; everything
+    // below works with site paths (see event_source::strip_stage)
+    event_source::strip_stage(&mut request);
+    
+    // Health Probes
+    // 
+    // /healthz and /readyz are answered before anything else: load balancers and
+    // uptime monitors poll them every few seconds, often with odd user agents and
+    // from hosts outside ALLOWED_HOSTS, so they skip the access log, the security
+    // logging (suspicious user agents), host validation and metrics. Other methods
+    // fall through to the pipeline and get its usual 405.
+    if config.health_checks && request.method() == lambda_http::http::Method::GET && is_probe(request.uri().path()) {
+        return probe_response(request.uri().path(), config).await;
+    }
+    
+    // The first request of the container is marked as a cold start (response log,
+    // metrics, Server-Timing); it is taken after the probe check so a load balancer
+    // polling /healthz while the container starts can't swallow the marker
+    let cold_start = lifecycle::take_cold_start();
+    
+    // Request Context
+    // 
+    // The request ID (inbound X-Request-Id, this invocation's Lambda context, or
+    // generated) and the trace context (inbound traceparent, X-Ray, or generated)
+    // are the same in every log line, error body and the response headers.
+    // Running the pipeline inside the context's scope lets the logger tag lines
+    // written by modules that never see the context (content sources, the cache).
+    // The pipeline future is boxed: it is large, and callers that await several
+    // requests in one async block would otherwise hit the type layout depth limit.
+    // 
+    // With the `otel` feature the pipeline runs inside a `request` span whose
+    // stages (size check, method check, path sanitization, content lookup and
+    // response building) are child spans; without it the span is a no-op.
+    // 
+    // With EMF_METRICS the pipeline records its route and any security rejection,
+    // and one CloudWatch EMF document is written per request (see metrics::emit).
+    // The local server's Prometheus registry, when installed, counts the same values.
+    // 
+    // With ACCESS_LOG the request side of the access log line is captured here,
+    // before the pipeline takes the request, and the line is written last.
+    // 
+    // With LOG_SAMPLE_RATE or LOG_SUPPRESS_PATHS, info-level lines of requests
+    // outside the sample are held back and only written if the request logs a
+    // warning or ends with a 4xx/5xx (see logging::sampled). Cold starts are
+    // always logged. The access log and metrics are not sampled.
+    // 
+    // With SERVER_TIMING the pipeline stages are timed (see telemetry::stage) and
+    // reported in a Server-Timing header, together with the total and a
+    // cold-start marker on the first request of the container.
+    // 
+    // Unless LATENCY_WINDOW=0, every latency goes into the rolling window that
+    // `main` logs p50/p95/p99 from (see slo::LatencyWindow).
+    let start_time = std::time::Instant::now();
+    let mut context = RequestContext::from_request(&request);
+    context.cold_start = cold_start;
+    let access_entry = config.access_log.as_ref().map(|_| AccessLogEntry::from_request(&request, config, &context.request_id));
+    let span = RequestSpan::start(&mut context, &request);
+    let keep_logs = context.cold_start.is_some() || config.log_sampling.keeps(request.uri().path(), &context.trace.trace_id);
+    let slow_threshold = config.slow_requests.threshold(request.uri().path());
+    let pipeline = Box::pin(logging::sampled(
+        keep_logs,
+        process_request(request, config, &context),
+        |result| result.as_ref().map_or(true, |response| response.status().as_u16() >= 400),
+    ));
+    let timed = server_timing::collect(context.clone().scope(pipeline));
+    let ((response, timings), recorded) = metrics::collect(span.instrument(timed)).await;
+    let mut response = response?;
+    span.record_status(response.status().as_u16());
+    let latency = start_time.elapsed();
+    if let Some(window) = slo::global() {
+        window.record(latency, latency > slow_threshold);
+    }
+    if let Some(namespace) = &config.metrics_namespace {
+        metrics::emit(namespace, &recorded, response.status().as_u16(), latency, &context);
+    }
+    if let Some(registry) = prometheus::global() {
+        registry.observe(&recorded, response.status().as_u16(), latency, body_len(response.body()));
+    }
+    if let (Some(format), Some(mut entry)) = (&config.access_log, access_entry) {
+        entry.status = response.status().as_u16();
+        entry.bytes = body_len(response.body());
+        entry.latency = latency;
+        access_log::write(&entry, format);
+    }
+    
+    // Echo the IDs so callers (CloudFront, gateways, browsers) can correlate with our logs
+    if let Ok(value) = HeaderValue::from_str(&context.request_id) {
+        response.headers_mut().insert("x-request-id", value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&context.trace.traceparent()) {
+        response.headers_mut().insert("traceparent", value);
+    }
+    if config.server_timing {
+        if let Ok(value) = HeaderValue::from_str(&server_timing::header_value(&timings, latency, context.cold_start)) {
+            response.headers_mut().insert("server-timing", value);
+        }
+    }
+    
+    Ok(response)
+}
+
+/// Returns the size of a response body in bytes
+fn body_len(body: &Body) -> u64 {
+    match body {
+        Body::Empty => 0,
+        Body::Text(text) => text.len() as u64,
+        Body::Binary(bytes) => bytes.len() as u64,
+    }
+}
+
+/// Runs the request pipeline for `handle_request`
+/// 
+/// Validation, canonical redirects, redirect rules and content resolution, in that
+/// order; every early return has already been logged with its status and timing.
+async fn process_request(request: Request, config: &ServerConfig, context: &RequestContext) -> Result<Response<Body>, Error> {
     // Record start time for processing time calculation (Task 26 - Requirements 2.4)
     let start_time = std::time::Instant::now();
     // Log incoming request with structured format and timestamp (Task 25 - Requirements 2.4)
@@ -274,19 +503,19 @@ pub async fn function_handler(request: Request) -> Result<Response<Body>, Error>
     // This status code indicates that the request entity is larger than limits
     // defined by server. The server is closing the connection or returning a
     // Retry-After header field indicating when to try again.
-    if let Err(security_error) = validate_request_size(&request) {
+    if let Err(security_error) = stage("size_check", || validate_request_size(&request)) {
         let app_error = ApplicationError::Security {
             security_error,
             context: "request size validation".to_string(),
         };
         
-        let response = create_generic_error_response(app_error)?;
+        let response = create_generic_error_response(app_error, context)?;
         
         // Log error response with processing time (Task 26 - Requirements 2.4)
         let processing_time = start_time.elapsed();
         let status_code = response.status().as_u16();
         let request_path = request.uri().path();
-        log_outgoing_response(status_code, processing_time, request_path);
+        log_outgoing_response(status_code, processing_time, request_path, None, config);
         
         return Ok(response);
     }
@@ -300,7 +529,7 @@ pub async fn function_handler(request: Request) -> Result<Response<Body>, Error>
     // This status code indicates that the server knows the request method,
     // but the target resource doesn't support this method. For a static web server,
     // only GET requests make sense since we're serving read-only content.
-    if let Err(security_error) = validate_http_method(request.method().as_str()) {
+    if let Err(security_error) = stage("method_check", || validate_http_method(request.method().as_str())) {
         let app_error = ApplicationError::Security {
             security_error,
             context: "HTTP method validation".to_string(),
@@ -308,13 +537,37 @@ pub async fn function_handler(request: Request) -> Result<Response<Body>, Error>
         
         // Return HTTP 405 Method Not Allowed for any non-GET request
         // Use create_generic_error_response to ensure all security headers are included
-        let response = create_generic_error_response(app_error)?;
+        let response = create_generic_error_response(app_error, context)?;
+        
+        // Log error response with processing time (Task 26 - Requirements 2.4)
+        let processing_time = start_time.elapsed();
+        let status_code = response.status().as_u16();
+        let request_path = request.uri().path();
+        log_outgoing_response(status_code, processing_time, request_path, None, config);
+        
+        return Ok(response);
+    }
+    
+    // Host Validation
+    // 
+    // When ALLOWED_HOSTS is configured, only those hosts are served. Anything else gets
+    // 421 Misdirected Request, or 400 if the Host header is missing. This stops the raw
+    // Function URL and arbitrary spoofed hosts from serving our content. With
+    // CANONICAL_HOST set, other hosts pass here and are redirected to it below instead.
+    let host = request_host(&request, config);
+    if let Err(security_error) = validate_host(host.as_deref(), request.uri().path(), config) {
+        let app_error = ApplicationError::Security {
+            security_error,
+            context: "host validation".to_string(),
+        };
+        
+        let response = create_generic_error_response(app_error, context)?;
         
         // Log error response with processing time (Task 26 - Requirements 2.4)
         let processing_time = start_time.elapsed();
         let status_code = response.status().as_u16();
         let request_path = request.uri().path();
-        log_outgoing_response(status_code, processing_time, request_path);
+        log_outgoing_response(status_code, processing_time, request_path, None, config);
         
         return Ok(response);
     }
@@ -322,9 +575,9 @@ pub async fn function_handler(request: Request) -> Result<Response<Body>, Error>
     // Path Sanitization (Task 17 - Requirements 3.4)
     // 
     // Security requirement: Sanitize request paths to prevent directory traversal attacks
-    // Even though our static server doesn't serve files from disk, path sanitization
-    // is a critical security practice that:
-    // 1. Prevents potential future vulnerabilities if file serving is added
+    // With CONTENT_DIR set, request paths are mapped to files on disk, so path
+    // sanitization is a critical security practice that:
+    // 1. Keeps asset lookups inside the content root
     // 2. Protects against log injection attacks
     // 3. Follows defense-in-depth security principles
     // 4. Ensures compliance with security best practices
@@ -333,15 +586,18 @@ pub async fn function_handler(request: Request) -> Result<Response<Body>, Error>
     // This status code indicates that the server cannot process the request
     // due to malformed syntax or invalid request message framing.
     // For malicious or malformed paths, this is the appropriate response.
+    // Once the raw path is known to be safe it is percent-decoded for the content
+    // lookup (see security::decode_path), so listing links like `%3Cscript%3E.txt`
+    // and names with spaces or non-ASCII characters resolve.
     let request_path = request.uri().path();
-    match sanitize_path(request_path) {
-        Ok(_sanitized_path) => {
+    let site_path = match stage("sanitize_path", || sanitize_path(request_path).and_then(|_| decode_path(request_path))) {
+        Ok(site_path) => {
             // Path is safe, continue processing
-            // Note: We don't actually use the sanitized path since we serve static content,
-            // but in a real file server, we would use _sanitized_path for file operations
-            info!("[{}] [SECURITY] Request path validation successful: path={}", 
+            // The content lookup re-checks every segment (see content::ContentSource::get)
+            debug!("[{}] [SECURITY] Request path validation successful: path={}", 
                   Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
                   request.uri().path());
+            site_path
         }
         Err(security_error) => {
             // Path contains malicious content, reject the request
@@ -357,22 +613,284 @@ pub async fn function_handler(request: Request) -> Result<Response<Body>, Error>
             
             // Return HTTP 400 Bad Request for malicious paths
             // We provide a generic error message to avoid information disclosure
-            let response = create_generic_error_response(app_error)?;
+            let response = create_generic_error_response(app_error, context)?;
             
             // Log error response with processing time (Task 26 - Requirements 2.4)
             let processing_time = start_time.elapsed();
             let status_code = response.status().as_u16();
             let request_path = request.uri().path();
-            log_outgoing_response(status_code, processing_time, request_path);
+            log_outgoing_response(status_code, processing_time, request_path, None, config);
+            
+            return Ok(response);
+        }
+    };
+    
+    // URL Canonicalization
+    // 
+    // Runs after path sanitization so only safe paths are rewritten. Depending on
+    // configuration this redirects other hosts (e.g. the raw lambda-url.*.on.aws domain)
+    // to CANONICAL_HOST, http to https (X-Forwarded-Proto from CloudFront), adds or strips
+    // trailing slashes, lowercases paths and collapses "//". All rules are combined into
+    // a single 301/308 so clients never follow a redirect chain.
+    let scheme = request_scheme(&request);
+    if let Some(redirect) = canonical_redirect(
+        host.as_deref(),
+        &scheme,
+        request.uri().path(),
+        request.uri().query(),
+        config,
+    ) {
+        record_route("redirect");
+        info!("[{}] [REDIRECT] rule={} status={} location={}",
+              Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+              redirect.rule,
+              redirect.status,
+              redirect.location);
+        
+        let response = create_redirect_response(redirect.status, &redirect.location)?;
+        
+        let processing_time = start_time.elapsed();
+        let status_code = response.status().as_u16();
+        let request_path = request.uri().path();
+        log_outgoing_response(status_code, processing_time, request_path, None, config);
+        
+        return Ok(response);
+    }
+    
+    // Redirect and Rewrite Rules
+    // 
+    // Rules from REDIRECTS_FILE (Netlify `_redirects` syntax) are checked in order and
+    // the first match wins. 3xx rules redirect, 410 rules answer Gone, and 200/404 rules
+    // rewrite the request to another path (re-sanitized, since placeholders can carry
+    // request data) while keeping the URL the client sees.
+    // As on Netlify, content shadows rules: unless a rule is forced (`200!`), it only
+    // applies when the request path resolves to nothing (or only to the SPA fallback),
+    // so `/* /index.html 200` doesn't swallow `/app.js`. A lookup that finds content,
+    // or fails, is answered below exactly as if no rule had matched.
+    let mut matched_rule: Option<String> = None;
+    let mut status_override: Option<u16> = None;
+    let mut content_path = site_path;
+    let mut shadowing_lookup = None;
+    let mut rule_match = config.redirect_rules.find_match(request.uri().path(), request.uri().query());
+    if rule_match.as_ref().is_some_and(|rule_match| !rule_match.force) {
+        let redirect_directories = config.trailing_slash != TrailingSlash::Strip;
+        let lookup = stage_async("content_lookup", resolve(&content_path, config, redirect_directories)).await;
+        if !matches!(lookup, Ok(Resolution::SpaFallback(_) | Resolution::NotFound)) {
+            rule_match = None;
+            shadowing_lookup = Some(lookup);
+        }
+    }
+    if let Some(rule_match) = rule_match {
+        let label = rule_match.label();
+        record_route(&rule_match.from);
+        
+        if rule_match.is_redirect() {
+            info!("[{}] [REDIRECT] rule={} status={} location={}",
+                  Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                  label,
+                  rule_match.status,
+                  rule_match.target);
+            
+            let response = create_redirect_response(rule_match.status, &rule_match.target)?;
+            
+            let processing_time = start_time.elapsed();
+            let status_code = response.status().as_u16();
+            let request_path = request.uri().path();
+            log_outgoing_response(status_code, processing_time, request_path, Some(&label), config);
+            
+            return Ok(response);
+        }
+        
+        if rule_match.status == 410 {
+            let app_error = ApplicationError::Gone {
+                path: request.uri().path().to_string(),
+                rule: label.clone(),
+            };
+            
+            let response = create_generic_error_response(app_error, context)?;
+            
+            let processing_time = start_time.elapsed();
+            let status_code = response.status().as_u16();
+            let request_path = request.uri().path();
+            log_outgoing_response(status_code, processing_time, request_path, Some(&label), config);
             
             return Ok(response);
         }
+        
+        // 200 and 404 rules are rewrites: the target must be as safe as a request path
+        let rewrite_path = rule_match.target.split('?').next().unwrap_or("/");
+        let rewrite_target = match sanitize_path(rewrite_path).and_then(|_| decode_path(rewrite_path)) {
+            Ok(rewrite_target) => rewrite_target,
+            Err(security_error) => {
+                let app_error = ApplicationError::Security {
+                    security_error,
+                    context: format!("rewrite rule {}", label),
+                };
+                
+                let response = create_generic_error_response(app_error, context)?;
+                
+                let processing_time = start_time.elapsed();
+                let status_code = response.status().as_u16();
+                let request_path = request.uri().path();
+                log_outgoing_response(status_code, processing_time, request_path, Some(&label), config);
+                
+                return Ok(response);
+            }
+        };
+        
+        info!("[{}] [REWRITE] rule={} status={} path={} target={}",
+              Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+              label,
+              rule_match.status,
+              request.uri().path(),
+              rewrite_path);
+        
+        if rule_match.status != 200 {
+            status_override = Some(rule_match.status);
+        }
+        content_path = rewrite_target;
+        matched_rule = Some(label);
     }
     
-    // If we reach here, it's a valid GET request with a safe path and acceptable size
-    // Return HTTP 200 OK with our static HTML content
+    // Content Lookup
+    // 
+    // If we reach here, it's a valid GET request with a safe path and acceptable size.
+    // The path (or rewrite target) is looked up in the content source. Unknown client-side
+    // routes fall back to the SPA entry point when SPA_FALLBACK is on; anything else
+    // that doesn't exist is a 404. I/O failures are internal errors, not 404s.
+    // Clean URLs (/about → /about.html) are resolved here; directories requested
+    // without a trailing slash are redirected, except for rewrites (the client never
+    // saw the target) and TRAILING_SLASH=strip (which would redirect straight back).
+    let redirect_directories = matched_rule.is_none() && config.trailing_slash != TrailingSlash::Strip;
+    
+    // Source failures are never turned into 404s or SPA fallbacks: S3 errors become 503
+    // with Retry-After, local I/O errors 500. Objects too large for a Lambda response are
+    // handed off to the source's direct URL (a presigned S3 link) when it has one.
+    let lookup = match shadowing_lookup {
+        Some(lookup) => lookup,
+        None => stage_async("content_lookup", resolve(&content_path, config, redirect_directories)).await,
+    };
+    let resolution = match lookup {
+        Ok(resolution) => resolution,
+        Err(content_error) => {
+            if let ContentError::TooLarge { path, size } = &content_error {
+                if let Some(url) = config.content_source.external_url(path).await {
+                    info!("[{}] [LARGE_OBJECT] path={} size={} action=presigned_redirect",
+                          Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                          path,
+                          size);
+                    
+                    let response = create_redirect_response(302, &url)?;
+                    
+                    let processing_time = start_time.elapsed();
+                    let status_code = response.status().as_u16();
+                    let request_path = request.uri().path();
+                    log_outgoing_response(status_code, processing_time, request_path, matched_rule.as_deref(), config);
+                    
+                    return Ok(response);
+                }
+            }
+            
+            let response = create_generic_error_response(content_error.to_application_error(), context)?;
+            
+            let processing_time = start_time.elapsed();
+            let status_code = response.status().as_u16();
+            let request_path = request.uri().path();
+            log_outgoing_response(status_code, processing_time, request_path, matched_rule.as_deref(), config);
+            
+            return Ok(response);
+        }
+    };
+    
+    let asset = match resolution {
+        Resolution::Found(asset) => {
+            if matched_rule.is_none() {
+                record_route(&content_route(&content_path));
+            }
+            asset
+        }
+        Resolution::DirectoryRedirect(_) => {
+            // Directories are only redirected when no rule rewrote the path, so the
+            // target is the request path plus a slash; use it as the client sent it
+            // (still percent-encoded), which is what a Location header needs
+            let target = format!("{}/", request.uri().path());
+            let location = match request.uri().query() {
+                Some(query) if !query.is_empty() => format!("{}?{}", target, query),
+                _ => target,
+            };
+            
+            record_route("redirect");
+            info!("[{}] [REDIRECT] rule=directory_slash status={} location={}",
+                  Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                  config.redirect_status,
+                  location);
+            
+            let response = create_redirect_response(config.redirect_status, &location)?;
+            
+            let processing_time = start_time.elapsed();
+            let status_code = response.status().as_u16();
+            let request_path = request.uri().path();
+            log_outgoing_response(status_code, processing_time, request_path, None, config);
+            
+            return Ok(response);
+        }
+        Resolution::Listing(entries) => {
+            let accept = request.headers().get("accept").and_then(|value| value.to_str().ok());
+            let as_json = wants_json(accept);
+            if matched_rule.is_none() {
+                record_route(&content_route(&content_path));
+            }
+            
+            info!("[{}] [LISTING] path={} entries={} format={}",
+                  Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                  content_path,
+                  entries.len(),
+                  if as_json { "json" } else { "html" });
+            
+            // Listings change whenever the directory does, and the format depends on Accept
+            let mut response = stage("response_building", || create_asset_response(render_listing(&content_path, entries, as_json)))?;
+            response.headers_mut().insert("cache-control", HeaderValue::from_static("no-cache"));
+            response.headers_mut().insert("vary", HeaderValue::from_static("Accept"));
+            
+            let processing_time = start_time.elapsed();
+            let status_code = response.status().as_u16();
+            let request_path = request.uri().path();
+            log_outgoing_response(status_code, processing_time, request_path, matched_rule.as_deref(), config);
+            
+            return Ok(response);
+        }
+        Resolution::SpaFallback(asset) => {
+            if matched_rule.is_none() {
+                record_route("spa");
+            }
+            info!("[{}] [SPA_FALLBACK] path={} index={}",
+                  Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                  content_path,
+                  asset.path);
+            asset
+        }
+        Resolution::NotFound => {
+            let app_error = ApplicationError::NotFound {
+                path: content_path,
+            };
+            
+            let response = create_generic_error_response(app_error, context)?;
+            
+            let processing_time = start_time.elapsed();
+            let status_code = response.status().as_u16();
+            let request_path = request.uri().path();
+            log_outgoing_response(status_code, processing_time, request_path, matched_rule.as_deref(), config);
+            
+            return Ok(response);
+        }
+    };
+    
+    // Return HTTP 200 OK with the asset (or the rule's status for 404 rewrites)
     // This satisfies Requirement 1.1: "return a valid HTML page with HTTP status 200"
-    let response = create_html_response()?;
+    let mut response = stage("response_building", || create_asset_response(asset))?;
+    if let Some(status) = status_override {
+        *response.status_mut() = lambda_http::http::StatusCode::from_u16(status).map_err(Box::new)?;
+    }
     
     // Log outgoing response with processing time (Task 26 - Requirements 2.4)
     // 
@@ -389,7 +907,7 @@ pub async fn function_handler(request: Request) -> Result<Response<Body>, Error>
     let status_code = response.status().as_u16();
     let request_path = request.uri().path();
     
-    log_outgoing_response(status_code, processing_time, request_path);
+    log_outgoing_response(status_code, processing_time, request_path, matched_rule.as_deref(), config);
     
     Ok(response)
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+// Liveness and readiness endpoints
+// This module answers /healthz and /readyz for load balancers and uptime monitors
+
+use chrono::Utc;
+use lambda_http::{Body, Error, Response};
+use log::warn;
+use serde_json::{json, Map, Value};
+
+use crate::config::{self, ServerConfig};
+use crate::content::{resolve, ContentSource, Resolution};
+
+/// Liveness route: the process is up and answering
+pub const HEALTH_PATH: &str = "/healthz";
+
+/// Readiness route: the process can serve the site
+pub const READY_PATH: &str = "/readyz";
+
+/// One readiness check and its outcome
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    /// Check name (`config`, `content`)
+    pub name: &'static str,
+    /// Whether the check passed
+    pub ok: bool,
+    /// Human-readable outcome, shown in the response body
+    pub detail: String,
+}
+
+/// Returns true for the built-in probe paths
+pub fn is_probe(path: &str) -> bool {
+    path == HEALTH_PATH || path == READY_PATH
+}
+
+/// Runs the readiness checks
+///
+/// ## Checks:
+///
+/// - **config**: the configuration was loaded from the environment, not
+///   replaced by the defaults after an error (see `config::global_fallback`)
+/// - **content**: the content source answers. `/` is resolved exactly like
+///   a request (through the content cache), so an S3 outage, a missing
+///   `CONTENT_DIR` or an unreadable file fails the check. A site without a
+///   root page is still ready; only source errors count.
+///
+/// The content cache is not checked: a cold cache only costs latency, so it
+/// never makes the server unready. The content check's lookup goes through
+/// the cache, though, so the first probe warms the root page.
+pub async fn readiness(config: &ServerConfig) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let fallback = config::global_fallback();
+    checks.push(Check {
+        name: "config",
+        ok: !fallback,
+        detail: if fallback {
+            "failed to load; serving defaults".to_string()
+        } else {
+            "loaded".to_string()
+        },
+    });
+
+    // The response is public, so details are the generic messages; the log has the cause
+    let reachable = match &config.content_source {
+        ContentSource::Directory(root) if !root.is_dir() => {
+            warn!("[{}] [READINESS] content directory missing: {}", timestamp(), root.display());
+            Err("content directory missing".to_string())
+        }
+        _ => resolve("/", config, false).await.map_err(|e| {
+            let error = e.to_application_error();
+            warn!("[{}] [READINESS] content check failed: {}", timestamp(), error.to_detailed_message());
+            error.to_generic_user_message()
+        }),
+    };
+    checks.push(match reachable {
+        Ok(Resolution::NotFound) => Check { name: "content", ok: true, detail: "reachable; no root page".to_string() },
+        Ok(_) => Check { name: "content", ok: true, detail: "reachable".to_string() },
+        Err(error) => Check { name: "content", ok: false, detail: error },
+    });
+
+    checks
+}
+
+/// Timestamp in the format of the other `[TAG]` log lines
+fn timestamp() -> String {
+    Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Answers a probe request
+///
+/// `/healthz` is always 200 while the process runs. `/readyz` is 200 when
+/// every check passes and 503 otherwise, with the checks as JSON so a
+/// failing probe explains itself. Both are `no-store` so no cache between
+/// the probe and the server can answer for it.
+pub async fn probe_response(path: &str, config: &ServerConfig) -> Result<Response<Body>, Error> {
+    let (status, body) = if path == READY_PATH {
+        let checks = readiness(config).await;
+        let ready = checks.iter().all(|check| check.ok);
+        let fields: Map<String, Value> = checks
+            .into_iter()
+            .map(|check| (check.name.to_string(), json!({"ok": check.ok, "detail": check.detail})))
+            .collect();
+        let status = if ready { 200 } else { 503 };
+        (status, json!({"status": if ready { "ready" } else { "not_ready" }, "checks": fields}))
+    } else {
+        (200, json!({"status": "ok"}))
+    };
+
+    let response = Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .header("cache-control", "no-store")
+        .header("x-frame-options", "DENY")  // Prevent clickjacking attacks
+        .header("x-content-type-options", "nosniff")  // Prevent MIME type sniffing
+        .header("content-security-policy", "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; font-src 'self'; connect-src 'self'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'")  // Restrict resource loading
+        .header("x-xss-protection", "1; mode=block")  // Enable XSS filtering with blocking mode
+        .header("strict-transport-security", "max-age=31536000")  // Enforce HTTPS for 1 year
+        .body(body.to_string().into())
+        .map_err(Box::new)?;
+
+    Ok(response)
+}
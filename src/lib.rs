@@ -5,13 +5,35 @@
 // proper error handling, and comprehensive testing.
 
 // Public modules - these contain the main functionality
+pub mod access_log;
+pub mod archive;
+pub mod cache;
+pub mod canonical;
+pub mod config;
+pub mod content;
+pub mod context;
+pub mod event_source;
+pub mod export;
 pub mod handler;
+pub mod health;
+pub mod lifecycle;
+pub mod listing;
+pub mod livereload;
+pub mod logging;
+pub mod metrics;
+pub mod prometheus;
+pub mod redact;
 pub mod response;
+pub mod rules;
+pub mod s3;
 pub mod security;
+pub mod server_timing;
+pub mod slo;
+pub mod telemetry;
 
 // Test modules - only compiled when running tests
 #[cfg(test)]
 mod tests;
 
 // Re-export the main handler function for easy access
-pub use handler::function_handler;
\ No newline at end of file
+pub use handler::{function_handler, handle_request};
\ No newline at end of file
@@ -0,0 +1,116 @@
+// Container lifecycle: init phase timing, cold start detection and pre-warming
+// This module measures how long a container takes to start and marks the first request it handles
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use log::{info, warn};
+
+use crate::config::ServerConfig;
+use crate::content::{resolve, Resolution};
+
+/// When `main` started
+static PROCESS_START: OnceLock<Instant> = OnceLock::new();
+
+/// Time from process start to the end of the init phase
+static INIT_DURATION: OnceLock<Duration> = OnceLock::new();
+
+/// Set until the first request of the process has been handled
+static COLD_START: AtomicBool = AtomicBool::new(true);
+
+tokio::task_local! {
+    /// Marker used instead of `COLD_START` inside `fresh_container`
+    static SCOPED_COLD_START: AtomicBool;
+}
+
+/// Marker carried by the first request of a container
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColdStart {
+    /// Duration of the init phase, when `main` measured it
+    pub init_duration: Option<Duration>,
+}
+
+/// Records the process start time; `main` calls this first
+pub fn mark_process_start() {
+    PROCESS_START.get_or_init(Instant::now);
+}
+
+/// Records the end of the init phase and logs its duration
+///
+/// `main` calls this once configuration, telemetry and pre-warming are done,
+/// just before the Lambda runtime (or the local server) starts taking
+/// requests. Lambda's `REPORT` line has an `Init Duration` too; this one
+/// shows how much of it is ours.
+pub fn mark_init_complete() {
+    let Some(start) = PROCESS_START.get() else {
+        return;
+    };
+    let duration = *INIT_DURATION.get_or_init(|| start.elapsed());
+    info!("[{}] [INIT] init_duration_ms={}",
+          Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+          duration.as_millis());
+}
+
+/// Returns the init phase duration, once `mark_init_complete` has run
+pub fn init_duration() -> Option<Duration> {
+    INIT_DURATION.get().copied()
+}
+
+/// Returns the cold start marker for the first request of the process, `None` afterwards
+///
+/// Lambda runs one request at a time per container, so the first request is
+/// the one that paid for the init phase (and for anything still lazily
+/// initialized). In local mode the first request plays the same role.
+pub fn take_cold_start() -> Option<ColdStart> {
+    SCOPED_COLD_START
+        .try_with(|marker| marker.swap(false, Ordering::Relaxed))
+        .unwrap_or_else(|_| COLD_START.swap(false, Ordering::Relaxed))
+        .then(|| ColdStart { init_duration: init_duration() })
+}
+
+/// Runs `future` as if it were a new container that has not handled a request yet
+///
+/// Requests handled inside take their own cold start marker instead of the
+/// process-wide one, so tests and tools can check which request reports
+/// the cold start without racing other requests in the process.
+pub async fn fresh_container<F: Future>(future: F) -> F::Output {
+    SCOPED_COLD_START.scope(AtomicBool::new(true), future).await
+}
+
+/// Loads what the first request would otherwise load (`PREWARM=true`)
+///
+/// Resolves `/` and, with SPA fallback, `SPA_INDEX` exactly like requests
+/// do, which creates the S3 client (credentials and region lookup), fills
+/// the content cache and touches the filesystem or site bundle. Redirect
+/// rules and bundle indexes are already built when the configuration loads.
+///
+/// Failures are logged and ignored: the request path reports them properly,
+/// and a content outage must not stop the container from starting.
+pub async fn prewarm(config: &ServerConfig) {
+    let mut paths = vec!["/".to_string()];
+    if config.spa_fallback && !paths.contains(&config.spa_index) {
+        paths.push(config.spa_index.clone());
+    }
+    for path in paths {
+        let started = Instant::now();
+        let result = match resolve(&path, config, false).await {
+            Ok(Resolution::NotFound) => "not_found",
+            Ok(_) => "loaded",
+            Err(e) => {
+                warn!("[{}] [PREWARM] path={} error={}",
+                      Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                      path,
+                      e.to_application_error().to_detailed_message());
+                continue;
+            }
+        };
+        info!("[{}] [PREWARM] path={} result={} duration_ms={}",
+              Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+              path,
+              result,
+              started.elapsed().as_millis());
+    }
+}
@@ -0,0 +1,169 @@
+// Auto-generated directory listings
+// This module renders the entries of a content directory as HTML or JSON
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::content::Asset;
+
+/// One entry of a directory listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryEntry {
+    /// File or directory name (a single path segment)
+    pub name: String,
+    /// Whether the entry is a directory
+    pub is_dir: bool,
+    /// Size in bytes (0 for directories)
+    pub size: u64,
+    /// Last modification time, if the source reports one
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Returns true when the `Accept` header asks for JSON rather than HTML
+///
+/// Browsers always list `text/html` first, so only clients that mention
+/// `application/json` without `text/html` get the JSON variant.
+pub fn wants_json(accept: Option<&str>) -> bool {
+    match accept {
+        Some(accept) => {
+            let accept = accept.to_ascii_lowercase();
+            accept.contains("application/json") && !accept.contains("text/html")
+        }
+        None => false,
+    }
+}
+
+/// Renders a listing as an asset ready for `create_asset_response`
+///
+/// Directories are listed first, then files, each sorted by name.
+pub fn render_listing(path: &str, mut entries: Vec<DirectoryEntry>, as_json: bool) -> Asset {
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+    if as_json {
+        Asset {
+            path: path.to_string(),
+            body: render_json(path, &entries).into_bytes(),
+            content_type: "application/json",
+            etag: None,
+            last_modified: None,
+        }
+    } else {
+        Asset {
+            path: path.to_string(),
+            body: render_html(path, &entries).into_bytes(),
+            content_type: "text/html",
+            etag: None,
+            last_modified: None,
+        }
+    }
+}
+
+/// Renders the JSON variant: `{"path": ..., "entries": [{name, type, size, modified}]}`
+fn render_json(path: &str, entries: &[DirectoryEntry]) -> String {
+    let entries: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|entry| {
+            json!({
+                "name": entry.name,
+                "type": if entry.is_dir { "directory" } else { "file" },
+                "size": entry.size,
+                "modified": entry.modified.map(|time| time.to_rfc3339()),
+            })
+        })
+        .collect();
+
+    json!({ "path": path, "entries": entries }).to_string()
+}
+
+/// Renders the HTML variant as a simple table
+///
+/// Every name is HTML-escaped and percent-encoded in links: file names come
+/// from whoever uploaded the build artifacts, and a name like
+/// `<script>.txt` must not become markup.
+fn render_html(path: &str, entries: &[DirectoryEntry]) -> String {
+    let title = escape_html(path);
+    let mut rows = String::new();
+
+    if path != "/" {
+        rows.push_str("<tr><td><a href=\"../\">../</a></td><td></td><td></td></tr>\n");
+    }
+
+    for entry in entries {
+        let suffix = if entry.is_dir { "/" } else { "" };
+        let size = if entry.is_dir { "-".to_string() } else { format_size(entry.size) };
+        let modified = entry
+            .modified
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_else(|| "-".to_string());
+
+        rows.push_str(&format!(
+            "<tr><td><a href=\"{}{}\">{}{}</a></td><td>{}</td><td>{}</td></tr>\n",
+            encode_segment(&entry.name),
+            suffix,
+            escape_html(&entry.name),
+            suffix,
+            size,
+            modified
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n<title>Index of {title}</title>\n\
+         <style>body{{font-family:sans-serif;margin:2rem}}td{{padding:0.2rem 1rem 0.2rem 0}}</style>\n\
+         </head>\n<body>\n<h1>Index of {title}</h1>\n<table>\n\
+         <tr><th>Name</th><th>Size</th><th>Modified</th></tr>\n{rows}</table>\n</body>\n</html>\n",
+        title = title,
+        rows = rows
+    )
+}
+
+/// Escapes the characters that are significant in HTML text and attributes
+pub fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encodes every segment of a decoded path, keeping the slashes
+///
+/// The inverse of `security::decode_path`, for building request URIs from
+/// content paths (see `export`).
+pub(crate) fn encode_path(path: &str) -> String {
+    path.split('/').map(encode_segment).collect::<Vec<_>>().join("/")
+}
+
+/// Percent-encodes a path segment for use in a relative link
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Formats a byte count for humans (`512 B`, `1.5 KB`, `3.2 MB`)
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
@@ -0,0 +1,251 @@
+// Live reload for the local development server
+// This module watches CONTENT_DIR and tells open browser tabs to refresh over SSE
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use chrono::Utc;
+use lambda_http::http::HeaderValue;
+use lambda_http::{Body, Response};
+use log::{info, warn};
+use tokio::sync::watch;
+
+use crate::cache::ContentCache;
+use crate::context::random_hex;
+
+/// Path of the Server-Sent Events endpoint the injected script connects to
+pub const LIVE_RELOAD_PATH: &str = "/__livereload";
+
+/// How often the content directory is scanned for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often an idle event stream sends a comment so proxies keep it open
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Size and modification time of every file under the content root
+pub type Snapshot = BTreeMap<PathBuf, (u64, Option<SystemTime>)>;
+
+/// Broadcasts "content changed" to every open event stream
+///
+/// The value is a generation number that increases with every detected
+/// change; subscribers only care that it changed.
+pub struct LiveReload {
+    generation: watch::Sender<u64>,
+}
+
+impl Default for LiveReload {
+    fn default() -> Self {
+        LiveReload::new()
+    }
+}
+
+impl LiveReload {
+    /// Creates a broadcaster at generation 0
+    pub fn new() -> LiveReload {
+        LiveReload { generation: watch::channel(0).0 }
+    }
+
+    /// Returns a receiver that is woken on every change
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.generation.subscribe()
+    }
+
+    /// Announces a change and returns the new generation
+    pub fn notify(&self) -> u64 {
+        let mut generation = 0;
+        self.generation.send_modify(|value| {
+            *value += 1;
+            generation = *value;
+        });
+        generation
+    }
+}
+
+/// Records every file under `root`, skipping unreadable entries
+///
+/// Polling is used instead of OS file notifications: it behaves the same on
+/// Linux, macOS, Docker bind mounts and network drives, and a development
+/// site is small enough to scan twice a second.
+pub fn snapshot(root: &Path) -> Snapshot {
+    let mut files = Snapshot::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(read_dir) = fs::read_dir(&dir) else { continue };
+        for entry in read_dir.flatten() {
+            let Ok(metadata) = entry.metadata() else { continue };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                files.insert(entry.path(), (metadata.len(), metadata.modified().ok()));
+            }
+        }
+    }
+    files
+}
+
+/// Watches `root` on a background thread
+///
+/// On every change the content cache is cleared (so the next request reads
+/// the new files) and open browser tabs are told to reload.
+pub fn spawn_watcher(root: PathBuf, cache: Option<Arc<ContentCache>>, reload: Arc<LiveReload>) {
+    let spawned = std::thread::Builder::new().name("content-watcher".to_string()).spawn(move || {
+        let mut previous = snapshot(&root);
+        info!("[{}] [LIVE_RELOAD] Watching {} ({} files)",
+              Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+              root.display(),
+              previous.len());
+
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = snapshot(&root);
+            if current == previous {
+                continue;
+            }
+
+            let added = current.keys().filter(|path| !previous.contains_key(*path)).count();
+            let removed = previous.keys().filter(|path| !current.contains_key(*path)).count();
+            let modified = current
+                .iter()
+                .filter(|(path, state)| previous.get(*path).is_some_and(|old| old != *state))
+                .count();
+
+            if let Some(cache) = &cache {
+                cache.clear();
+            }
+            let generation = reload.notify();
+
+            info!("[{}] [LIVE_RELOAD] Content changed: added={} modified={} removed={} generation={}",
+                  Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                  added,
+                  modified,
+                  removed,
+                  generation);
+            previous = current;
+        }
+    });
+
+    if let Err(e) = spawned {
+        warn!("Failed to start content watcher: {}", e);
+    }
+}
+
+/// Opens a Server-Sent Events stream that emits `reload` on every change
+///
+/// `retry: 500` makes the browser reconnect quickly when the development
+/// server restarts.
+pub fn event_stream(reload: &LiveReload) -> hyper::Response<hyper::Body> {
+    let (mut sender, body) = hyper::Body::channel();
+    let mut changes = reload.subscribe();
+
+    tokio::spawn(async move {
+        if sender.send_data("retry: 500\n\n".into()).await.is_err() {
+            return;
+        }
+        loop {
+            let message = tokio::select! {
+                changed = changes.changed() => match changed {
+                    Ok(()) => format!("event: reload\ndata: {}\n\n", *changes.borrow_and_update()),
+                    Err(_) => return,
+                },
+                _ = tokio::time::sleep(KEEPALIVE_INTERVAL) => ": keepalive\n\n".to_string(),
+            };
+            // The browser closed the tab or navigated away
+            if sender.send_data(message.into()).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    hyper::Response::builder()
+        .status(200)
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-store")
+        .header("x-content-type-options", "nosniff")
+        .body(body)
+        .expect("static SSE response headers are valid")
+}
+
+/// Adds the live-reload client to an HTML response
+///
+/// The inline script is allowed by a per-response nonce appended to the
+/// `script-src` directive, so the rest of the Content Security Policy stays
+/// exactly as strict as in production. The body changes on every response,
+/// so `ETag` and `Last-Modified` are dropped and caching is disabled.
+///
+/// Non-HTML responses are returned unchanged.
+pub fn inject_script(response: Response<Body>) -> Response<Body> {
+    let is_html = response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if !is_html {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Body::Text(html) = body else {
+        return Response::from_parts(parts, body);
+    };
+
+    let nonce = generate_nonce();
+    let script = format!(
+        "<script nonce=\"{}\">new EventSource(\"{}\").addEventListener(\"reload\",function(){{location.reload()}});</script>",
+        nonce, LIVE_RELOAD_PATH
+    );
+    let html = match html.to_ascii_lowercase().rfind("</body>") {
+        Some(position) => format!("{}{}{}", &html[..position], script, &html[position..]),
+        None => format!("{}{}", html, script),
+    };
+
+    let csp = parts
+        .headers
+        .get("content-security-policy")
+        .and_then(|value| value.to_str().ok())
+        .map(|csp| add_script_nonce(csp, &nonce));
+    if let Some(csp) = csp.and_then(|csp| HeaderValue::from_str(&csp).ok()) {
+        parts.headers.insert("content-security-policy", csp);
+    }
+    parts.headers.remove("etag");
+    parts.headers.remove("last-modified");
+    parts.headers.insert("cache-control", HeaderValue::from_static("no-store"));
+
+    Response::from_parts(parts, Body::Text(html))
+}
+
+/// Allows scripts carrying `nonce` in a Content Security Policy
+///
+/// The nonce is added to `script-src`; a policy without one gets
+/// `script-src 'self' 'nonce-…'` so it doesn't fall back to a looser
+/// `default-src`.
+pub fn add_script_nonce(csp: &str, nonce: &str) -> String {
+    let mut found = false;
+    let mut directives: Vec<String> = csp
+        .split(';')
+        .map(|directive| directive.trim())
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| {
+            if directive.split_whitespace().next() == Some("script-src") {
+                found = true;
+                format!("{} 'nonce-{}'", directive, nonce)
+            } else {
+                directive.to_string()
+            }
+        })
+        .collect();
+    if !found {
+        directives.push(format!("script-src 'self' 'nonce-{}'", nonce));
+    }
+    directives.join("; ")
+}
+
+/// Generates a 128-bit hex nonce
+///
+/// See `context::random_hex` for the randomness source; it is plenty for a
+/// development server that only listens on localhost by default.
+pub fn generate_nonce() -> String {
+    random_hex(2)
+}
@@ -0,0 +1,360 @@
+// Log output format selection and sampling
+// This module sets up the logger, builds JSON log events for LOG_FORMAT=json and samples per-request lines
+
+use std::cell::RefCell;
+use std::env;
+use std::future::Future;
+use std::io::Write;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use chrono::Utc;
+use log::kv::Key;
+use log::{Level, Log, Metadata, Record};
+use serde_json::{json, Map, Value};
+
+use crate::content::matches_prefix;
+use crate::context::RequestContext;
+use crate::redact;
+
+/// Key marking records whose message is a complete `json_event` line
+pub const JSON_EVENT_KEY: &str = "json_event";
+
+/// Most lines held back for one unsampled request; later ones are dropped
+pub const MAX_HELD_LINES: usize = 256;
+
+tokio::task_local! {
+    /// Info-level lines of the current request, held until it is known to be interesting
+    ///
+    /// `None` once the lines have been released (or for sampled requests,
+    /// which never enter the scope), so later lines are written directly.
+    static HELD: RefCell<Option<Vec<HeldLine>>>;
+}
+
+/// A log line held back by `SamplingLogger`
+struct HeldLine {
+    level: Level,
+    target: String,
+    message: String,
+    json_event: bool,
+}
+
+/// Format selected by `init`; `LogFormat::Text` until then
+static LOG_FORMAT: AtomicU8 = AtomicU8::new(LogFormat::Text as u8);
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// `[timestamp] [TAG] key=value` lines (the default)
+    #[default]
+    Text = 0,
+    /// One JSON object per line, for CloudWatch Logs Insights
+    Json = 1,
+}
+
+impl LogFormat {
+    /// Parses a `LOG_FORMAT` value (`text` or `json`, case-insensitive)
+    pub fn parse(value: &str) -> Result<LogFormat, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("LOG_FORMAT must be 'text' or 'json', got '{}'", other)),
+        }
+    }
+
+    /// Reads `LOG_FORMAT` from the environment, defaulting to text
+    pub fn from_env() -> Result<LogFormat, String> {
+        env::var("LOG_FORMAT").map_or(Ok(LogFormat::Text), |value| LogFormat::parse(&value))
+    }
+}
+
+/// Returns the format selected at startup
+pub fn log_format() -> LogFormat {
+    match LOG_FORMAT.load(Ordering::Relaxed) {
+        1 => LogFormat::Json,
+        _ => LogFormat::Text,
+    }
+}
+
+/// Records the format and installs the stdout logger
+///
+/// Lines written while a request is being handled end with that request's
+/// IDs (` request_id=... trace_id=... span_id=...` in text, fields of the
+/// same names in JSON), including lines from modules that never see the
+/// request context.
+///
+/// ## JSON mode:
+///
+/// Events built with `json_event` and logged with the `json_event = true`
+/// key are written as they are. Every other log line (from this crate or a
+/// dependency, whatever its message looks like) is wrapped as
+/// `{"timestamp","level","target","message"}`, so the stream stays valid
+/// JSON lines and Logs Insights can parse each line without a regex.
+///
+/// `RUST_LOG` filtering works the same in both formats, and both write the
+/// message through the process-wide redactor (see `redact::Redactor`), so
+/// every emitter, including dependencies, is covered.
+pub fn init(format: LogFormat) {
+    LOG_FORMAT.store(format as u8, Ordering::Relaxed);
+
+    let mut builder = env_logger::Builder::from_default_env();
+    builder.target(env_logger::Target::Stdout);
+    match format {
+        LogFormat::Json => builder.format(|buf, record| writeln!(buf, "{}", json_line(record))),
+        // Same layout as env_logger's default format, plus the request ID
+        LogFormat::Text => builder.format(|buf, record| {
+            let context = RequestContext::current();
+            let message = redacted(record, context.as_ref());
+            let level = buf.default_styled_level(record.level());
+            write!(buf, "[{} {:<5} {}] {}", buf.timestamp(), level, record.target(), message)?;
+            if let Some(context) = context {
+                if !message.contains(&context.request_id) {
+                    write!(buf, " request_id={}", context.request_id)?;
+                }
+                write!(buf, " trace_id={} span_id={}", context.trace.trace_id, context.trace.span_id)?;
+            }
+            writeln!(buf)
+        }),
+    };
+    let logger = builder.build();
+    let max_level = logger.filter();
+    log::set_boxed_logger(Box::new(SamplingLogger::new(logger))).expect("logger initialized twice");
+    log::set_max_level(max_level);
+}
+
+/// Which requests have their info-level lines written
+///
+/// Warnings and errors (security events, 4xx/5xx responses, slow requests)
+/// are always written. Info-level lines (`[REQUEST]`, `[RESPONSE]`, cache
+/// and redirect lines) are written for a `rate` fraction of requests, and
+/// never for paths under a `suppress` prefix; see `sampled` for how the
+/// lines of other requests are held back and released when the request
+/// turns out to matter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogSampling {
+    /// Fraction of requests (0.0 to 1.0) whose info-level lines are written
+    pub rate: f64,
+    /// Path prefixes whose info-level lines are never written
+    pub suppress: Vec<String>,
+}
+
+impl Default for LogSampling {
+    fn default() -> Self {
+        LogSampling { rate: 1.0, suppress: Vec::new() }
+    }
+}
+
+impl LogSampling {
+    /// Parses a `LOG_SAMPLE_RATE` value: a fraction (`0.01`) or a percentage (`1%`)
+    pub fn parse_rate(value: &str) -> Result<f64, String> {
+        let trimmed = value.trim();
+        let rate = match trimmed.strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().map(|percent| percent / 100.0),
+            None => trimmed.parse::<f64>(),
+        }
+        .map_err(|_| format!("LOG_SAMPLE_RATE must be a fraction or a percentage, got '{}'", trimmed))?;
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(format!("LOG_SAMPLE_RATE must be between 0 and 1 (0% and 100%), got '{}'", trimmed));
+        }
+        Ok(rate)
+    }
+
+    /// Returns true when the request's info-level lines are written as they happen
+    ///
+    /// The decision is a function of the trace ID rather than a coin flip,
+    /// so every hop that samples the same trace at the same rate keeps or
+    /// drops it together. The low 64 bits are used: they are random for
+    /// generated, W3C and X-Ray trace IDs alike (X-Ray puts a timestamp in
+    /// the high bits).
+    pub fn keeps(&self, path: &str, trace_id: &str) -> bool {
+        if self.suppress.iter().any(|prefix| matches_prefix(path, prefix)) {
+            return false;
+        }
+        if self.rate >= 1.0 {
+            return true;
+        }
+        let low = trace_id.len().saturating_sub(16);
+        match u64::from_str_radix(trace_id.get(low..).unwrap_or_default(), 16) {
+            Ok(value) => (value as f64) < self.rate * u64::MAX as f64,
+            Err(_) => true,
+        }
+    }
+}
+
+/// Runs a request with its info-level lines held back unless `keep` is set
+///
+/// Held lines are released, in order and with their request IDs, as soon
+/// as the request logs a warning or error (a security event, an error
+/// response, a slow request), or when it completes and `escalate` says the
+/// output is worth keeping (the handler escalates on any 4xx/5xx).
+/// Otherwise they are dropped. Released lines keep their own `[timestamp]`
+/// and JSON `timestamp` fields, but the text prefix shows when they were
+/// written.
+pub async fn sampled<F: Future>(keep: bool, future: F, escalate: impl FnOnce(&F::Output) -> bool) -> F::Output {
+    if keep {
+        return future.await;
+    }
+    HELD.scope(RefCell::new(Some(Vec::new())), async {
+        let output = future.await;
+        if escalate(&output) {
+            release(log::logger());
+        }
+        output
+    })
+    .await
+}
+
+/// Writes the current request's held lines to `logger` and stops holding
+fn release(logger: &dyn Log) {
+    let lines = HELD.try_with(|held| held.borrow_mut().take()).ok().flatten().unwrap_or_default();
+    for line in lines {
+        let key_values = [(JSON_EVENT_KEY, true)];
+        let key_values: &[(&str, bool)] = if line.json_event { &key_values } else { &[] };
+        logger.log(
+            &Record::builder()
+                .level(line.level)
+                .target(&line.target)
+                .args(format_args!("{}", line.message))
+                .key_values(&key_values)
+                .build(),
+        );
+    }
+}
+
+/// Logger that holds back info-level lines of unsampled requests
+///
+/// Installed by `init` around the env_logger logger; `RUST_LOG` filtering
+/// happens first, so lines it drops are never held.
+pub struct SamplingLogger<L> {
+    inner: L,
+}
+
+impl<L: Log> SamplingLogger<L> {
+    /// Wraps the logger that writes the lines
+    pub fn new(inner: L) -> Self {
+        SamplingLogger { inner }
+    }
+
+    /// Returns the wrapped logger
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+}
+
+impl<L: Log> Log for SamplingLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+        if record.level() > Level::Warn {
+            let held = HELD
+                .try_with(|held| match held.borrow_mut().as_mut() {
+                    Some(lines) => {
+                        if lines.len() < MAX_HELD_LINES {
+                            lines.push(HeldLine {
+                                level: record.level(),
+                                target: record.target().to_string(),
+                                message: record.args().to_string(),
+                                json_event: is_json_event(record),
+                            });
+                        }
+                        true
+                    }
+                    None => false,
+                })
+                .unwrap_or(false);
+            if held {
+                return;
+            }
+        } else {
+            release(&self.inner);
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Formats one line of the JSON log format
+///
+/// Records carrying the `json_event` key are events built by `json_event`
+/// and are only redacted; anything else is wrapped with the timestamp,
+/// level, target and the current request's IDs.
+pub(crate) fn json_line(record: &Record) -> String {
+    let context = RequestContext::current();
+    let message = redacted(record, context.as_ref());
+    if is_json_event(record) {
+        return message;
+    }
+    let line = json!({
+        "timestamp": timestamp(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": message,
+        "request_id": context.as_ref().map(|context| &context.request_id),
+        "trace_id": context.as_ref().map(|context| &context.trace.trace_id),
+        "span_id": context.as_ref().map(|context| &context.trace.span_id),
+    });
+    strip_nulls(line).to_string()
+}
+
+/// Returns true for records logged with the `json_event = true` key
+fn is_json_event(record: &Record) -> bool {
+    record
+        .key_values()
+        .get(Key::from_str(JSON_EVENT_KEY))
+        .and_then(|value| value.to_bool())
+        .unwrap_or(false)
+}
+
+/// Returns the message of a record with personal data and secrets masked
+///
+/// The current request's IDs are kept: they are what the line is searched by.
+fn redacted(record: &Record, context: Option<&RequestContext>) -> String {
+    let keep: Vec<&str> = context
+        .map(|context| vec![context.request_id.as_str(), context.trace.trace_id.as_str(), context.trace.span_id.as_str()])
+        .unwrap_or_default();
+    redact::global().redact(&record.args().to_string(), &keep)
+}
+
+/// Builds one JSON log line
+///
+/// Log it with the `json_event = true` key so the JSON format writes it as
+/// is instead of wrapping it: `info!(json_event = true; "{}", json_event(..))`.
+///
+/// Every line carries `timestamp`, `level` and `event` (plus `request_id`,
+/// `trace_id` and `span_id` inside a request) and the event's own fields; `null` fields are dropped
+/// so optional values don't need their own branches at each call site.
+pub fn json_event(level: Level, event: &str, fields: Value) -> String {
+    let mut line = Map::new();
+    line.insert("timestamp".to_string(), Value::String(timestamp()));
+    line.insert("level".to_string(), Value::String(level.as_str().to_string()));
+    line.insert("event".to_string(), Value::String(event.to_string()));
+    if let Some(context) = RequestContext::current() {
+        line.insert("request_id".to_string(), Value::String(context.request_id));
+        line.insert("trace_id".to_string(), Value::String(context.trace.trace_id));
+        line.insert("span_id".to_string(), Value::String(context.trace.span_id));
+    }
+    if let Value::Object(fields) = fields {
+        line.extend(fields);
+    }
+    strip_nulls(Value::Object(line)).to_string()
+}
+
+/// Drops top-level `null` fields
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(fields.into_iter().filter(|(_, value)| !value.is_null()).collect()),
+        other => other,
+    }
+}
+
+/// The same ISO 8601 timestamp the text format uses
+fn timestamp() -> String {
+    Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
@@ -6,20 +6,26 @@
 // like those using Function URLs or API Gateway
 use lambda_http::{run, service_fn, Error};
 
-// Import Tokio for async runtime support
-// Rust's async/await requires a runtime to execute async functions
-// Tokio is the most popular async runtime for Rust
-use tokio;
-
 // Import logging functionality for structured request/response logging
-use log::{info, error};
+use log::{info, error, warn};
 
 // Import clap for command-line argument parsing
 // This allows us to parse different execution modes (local vs Lambda)
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 
 // Import our handler function from the library
 use static_web_lambda::function_handler;
+use static_web_lambda::config::ServerConfig;
+use static_web_lambda::content::ContentSource;
+use static_web_lambda::export::export_site;
+use static_web_lambda::lifecycle;
+use static_web_lambda::livereload::{self, LiveReload, LIVE_RELOAD_PATH};
+use static_web_lambda::logging::{self, LogFormat};
+use static_web_lambda::prometheus;
+use static_web_lambda::redact::{self, Redactor};
+use static_web_lambda::rules::RuleSet;
+use static_web_lambda::slo;
+use static_web_lambda::telemetry;
 
 /// Command-line arguments for the static web Lambda application
 /// 
@@ -29,6 +35,9 @@ use static_web_lambda::function_handler;
 /// Usage examples:
 /// - Run in Lambda mode (default): `./static-web-lambda`
 /// - Run in local development mode: `./static-web-lambda --mode local`
+/// - Reload the browser on file changes: `./static-web-lambda --mode local --live-reload`
+/// - Expose Prometheus metrics: `./static-web-lambda --mode local --admin-port 9090`
+/// - Validate a redirects file: `./static-web-lambda check-redirects _redirects`
 /// - Show help: `./static-web-lambda --help`
 #[derive(Parser, Debug)]
 #[command(
@@ -91,6 +100,67 @@ struct Args {
         help = "Host address for local server (ignored in Lambda mode)"
     )]
     host: String,
+    
+    /// Inject a live-reload script into HTML pages (only used in local mode)
+    /// 
+    /// With CONTENT_DIR set, the local server always watches the directory
+    /// and drops cached content when a file changes. This flag additionally
+    /// adds a small script to every HTML response that listens on
+    /// `/__livereload` (Server-Sent Events) and refreshes the page on save.
+    /// The script is allowed through the Content Security Policy with a
+    /// per-response nonce. It is never injected in Lambda mode.
+    #[arg(
+        long = "live-reload",
+        help = "Refresh the browser when CONTENT_DIR changes (ignored in Lambda mode)"
+    )]
+    live_reload: bool,
+    
+    /// Port for the admin server with Prometheus metrics (only used in local mode)
+    /// 
+    /// When set, a second server on the same host answers `GET /metrics` in
+    /// the Prometheus text format: request counts by route and status,
+    /// latency histograms, security rejections by type and bytes served.
+    /// It is a separate port so the site itself never exposes metrics and
+    /// the scrape endpoint can stay cluster-internal. Lambda mode never
+    /// starts it; use the EMF metrics (EMF_METRICS) there instead.
+    #[arg(
+        long = "admin-port",
+        env = "ADMIN_PORT",
+        help = "Serve Prometheus metrics on this port at /metrics (ignored in Lambda mode)"
+    )]
+    admin_port: Option<u16>,
+    
+    /// Optional maintenance command to run instead of serving requests
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Maintenance commands that run once and exit
+/// 
+/// These are meant for CI pipelines and deploy scripts: they validate or
+/// generate artifacts without starting the Lambda runtime or a server.
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Validate a redirect rules file (Netlify `_redirects` syntax)
+    /// 
+    /// Prints every parsed rule, or every syntax error with its line number.
+    /// Exits with an error if any line is invalid, so it can gate deploys.
+    CheckRedirects {
+        /// Path to the rules file (the same file REDIRECTS_FILE points at)
+        file: std::path::PathBuf,
+    },
+
+    /// Export the site as plain files for a CDN or static host
+    /// 
+    /// Renders every asset, directory listing, redirect and the 404 page
+    /// through the same handler the Lambda uses, with the same environment
+    /// configuration, and writes `_redirects` and `_headers` next to them.
+    /// The result can be uploaded to any static host as a fallback origin.
+    Export {
+        /// Output directory; created if missing, existing files are overwritten
+        #[arg(long, default_value = "dist")]
+        out: std::path::PathBuf,
+    },
 }
 
 /// Execution modes supported by the application
@@ -138,6 +208,9 @@ enum ExecutionMode {
 /// 6. Handle runtime startup errors gracefully
 #[tokio::main]
 async fn main() -> Result<(), Error> {
+    // Record the process start first so the init phase duration covers everything
+    lifecycle::mark_process_start();
+    
     // Parse command-line arguments using clap
     // 
     // This automatically handles:
@@ -168,22 +241,133 @@ async fn main() -> Result<(), Error> {
     // - Proper log levels (error, warn, info, debug, trace)
     // - Easy parsing by log aggregation tools
     // - Better debugging and monitoring capabilities
-    env_logger::Builder::from_default_env()
-        .target(env_logger::Target::Stdout)  // Output to stdout instead of stderr
-        .init();
+    // 
+    // LOG_FORMAT=json switches every line to one JSON object for CloudWatch
+    // Logs Insights; an invalid value stops startup like any other bad setting
+    // 
+    // The redactor is installed first so even startup lines are redacted; with
+    // invalid LOG_REDACT_* settings the defaults apply until the process exits
+    let log_format = LogFormat::from_env();
+    let redactor = Redactor::from_env();
+    redact::install(redactor.clone().unwrap_or_default());
+    logging::init(log_format.clone().unwrap_or_default());
+    if let Err(e) = log_format {
+        error!("Invalid logging configuration: {}", e);
+        return Err(Error::from(e));
+    }
+    if let Err(e) = redactor {
+        error!("Invalid log redaction configuration: {}", e);
+        return Err(Error::from(e));
+    }
+    
+    // Maintenance commands run instead of the server
+    if let Some(command) = args.command {
+        return run_command(command).await;
+    }
+    
+    // Load configuration during the init phase so errors (such as an invalid
+    // REDIRECTS_FILE) stop the process before it accepts any traffic
+    if let Err(e) = ServerConfig::init_global() {
+        error!("Invalid server configuration: {}", e);
+        return Err(Error::from(e));
+    }
+    
+    // Install the OTLP span exporter (only with the `otel` feature and an endpoint)
+    match telemetry::init() {
+        Ok(true) => info!("OpenTelemetry tracing enabled"),
+        Ok(false) if telemetry::enabled() => info!("OpenTelemetry tracing disabled: OTEL_EXPORTER_OTLP_ENDPOINT is not set"),
+        Ok(false) => {}
+        Err(e) => {
+            error!("Invalid tracing configuration: {}", e);
+            return Err(Error::from(e));
+        }
+    }
     
     // Log the execution mode for debugging and monitoring
     info!("Starting static-web-lambda in {:?} mode", args.mode);
     
+    // With PREWARM the first request's content is loaded now, during the init
+    // phase, instead of on the first request; the init duration is logged and
+    // reported with the first request (cold start)
+    if ServerConfig::global().prewarm {
+        lifecycle::prewarm(ServerConfig::global()).await;
+    }
+    lifecycle::mark_init_complete();
+    
+    // Keep the latest LATENCY_WINDOW request latencies for the p50/p95/p99
+    // summary, logged periodically by the local server and at shutdown
+    if ServerConfig::global().latency_window > 0 {
+        slo::install(ServerConfig::global().latency_window);
+    }
+    
     // Route to the appropriate execution mode based on command-line arguments
-    match args.mode {
+    let result = match args.mode {
         ExecutionMode::Lambda => {
+            if args.admin_port.is_some() {
+                warn!("--admin-port is ignored in Lambda mode; use EMF_METRICS for metrics");
+            }
             info!("Initializing Lambda function runtime...");
             run_lambda_mode().await
         }
         ExecutionMode::Local => {
             info!("Starting local development server on {}:{}", args.host, args.port);
-            run_local_mode(args.host, args.port).await
+            run_local_mode(args.host, args.port, args.live_reload, args.admin_port).await
+        }
+    };
+    
+    // Log the final latency summary and export any spans still buffered
+    // before the process exits
+    if let Some(window) = slo::global() {
+        window.log_summary("shutdown");
+    }
+    telemetry::shutdown();
+    result
+}
+
+/// Run a maintenance command and exit
+/// 
+/// Output goes to stdout/stderr directly (not the logger) because these
+/// commands are run by people and CI jobs, not inside Lambda.
+async fn run_command(command: Command) -> Result<(), Error> {
+    match command {
+        Command::CheckRedirects { file } => match RuleSet::load(&file) {
+            Ok(rules) => {
+                for rule in rules.rules() {
+                    let query: Vec<String> = rule.query.iter()
+                        .map(|(name, value)| format!("{}={}", name, value))
+                        .collect();
+                    println!(
+                        "line {:>4}: {} {}{} -> {}",
+                        rule.line,
+                        rule.status,
+                        rule.from,
+                        if query.is_empty() { String::new() } else { format!(" [{}]", query.join(" ")) },
+                        rule.to
+                    );
+                }
+                println!("{}: {} rules OK", file.display(), rules.rules().len());
+                Ok(())
+            }
+            Err(errors) => {
+                for error in &errors {
+                    eprintln!("{}: {}", file.display(), error);
+                }
+                Err(Error::from(format!("{} invalid rule(s) in {}", errors.len(), file.display())))
+            }
+        },
+        Command::Export { out } => {
+            let config = ServerConfig::init_global().map_err(Error::from)?;
+            let summary = export_site(config, &out).await.map_err(Error::from)?;
+            for route in &summary.skipped {
+                eprintln!("skipped {}: did not render with status 200", route);
+            }
+            println!(
+                "{}: {} files, {} redirect pages, 404.html, _redirects, _headers",
+                out.display(),
+                summary.files,
+                summary.redirects
+            );
+            Ok(())
         }
     }
 }
@@ -221,7 +405,23 @@ async fn run_lambda_mode() -> Result<(), Error> {
     // Validate that our handler function is properly configured
     // This is a good practice to catch configuration issues early
     info!("Handler function registered successfully");
-    
+
+    // Lambda only sends SIGTERM before shutting a container down when an
+    // extension is registered; otherwise the container is frozen and discarded
+    // without notice. When it does, log the final latency summary (the runtime
+    // below never returns) and exit like the default signal action would.
+    #[cfg(unix)]
+    if let Some(window) = slo::global() {
+        tokio::spawn(async move {
+            if let Ok(mut terminate) = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                terminate.recv().await;
+                window.log_summary("shutdown");
+                telemetry::shutdown();
+                std::process::exit(0);
+            }
+        });
+    }
+
     // Start the Lambda HTTP runtime with comprehensive error handling
     // 
     // service_fn() converts our handler function into a service that can process
@@ -239,7 +439,17 @@ async fn run_lambda_mode() -> Result<(), Error> {
     // - If run() fails during startup, we log the error and propagate it
     // - Runtime errors during request processing are handled by the Lambda service
     // - The ? operator propagates startup errors to the Lambda service for logging
-    match run(service_fn(function_handler)).await {
+    // 
+    // Buffered spans are flushed after every invocation, before the response
+    // is handed back and Lambda can freeze the container (no-op without `otel`)
+    let handler = |request| async {
+        let response = function_handler(request).await;
+        if telemetry::enabled() {
+            let _ = tokio::task::spawn_blocking(telemetry::flush).await;
+        }
+        response
+    };
+    match run(service_fn(handler)).await {
         Ok(()) => {
             // This should rarely happen as run() typically doesn't return Ok(())
             // unless the Lambda service is shutting down gracefully
@@ -311,11 +521,20 @@ fn is_lambda_environment() -> bool {
 /// - Allows in-flight requests to complete before shutting down
 /// - Provides clean shutdown logging for debugging
 /// - Prevents data loss during development
-async fn run_local_mode(host: String, port: u16) -> Result<(), Error> {
+/// 
+/// When the content source is a directory (`CONTENT_DIR`), it is watched
+/// for changes and the content cache is cleared on every change, so edits
+/// show up without a restart. With `live_reload`, HTML responses also get a
+/// script that refreshes the page (see `livereload::inject_script`).
+/// 
+/// With `admin_port`, the Prometheus registry is installed and served on
+/// that port (see `run_admin_server`).
+async fn run_local_mode(host: String, port: u16, live_reload: bool, admin_port: Option<u16>) -> Result<(), Error> {
     use hyper::service::{make_service_fn, service_fn};
     use hyper::Server;
     use std::convert::Infallible;
     use std::net::SocketAddr;
+    use std::sync::Arc;
     
     info!("Starting local development server on {}:{}", host, port);
     
@@ -324,17 +543,66 @@ async fn run_local_mode(host: String, port: u16) -> Result<(), Error> {
         .parse()
         .map_err(|e| Error::from(format!("Invalid host:port combination: {}", e)))?;
     
+    // Watch the content directory so edits are served without a restart
+    let config = ServerConfig::global();
+    let reload = Arc::new(LiveReload::new());
+    match &config.content_source {
+        ContentSource::Directory(root) => {
+            livereload::spawn_watcher(root.clone(), config.content_cache.clone(), reload.clone());
+        }
+        _ if live_reload => warn!("--live-reload only watches CONTENT_DIR; pages will not refresh automatically"),
+        _ => {}
+    }
+    
+    // Metrics get their own port so the public site never serves them
+    if let Some(admin_port) = admin_port {
+        let admin_addr: SocketAddr = format!("{}:{}", host, admin_port)
+            .parse()
+            .map_err(|e| Error::from(format!("Invalid host:admin-port combination: {}", e)))?;
+        if admin_addr == addr {
+            return Err(Error::from("--admin-port must differ from --port"));
+        }
+        let listener = std::net::TcpListener::bind(admin_addr)
+            .map_err(|e| Error::from(format!("Failed to bind admin port {}: {}", admin_addr, e)))?;
+        tokio::spawn(run_admin_server(listener, prometheus::install()));
+    }
+
+    // Log the latency summary every LATENCY_SUMMARY_SECONDS while requests come in
+    if let Some(window) = slo::global() {
+        let period = config.latency_summary_interval;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                if window.has_new() {
+                    window.log_summary("interval");
+                }
+            }
+        });
+    }
+
     // Create a service that converts hyper requests to lambda_http requests
     // and calls our Lambda handler function
-    let make_svc = make_service_fn(|_conn| {
-        async {
-            Ok::<_, Infallible>(service_fn(|req: hyper::Request<hyper::Body>| {
+    let make_svc = make_service_fn(move |conn: &hyper::server::conn::AddrStream| {
+        let reload = reload.clone();
+        let remote_addr = conn.remote_addr();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: hyper::Request<hyper::Body>| {
+                let reload = reload.clone();
                 async move {
+                    // The live-reload event stream is served by the local server itself
+                    if live_reload && req.uri().path() == LIVE_RELOAD_PATH {
+                        return Ok::<_, Error>(livereload::event_stream(&reload));
+                    }
+                    
                     // Convert hyper request to lambda_http request
-                    let lambda_request = convert_hyper_to_lambda_request(req).await?;
+                    let lambda_request = convert_hyper_to_lambda_request(req, remote_addr).await?;
                     
                     // Call our Lambda handler function (same as used in Lambda mode)
-                    let lambda_response = function_handler(lambda_request).await?;
+                    let mut lambda_response = function_handler(lambda_request).await?;
+                    if live_reload {
+                        lambda_response = livereload::inject_script(lambda_response);
+                    }
                     
                     // Convert lambda_http response back to hyper response
                     let hyper_response = convert_lambda_to_hyper_response(lambda_response).await?;
@@ -364,6 +632,42 @@ async fn run_local_mode(host: String, port: u16) -> Result<(), Error> {
     Ok(())
 }
 
+/// Serve the admin endpoints of the local server
+/// 
+/// `GET /metrics` renders the Prometheus registry; everything else is 404.
+/// Admin requests don't go through the request handler, so scrapes are not
+/// logged or counted as site traffic.
+async fn run_admin_server(listener: std::net::TcpListener, registry: &'static prometheus::Registry) {
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Response, Server, StatusCode};
+    use std::convert::Infallible;
+    
+    let make_svc = make_service_fn(move |_conn| async move {
+        Ok::<_, Infallible>(service_fn(move |req: hyper::Request<Body>| async move {
+            let response = match (req.method(), req.uri().path()) {
+                (&hyper::Method::GET, "/metrics") => Response::builder()
+                    .header("content-type", "text/plain; version=0.0.4; charset=utf-8")
+                    .body(Body::from(registry.render())),
+                _ => Response::builder().status(StatusCode::NOT_FOUND).body(Body::from("Not Found")),
+            };
+            Ok::<_, Infallible>(response.expect("static admin response"))
+        }))
+    });
+    
+    let addr = listener.local_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    let server = match Server::from_tcp(listener) {
+        Ok(builder) => builder.serve(make_svc).with_graceful_shutdown(shutdown_signal()),
+        Err(e) => {
+            error!("Admin server error: {}", e);
+            return;
+        }
+    };
+    info!("Prometheus metrics available at http://{}/metrics", addr);
+    if let Err(e) = server.await {
+        error!("Admin server error: {}", e);
+    }
+}
+
 /// Handle graceful shutdown signals for the local development server
 /// 
 /// This function listens for common shutdown signals and provides a clean
@@ -419,8 +723,14 @@ async fn shutdown_signal() {
 /// 
 /// This function bridges the gap between the local hyper server and the Lambda handler.
 /// It ensures that the same handler function can process requests in both environments.
-async fn convert_hyper_to_lambda_request(req: hyper::Request<hyper::Body>) -> Result<lambda_http::Request, Error> {
+/// 
+/// The peer address becomes the `sourceIp` of a Function URL style request
+/// context, which is where the access log reads the client IP in Lambda.
+async fn convert_hyper_to_lambda_request(req: hyper::Request<hyper::Body>, remote_addr: std::net::SocketAddr) -> Result<lambda_http::Request, Error> {
+    use lambda_http::aws_lambda_events::apigw::{ApiGatewayV2httpRequestContext, ApiGatewayV2httpRequestContextHttpDescription};
     use lambda_http::http;
+    use lambda_http::request::RequestContext;
+    use lambda_http::RequestExt;
     
     // Extract the parts of the hyper request
     let (parts, body) = req.into_parts();
@@ -448,7 +758,15 @@ async fn convert_hyper_to_lambda_request(req: hyper::Request<hyper::Body>) -> Re
     let mut lambda_request = lambda_request;
     *lambda_request.headers_mut() = parts.headers;
     
-    Ok(lambda_request)
+    let request_context = ApiGatewayV2httpRequestContext {
+        http: ApiGatewayV2httpRequestContextHttpDescription {
+            source_ip: Some(remote_addr.ip().to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    
+    Ok(lambda_request.with_request_context(RequestContext::ApiGatewayV2(request_context)))
 }
 
 /// Convert a lambda_http response to a hyper HTTP response
@@ -563,6 +881,6 @@ mod tests {
         assert!(html_content.contains("<!DOCTYPE html>"), "Should contain DOCTYPE declaration");
         assert!(html_content.contains("charset=\"UTF-8\""), "Should contain UTF-8 charset");
         assert!(html_content.contains("name=\"viewport\""), "Should contain viewport meta tag");
-        assert!(html_content.contains("🦀 Rust Lambda Function"), "Should contain main heading");
+        assert!(html_content.contains("Serverless Static Web Application"), "Should contain main heading");
     }
 }
\ No newline at end of file
@@ -0,0 +1,180 @@
+// CloudWatch Embedded Metric Format (EMF) metrics
+// This module records what each request did and writes it as one EMF document per request
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::{json, Map, Value};
+
+use crate::context::RequestContext;
+use crate::security::SecurityError;
+
+/// Namespace used when `METRICS_NAMESPACE` isn't set
+pub const DEFAULT_NAMESPACE: &str = "StaticWebLambda";
+
+/// Route recorded for requests that never reached content (rejections, 404s, errors)
+pub const ROUTE_OTHER: &str = "other";
+
+tokio::task_local! {
+    /// Metrics of the request being handled by the current task
+    static RECORDER: RefCell<RequestMetrics>;
+}
+
+/// What one request did, as far as metrics are concerned
+///
+/// The pipeline records into this through `record_route` and
+/// `record_security_error`; `handle_request` adds the status and latency
+/// and writes the EMF document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestMetrics {
+    /// Low-cardinality route label (see `record_route`)
+    pub route: String,
+    /// Variant of the `SecurityError` that rejected the request, if any
+    pub security_error: Option<&'static str>,
+}
+
+impl Default for RequestMetrics {
+    fn default() -> Self {
+        RequestMetrics { route: ROUTE_OTHER.to_string(), security_error: None }
+    }
+}
+
+/// Runs `future` with a fresh metrics recorder and returns what it recorded
+///
+/// When the caller is already inside a recorder (a test wrapping
+/// `handle_request`), that recorder is reset and reused, so the caller sees
+/// the same values the handler emitted.
+pub async fn collect<F: Future>(future: F) -> (F::Output, RequestMetrics) {
+    let recorded = async {
+        let output = future.await;
+        (output, RECORDER.with(|metrics| metrics.borrow().clone()))
+    };
+    if RECORDER.try_with(|metrics| *metrics.borrow_mut() = RequestMetrics::default()).is_ok() {
+        recorded.await
+    } else {
+        RECORDER.scope(RefCell::new(RequestMetrics::default()), recorded).await
+    }
+}
+
+/// Records the route that answered the current request
+///
+/// Routes are dimension values, and every distinct value is a separate
+/// CloudWatch metric, so they must never come straight from the request
+/// path. The handler records:
+///
+/// - the `from` pattern of the matching redirect rule (`/blog/*`)
+/// - `/<first segment>` of a served asset or listing (`/docs`, `/assets`),
+///   or `/` for files at the site root, which is bounded by the site itself
+/// - `spa` for the SPA fallback and `redirect` for canonical and directory
+///   redirects
+///
+/// Anything not recorded (rejections, 404s, source errors) is `other`, so
+/// scanners can't create metrics.
+pub fn record_route(route: &str) {
+    let _ = RECORDER.try_with(|metrics| metrics.borrow_mut().route = route.to_string());
+}
+
+/// Records the security check that rejected the current request
+pub fn record_security_error(error: &SecurityError) {
+    let _ = RECORDER.try_with(|metrics| metrics.borrow_mut().security_error = Some(error.variant_name()));
+}
+
+/// Returns the route label for a served content path
+///
+/// `/docs/guide/intro.html` → `/docs`, `/app.js` → `/`.
+pub fn content_route(path: &str) -> String {
+    match path.trim_start_matches('/').split_once('/') {
+        Some((segment, _)) if !segment.is_empty() => format!("/{}", segment),
+        _ => "/".to_string(),
+    }
+}
+
+/// Returns `2xx`, `3xx`, `4xx` or `5xx` for a status code
+pub fn status_class(status: u16) -> String {
+    format!("{}xx", status / 100)
+}
+
+/// Builds the EMF document for one request
+///
+/// ## Metrics:
+///
+/// - `Requests` (Count) and `Latency` (Milliseconds), by
+///   `FunctionName, Route, StatusClass` and by `FunctionName, StatusClass`
+/// - `SecurityRejections` (Count), by `FunctionName, SecurityError` and by
+///   `FunctionName` alone (CloudWatch alarms can't sum across dimension
+///   values), only when a security check rejected the request
+/// - `ColdStarts` (Count) and `InitDuration` (Milliseconds), by
+///   `FunctionName`, only on the first request of a container
+///
+/// `request_id` and `trace_id` are included as plain properties: they
+/// aren't dimensions, but Logs Insights can search them to go from a metric
+/// spike to the requests behind it.
+pub fn emf_document(
+    namespace: &str,
+    function_name: &str,
+    metrics: &RequestMetrics,
+    status: u16,
+    latency: Duration,
+    context: &RequestContext,
+) -> String {
+    let mut directives = vec![json!({
+        "Namespace": namespace,
+        "Dimensions": [["FunctionName", "Route", "StatusClass"], ["FunctionName", "StatusClass"]],
+        "Metrics": [{"Name": "Requests", "Unit": "Count"}, {"Name": "Latency", "Unit": "Milliseconds"}],
+    })];
+
+    let mut document = Map::new();
+    document.insert("FunctionName".to_string(), json!(function_name));
+    document.insert("Route".to_string(), json!(metrics.route));
+    document.insert("StatusClass".to_string(), json!(status_class(status)));
+    document.insert("Requests".to_string(), json!(1));
+    document.insert("Latency".to_string(), json!(latency.as_secs_f64() * 1000.0));
+
+    if let Some(variant) = metrics.security_error {
+        directives.push(json!({
+            "Namespace": namespace,
+            "Dimensions": [["FunctionName", "SecurityError"], ["FunctionName"]],
+            "Metrics": [{"Name": "SecurityRejections", "Unit": "Count"}],
+        }));
+        document.insert("SecurityError".to_string(), json!(variant));
+        document.insert("SecurityRejections".to_string(), json!(1));
+    }
+
+    if let Some(cold_start) = context.cold_start {
+        let mut metrics = vec![json!({"Name": "ColdStarts", "Unit": "Count"})];
+        document.insert("ColdStarts".to_string(), json!(1));
+        if let Some(init) = cold_start.init_duration {
+            metrics.push(json!({"Name": "InitDuration", "Unit": "Milliseconds"}));
+            document.insert("InitDuration".to_string(), json!(init.as_secs_f64() * 1000.0));
+        }
+        directives.push(json!({"Namespace": namespace, "Dimensions": [["FunctionName"]], "Metrics": metrics}));
+    }
+
+    document.insert("status".to_string(), json!(status));
+    document.insert("request_id".to_string(), json!(context.request_id));
+    document.insert("trace_id".to_string(), json!(context.trace.trace_id));
+    document.insert(
+        "_aws".to_string(),
+        json!({"Timestamp": Utc::now().timestamp_millis(), "CloudWatchMetrics": directives}),
+    );
+    Value::Object(document).to_string()
+}
+
+/// Writes the EMF document for one request to stdout
+///
+/// CloudWatch extracts metrics from any log line that is an EMF document,
+/// so this costs no API calls. The line is printed directly rather than
+/// through the logger: in text format the logger would prefix it, and
+/// `RUST_LOG` must not be able to turn metrics off.
+pub fn emit(namespace: &str, metrics: &RequestMetrics, status: u16, latency: Duration, context: &RequestContext) {
+    println!("{}", emf_document(namespace, function_name(), metrics, status, latency, context));
+}
+
+/// The Lambda function name, or `local` outside Lambda
+fn function_name() -> &'static str {
+    static NAME: OnceLock<String> = OnceLock::new();
+    NAME.get_or_init(|| std::env::var("AWS_LAMBDA_FUNCTION_NAME").unwrap_or_else(|_| "local".to_string()))
+}
@@ -0,0 +1,137 @@
+// Prometheus metrics for the local server
+// This module keeps request counters and latency histograms and renders them in the text exposition format
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::metrics::RequestMetrics;
+
+/// Upper bounds (seconds) of the latency histogram buckets
+pub const LATENCY_BUCKETS: [f64; 11] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Registry installed by `install`; `None` until then
+static GLOBAL: OnceLock<Registry> = OnceLock::new();
+
+/// Request metrics of a long-running local server
+///
+/// Only `--mode local` installs a registry (see `install`), and only when an
+/// admin port is configured; in Lambda mode nothing is recorded, since each
+/// container only sees a fraction of the traffic and is never scraped. The
+/// Lambda equivalent is the EMF metrics in `metrics`.
+///
+/// Labels use the same low-cardinality `route` as the EMF metrics, so a
+/// scanner can't grow the series count.
+#[derive(Debug, Default)]
+pub struct Registry {
+    series: Mutex<Series>,
+}
+
+/// All series, keyed by their label values
+#[derive(Debug, Default)]
+struct Series {
+    requests: BTreeMap<(String, u16), u64>,
+    latency: BTreeMap<String, Histogram>,
+    rejections: BTreeMap<&'static str, u64>,
+    bytes: BTreeMap<String, u64>,
+}
+
+/// Cumulative-on-render latency histogram
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Observations per bucket (not cumulative); the last slot is `+Inf`
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+/// Installs the process-wide registry and returns it
+pub fn install() -> &'static Registry {
+    GLOBAL.get_or_init(Registry::default)
+}
+
+/// Returns the process-wide registry, if one was installed
+pub fn global() -> Option<&'static Registry> {
+    GLOBAL.get()
+}
+
+impl Registry {
+    /// Records one handled request
+    pub fn observe(&self, metrics: &RequestMetrics, status: u16, latency: Duration, bytes: u64) {
+        let mut series = self.series.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *series.requests.entry((metrics.route.clone(), status)).or_default() += 1;
+        *series.bytes.entry(metrics.route.clone()).or_default() += bytes;
+        if let Some(variant) = metrics.security_error {
+            *series.rejections.entry(variant).or_default() += 1;
+        }
+
+        let seconds = latency.as_secs_f64();
+        let histogram = series.latency.entry(metrics.route.clone()).or_default();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        histogram.buckets[bucket] += 1;
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+
+    /// Renders every series in the Prometheus text exposition format (0.0.4)
+    ///
+    /// ## Metrics:
+    ///
+    /// - `static_web_requests_total{route,status}`: handled requests
+    /// - `static_web_request_duration_seconds{route}`: handler latency histogram
+    /// - `static_web_security_rejections_total{type}`: requests rejected by a
+    ///   security check, by `SecurityError` variant
+    /// - `static_web_response_bytes_total{route}`: response body bytes served
+    pub fn render(&self) -> String {
+        let series = self.series.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut out = String::new();
+
+        header(&mut out, "static_web_requests_total", "counter", "Requests handled, by route and status code.");
+        for ((route, status), count) in &series.requests {
+            let _ = writeln!(out, "static_web_requests_total{{route=\"{}\",status=\"{}\"}} {}", escape(route), status, count);
+        }
+
+        header(&mut out, "static_web_request_duration_seconds", "histogram", "Time spent handling requests, by route.");
+        for (route, histogram) in &series.latency {
+            let route = escape(route);
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets.iter()) {
+                cumulative += count;
+                let _ = writeln!(out, "static_web_request_duration_seconds_bucket{{route=\"{}\",le=\"{}\"}} {}", route, bound, cumulative);
+            }
+            let _ = writeln!(out, "static_web_request_duration_seconds_bucket{{route=\"{}\",le=\"+Inf\"}} {}", route, histogram.count);
+            let _ = writeln!(out, "static_web_request_duration_seconds_sum{{route=\"{}\"}} {}", route, histogram.sum);
+            let _ = writeln!(out, "static_web_request_duration_seconds_count{{route=\"{}\"}} {}", route, histogram.count);
+        }
+
+        header(&mut out, "static_web_security_rejections_total", "counter", "Requests rejected by security checks, by SecurityError type.");
+        for (variant, count) in &series.rejections {
+            let _ = writeln!(out, "static_web_security_rejections_total{{type=\"{}\"}} {}", variant, count);
+        }
+
+        header(&mut out, "static_web_response_bytes_total", "counter", "Response body bytes served, by route.");
+        for (route, bytes) in &series.bytes {
+            let _ = writeln!(out, "static_web_response_bytes_total{{route=\"{}\"}} {}", escape(route), bytes);
+        }
+
+        out
+    }
+}
+
+/// Writes the `# HELP` and `# TYPE` lines of a metric
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escapes a label value (`\`, `"` and newlines)
+///
+/// Routes include redirect rule patterns from the rules file, which may
+/// contain any printable character.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,258 @@
+// Redaction of personal data and secrets in logs
+// This module masks emails, tokens, secret query parameters and client IPs before a line is written
+
+use std::env;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+
+use crate::context::random_hex;
+
+/// Redactor installed by `main`; `global` falls back to the default
+static GLOBAL: OnceLock<Redactor> = OnceLock::new();
+
+/// Query parameters whose values are always redacted (matched case-insensitively)
+pub const DEFAULT_QUERY_PARAMS: [&str; 20] = [
+    "token",
+    "access_token",
+    "id_token",
+    "refresh_token",
+    "code",
+    "password",
+    "passwd",
+    "secret",
+    "client_secret",
+    "key",
+    "api_key",
+    "apikey",
+    "auth",
+    "session",
+    "sessionid",
+    "signature",
+    "sig",
+    "x-amz-credential",
+    "x-amz-signature",
+    "x-amz-security-token",
+];
+
+/// How client IP addresses appear in logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClientIp {
+    /// Zero the host part: the last octet of IPv4, all but the /48 of IPv6 (the default)
+    #[default]
+    Truncate,
+    /// A salted SHA-256 prefix, stable for one salt
+    Hash,
+    /// The address as received
+    Full,
+}
+
+impl ClientIp {
+    /// Parses a `LOG_CLIENT_IP` value (`truncate`, `hash` or `full`)
+    pub fn parse(value: &str) -> Result<ClientIp, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "truncate" => Ok(ClientIp::Truncate),
+            "hash" => Ok(ClientIp::Hash),
+            "full" => Ok(ClientIp::Full),
+            other => Err(format!("LOG_CLIENT_IP must be 'truncate', 'hash' or 'full', got '{}'", other)),
+        }
+    }
+}
+
+/// A pattern whose matches are replaced by `[REDACTED:<name>]`
+#[derive(Debug, Clone)]
+struct Pattern {
+    name: String,
+    regex: Regex,
+    /// Extra test on a match, to keep a broad pattern from hitting ordinary words
+    check: fn(&str) -> bool,
+}
+
+/// Masks personal data and secrets in log lines
+///
+/// ## Redactions (in order):
+///
+/// 1. **Query parameters**: values of denylisted parameters (`?token=...`,
+///    `&code=...`) become `[REDACTED]`, wherever a URL appears in a line
+/// 2. **Patterns**: matches become `[REDACTED:<name>]`:
+///    - `email`: email addresses
+///    - `jwt`: JSON Web Tokens (`eyJ...` header, payload and signature)
+///    - `hex`: hex strings of 32 or more characters (session IDs, API keys)
+///    - `base64`: base64 strings of 40 or more characters mixing upper and
+///      lower case letters and digits, so long path slugs are left alone;
+///      `/` is not part of a match, so URL paths are never one long token
+///    - `pattern`: each custom `LOG_REDACT_PATTERNS` regex
+///
+/// The current request's own IDs are never redacted (see `redact`), even
+/// though trace IDs and CloudFront request IDs look like tokens.
+///
+/// Client IPs are not found by pattern; emitters that log one (the access
+/// log) pass it through `client_ip`.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    query: Option<Regex>,
+    patterns: Vec<Pattern>,
+    client_ip: ClientIp,
+    ip_salt: String,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Redactor::new(&[], &[]).expect("built-in redaction patterns compile")
+    }
+}
+
+impl Redactor {
+    /// Builds a redactor with the built-in patterns and query parameters plus extra ones
+    ///
+    /// Client IPs are truncated; see `with_client_ip`.
+    pub fn new(extra_patterns: &[String], extra_params: &[String]) -> Result<Redactor, String> {
+        let builtin = |name: &str, regex: &str, check: fn(&str) -> bool| Pattern {
+            name: name.to_string(),
+            regex: Regex::new(regex).expect("built-in redaction pattern compiles"),
+            check,
+        };
+        let mut patterns = vec![
+            builtin("email", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}", |_| true),
+            builtin("jwt", r"eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*", |_| true),
+            builtin("hex", r"\b[0-9A-Fa-f]{32,}\b", |_| true),
+            builtin("base64", r"[A-Za-z0-9+]{40,}={0,2}", |found| {
+                found.bytes().any(|b| b.is_ascii_uppercase())
+                    && found.bytes().any(|b| b.is_ascii_lowercase())
+                    && found.bytes().any(|b| b.is_ascii_digit())
+            }),
+        ];
+        for pattern in extra_patterns {
+            let regex = Regex::new(pattern).map_err(|e| format!("Invalid LOG_REDACT_PATTERNS entry '{}': {}", pattern, e))?;
+            patterns.push(Pattern { name: "pattern".to_string(), regex, check: |_| true });
+        }
+
+        let mut params: Vec<String> = DEFAULT_QUERY_PARAMS.iter().map(|param| param.to_string()).collect();
+        params.extend(extra_params.iter().map(|param| param.trim().to_ascii_lowercase()).filter(|param| !param.is_empty()));
+        Ok(Redactor { query: Some(query_regex(&params)), patterns, ..Redactor::off() })
+    }
+
+    /// A redactor that leaves log lines alone (`LOG_REDACT=false`)
+    ///
+    /// Client IPs are still truncated unless `with_client_ip` says otherwise.
+    pub fn off() -> Redactor {
+        Redactor { query: None, patterns: Vec::new(), client_ip: ClientIp::Truncate, ip_salt: random_hex(1) }
+    }
+
+    /// Sets how client IPs are logged
+    ///
+    /// Without a salt, hashes use a random per-process salt: they correlate
+    /// requests within one container but can't be reversed by hashing all
+    /// IPv4 addresses. Set a salt to correlate across containers.
+    pub fn with_client_ip(self, client_ip: ClientIp, salt: Option<String>) -> Redactor {
+        Redactor { client_ip, ip_salt: salt.unwrap_or(self.ip_salt), ..self }
+    }
+
+    /// Reads the redaction settings from the environment
+    ///
+    /// ## Environment Variables:
+    ///
+    /// - `LOG_REDACT`: `false` turns off query parameter and pattern redaction (default on)
+    /// - `LOG_REDACT_PATTERNS`: extra regexes, separated by whitespace (use `\s` for a space)
+    /// - `LOG_REDACT_QUERY_PARAMS`: extra query parameter names, comma-separated
+    /// - `LOG_CLIENT_IP`: `truncate` (default), `hash` or `full`
+    /// - `LOG_IP_HASH_SALT`: salt for `hash`
+    ///
+    /// Like `LOG_FORMAT`, an invalid value stops startup: silently logging
+    /// what was meant to be redacted is worse than not starting.
+    pub fn from_env() -> Result<Redactor, String> {
+        let var = |name: &str| env::var(name).ok().map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+
+        let enabled = match var("LOG_REDACT").map(|value| value.to_ascii_lowercase()).as_deref() {
+            None | Some("true" | "1" | "yes" | "on") => true,
+            Some("false" | "0" | "no" | "off") => false,
+            Some(other) => return Err(format!("LOG_REDACT must be true or false, got '{}'", other)),
+        };
+        let redactor = if enabled {
+            let patterns: Vec<String> = var("LOG_REDACT_PATTERNS")
+                .map(|value| value.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default();
+            let params: Vec<String> = var("LOG_REDACT_QUERY_PARAMS")
+                .map(|value| value.split(',').map(str::to_string).collect())
+                .unwrap_or_default();
+            Redactor::new(&patterns, &params)?
+        } else {
+            Redactor::off()
+        };
+        let client_ip = var("LOG_CLIENT_IP").map_or(Ok(ClientIp::Truncate), |value| ClientIp::parse(&value))?;
+        Ok(redactor.with_client_ip(client_ip, var("LOG_IP_HASH_SALT")))
+    }
+
+    /// Returns `text` with secret query parameter values and pattern matches masked
+    ///
+    /// Matches equal to one of `keep` (the request, trace and span IDs of the
+    /// current request) are left as they are.
+    pub fn redact(&self, text: &str, keep: &[&str]) -> String {
+        let mut redacted = match &self.query {
+            Some(query) => query.replace_all(text, "${1}=[REDACTED]").into_owned(),
+            None => text.to_string(),
+        };
+        for pattern in &self.patterns {
+            if !pattern.regex.is_match(&redacted) {
+                continue;
+            }
+            redacted = pattern
+                .regex
+                .replace_all(&redacted, |caps: &Captures| {
+                    let found = &caps[0];
+                    if keep.contains(&found) || !(pattern.check)(found) {
+                        found.to_string()
+                    } else {
+                        format!("[REDACTED:{}]", pattern.name)
+                    }
+                })
+                .into_owned();
+        }
+        redacted
+    }
+
+    /// Returns a client IP address as it may be logged
+    ///
+    /// Truncation keeps a valid address (so log analyzers still parse it)
+    /// and returns `None` for anything that isn't one.
+    pub fn client_ip(&self, ip: &str) -> Option<String> {
+        match self.client_ip {
+            ClientIp::Full => Some(ip.to_string()),
+            ClientIp::Hash => {
+                let digest = Sha256::new().chain_update(self.ip_salt.as_bytes()).chain_update(ip.as_bytes()).finalize();
+                Some(digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect())
+            }
+            ClientIp::Truncate => match ip.parse::<IpAddr>().ok()? {
+                IpAddr::V4(v4) => {
+                    let [a, b, c, _] = v4.octets();
+                    Some(format!("{}.{}.{}.0", a, b, c))
+                }
+                IpAddr::V6(v6) => {
+                    let segments = v6.segments();
+                    Some(std::net::Ipv6Addr::new(segments[0], segments[1], segments[2], 0, 0, 0, 0, 0).to_string())
+                }
+            },
+        }
+    }
+}
+
+/// Matches `?name=value` / `&name=value` / `;name=value` for any denylisted name
+///
+/// The value stops at `&`, `#`, whitespace, `"` and `\`, so a URL inside a
+/// JSON log line is redacted without breaking the line's escaping.
+fn query_regex(params: &[String]) -> Regex {
+    let names: Vec<String> = params.iter().map(|param| regex::escape(param)).collect();
+    Regex::new(&format!(r#"(?i)([?&;](?:{}))=[^&#\s"\\]*"#, names.join("|"))).expect("query parameter pattern compiles")
+}
+
+/// Installs the process-wide redactor used by the logger and the access log
+pub fn install(redactor: Redactor) {
+    let _ = GLOBAL.set(redactor);
+}
+
+/// Returns the process-wide redactor (the default until `install` is called)
+pub fn global() -> &'static Redactor {
+    GLOBAL.get_or_init(Redactor::default)
+}
@@ -4,7 +4,10 @@
 use lambda_http::{Error, Response, Body};
 use log;
 use chrono;
-use std::env;
+use crate::content::Asset;
+use crate::context::RequestContext;
+use crate::logging::{json_event, log_format, LogFormat};
+use crate::metrics::record_security_error;
 
 /// Static HTML content served by our Lambda function
 /// 
@@ -22,98 +25,7 @@ use std::env;
 /// - Syntax highlighting for HTML in editors
 /// - Easier to maintain and edit HTML content
 /// - Still gets all the performance benefits of compile-time inclusion
-const HTML_CONTENT: &str = include_str!("index.html");
-
-/// Generates or extracts a request ID for error correlation and logging
-/// 
-/// This function implements request ID generation for error correlation as required by
-/// Requirements 5.4: "Include request ID for error correlation"
-/// 
-/// ## Request ID Sources (in order of preference):
-/// 
-/// 1. **AWS Lambda Request ID**: AWS Lambda automatically provides a unique request ID
-///    for each invocation via the `AWS_LAMBDA_LOG_STREAM_NAME` and `_X_AMZN_TRACE_ID`
-///    environment variables. This is the preferred source as it integrates with AWS
-///    CloudWatch and X-Ray tracing.
-/// 
-/// 2. **Generated UUID**: If AWS Lambda environment variables are not available
-///    (e.g., during local testing), we generate a random UUID for request tracking.
-/// 
-/// ## Request ID Format:
-/// 
-/// - **AWS Lambda**: Uses the format provided by AWS (typically UUID-like)
-/// - **Generated**: Uses a simple timestamp-based format for local development
-/// 
-/// ## Use Cases:
-/// 
-/// - **Error Correlation**: Link error logs with specific requests for debugging
-/// - **Distributed Tracing**: Integrate with AWS X-Ray and CloudWatch for request tracking
-/// - **Security Monitoring**: Track security violations across multiple log entries
-/// - **Performance Analysis**: Correlate request processing times with specific requests
-/// - **Incident Response**: Quickly identify all log entries related to a problematic request
-/// 
-/// ## Security Considerations:
-/// 
-/// - Request IDs are safe to include in user-facing error messages
-/// - They don't reveal sensitive information about the system or request
-/// - They provide a way for users to reference specific errors when reporting issues
-/// - They enable security teams to correlate attacks across multiple requests
-/// 
-/// ## Implementation Notes:
-/// 
-/// - This function is called once per request to ensure consistent request ID usage
-/// - The request ID is included in both detailed error logs and generic user messages
-/// - AWS Lambda request IDs are automatically correlated with CloudWatch logs
-/// - Generated request IDs are useful for local development and testing
-/// 
-/// ## Return Value:
-/// 
-/// Returns a string containing a unique request identifier that can be safely
-/// included in both logs and user-facing error messages.
-fn generate_request_id() -> String {
-    // Try to get AWS Lambda request ID from environment variables
-    // AWS Lambda provides several environment variables that can be used for request correlation:
-    // - AWS_LAMBDA_LOG_STREAM_NAME: Contains the log stream name which includes request info
-    // - _X_AMZN_TRACE_ID: Contains AWS X-Ray trace ID for distributed tracing
-    // - AWS_LAMBDA_REQUEST_ID: Direct request ID (if available in newer Lambda runtimes)
-    
-    // First, try to get the X-Ray trace ID which is most useful for correlation
-    if let Ok(trace_id) = env::var("_X_AMZN_TRACE_ID") {
-        // Extract just the trace ID portion (before any additional metadata)
-        // X-Ray trace IDs have the format: Root=1-5e1b4151-5ac6c58f5b5dcc1e1e0a7e1c;Parent=...;Sampled=...
-        if let Some(root_part) = trace_id.split(';').next() {
-            if let Some(trace_part) = root_part.strip_prefix("Root=") {
-                return format!("trace-{}", trace_part);
-            }
-        }
-        // If parsing fails, use the full trace ID (truncated for readability)
-        return format!("trace-{}", &trace_id[..std::cmp::min(trace_id.len(), 32)]);
-    }
-    
-    // Try to get AWS Lambda request ID directly (newer runtimes)
-    if let Ok(request_id) = env::var("AWS_LAMBDA_REQUEST_ID") {
-        return format!("lambda-{}", request_id);
-    }
-    
-    // Try to extract request ID from log stream name
-    // Log stream names typically contain request-specific information
-    if let Ok(log_stream) = env::var("AWS_LAMBDA_LOG_STREAM_NAME") {
-        // Extract the last part of the log stream name which often contains request info
-        if let Some(last_part) = log_stream.split('/').last() {
-            return format!("stream-{}", last_part);
-        }
-    }
-    
-    // Fallback: Generate a timestamp-based request ID for local development
-    // This ensures we always have a request ID even when running outside AWS Lambda
-    let timestamp = chrono::Utc::now();
-    let timestamp_str = timestamp.format("%Y%m%d-%H%M%S-%3f").to_string();
-    
-    // Add a random component to ensure uniqueness even for concurrent requests
-    let random_suffix = timestamp.timestamp_nanos_opt().unwrap_or(0) % 10000;
-    
-    format!("local-{}-{:04}", timestamp_str, random_suffix)
-}
+pub(crate) const HTML_CONTENT: &str = include_str!("index.html");
 
 /// Creates an HTTP response with HTML content and proper headers
 /// 
@@ -784,6 +696,32 @@ pub enum ApplicationError {
         /// Estimated time until service recovery (if known)
         retry_after: Option<u32>,
     },
+
+    /// Content that was removed on purpose (410 rules in the redirects file)
+    /// 
+    /// Unlike 404, 410 tells clients and search engines that the resource
+    /// existed and will not come back, so it should be dropped from indexes.
+    /// 
+    /// **User Message**: Generic gone message
+    /// **HTTP Status**: 410 Gone
+    /// **Logging**: Requested path and the rule that retired it
+    Gone {
+        path: String,
+        rule: String,
+    },
+
+    /// No content exists for the requested path
+    /// 
+    /// Returned when the asset lookup finds nothing and SPA fallback doesn't
+    /// apply (file-like paths such as `/missing.js`, excluded prefixes, or
+    /// fallback disabled).
+    /// 
+    /// **User Message**: Generic not found message
+    /// **HTTP Status**: 404 Not Found
+    /// **Logging**: Requested path
+    NotFound {
+        path: String,
+    },
 }
 
 impl ApplicationError {
@@ -818,6 +756,8 @@ impl ApplicationError {
             ApplicationError::InternalError { .. } => 500, // Internal Server Error
             ApplicationError::RequestError { .. } => 400, // Bad Request
             ApplicationError::ServiceUnavailable { .. } => 503, // Service Unavailable
+            ApplicationError::Gone { .. } => 410, // Gone
+            ApplicationError::NotFound { .. } => 404, // Not Found
         }
     }
 
@@ -876,6 +816,14 @@ impl ApplicationError {
                     }
                 }
             }
+            ApplicationError::Gone { .. } => {
+                // Generic message for retired content
+                "Gone. The requested resource is no longer available.".to_string()
+            }
+            ApplicationError::NotFound { .. } => {
+                // Generic message for missing content
+                "Not Found. The requested resource could not be found.".to_string()
+            }
         }
     }
 
@@ -933,6 +881,12 @@ impl ApplicationError {
                     }
                 }
             }
+            ApplicationError::Gone { path, rule } => {
+                format!("Gone: '{}' retired by redirect rule {}", path, rule)
+            }
+            ApplicationError::NotFound { path } => {
+                format!("Not Found: no content for '{}'", path)
+            }
         }
     }
 
@@ -955,12 +909,16 @@ impl ApplicationError {
     /// - "Internal": For internal server errors (unexpected failures, system errors)
     /// - "Request": For request processing errors (malformed requests, invalid data)
     /// - "ServiceUnavailable": For service unavailable errors (temporary failures, rate limiting)
+    /// - "Gone": For content retired by a 410 redirect rule
+    /// - "NotFound": For paths with no content
     pub fn error_type_name(&self) -> &'static str {
         match self {
             ApplicationError::Security { .. } => "Security",
             ApplicationError::InternalError { .. } => "Internal",
             ApplicationError::RequestError { .. } => "Request",
             ApplicationError::ServiceUnavailable { .. } => "ServiceUnavailable",
+            ApplicationError::Gone { .. } => "Gone",
+            ApplicationError::NotFound { .. } => "NotFound",
         }
     }
 }
@@ -1010,6 +968,7 @@ impl ApplicationError {
 /// 
 /// ## Parameters:
 /// - `error`: The ApplicationError containing full error details
+/// - `context`: The request context whose ID is logged and shown to the user
 /// 
 /// ## Return Value:
 /// - `Ok(Response<Body>)`: Successfully created error response
@@ -1026,20 +985,74 @@ impl ApplicationError {
 ///     },
 ///     context: "request validation".to_string(),
 /// };
-/// let response = create_generic_error_response(security_err)?;
+/// let response = create_generic_error_response(security_err, &context)?;
 /// 
 /// // Internal error
 /// let internal_err = ApplicationError::InternalError {
 ///     details: "Failed to allocate memory for response".to_string(),
 ///     cause: Some("Out of memory".to_string()),
 /// };
-/// let response = create_generic_error_response(internal_err)?;
+/// let response = create_generic_error_response(internal_err, &context)?;
 /// ```
-pub fn create_generic_error_response(error: ApplicationError) -> Result<Response<Body>, Error> {
-    // Generate a unique request ID for error correlation (Task 30 - Requirements 5.4)
+pub fn create_generic_error_response(error: ApplicationError, context: &RequestContext) -> Result<Response<Body>, Error> {
+    // Use the request's ID for error correlation (Task 30 - Requirements 5.4)
     // This enables correlation between user-facing error messages and detailed internal logs
-    let request_id = generate_request_id();
+    let request_id = context.request_id.as_str();
+    
+    // Security rejections are counted by type in the EMF metrics
+    if let ApplicationError::Security { security_error, .. } = &error {
+        record_security_error(security_error);
+    }
     
+    // With LOG_FORMAT=json the error is one event whose fields replace both text lines below
+    if log_format() == LogFormat::Json {
+        log::error!(json_event = true; "{}", json_event(log::Level::Error, "error", error_log_fields(&error, request_id)));
+    } else {
+        log_error_text(&error, request_id);
+    }
+    
+    // Get the appropriate HTTP status code for this error type
+    let status_code = error.to_http_status_code();
+    
+    // Get the generic, user-safe error message with request ID for correlation
+    // This message is designed to be helpful to legitimate users while
+    // not revealing any sensitive information to potential attackers
+    // The request ID allows users to reference specific errors when reporting issues
+    let user_message = format!("{} (Request ID: {})", error.to_generic_user_message(), request_id);
+    
+    // Build the error response with consistent security headers
+    let mut response_builder = Response::builder()
+        .status(status_code)
+        .header("content-type", "text/plain")  // Plain text for error messages
+        // Include all security headers to maintain consistent security posture
+        .header("x-frame-options", "DENY")  // Prevent clickjacking attacks
+        .header("x-content-type-options", "nosniff")  // Prevent MIME type sniffing
+        .header("content-security-policy", "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; font-src 'self'; connect-src 'self'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'")  // Restrict resource loading
+        .header("x-xss-protection", "1; mode=block")  // Enable XSS filtering with blocking mode
+        .header("strict-transport-security", "max-age=31536000");  // Enforce HTTPS for 1 year
+    
+    // Add Allow header for 405 Method Not Allowed responses
+    // This tells the client which HTTP methods are supported
+    if status_code == 405 {
+        response_builder = response_builder.header("allow", "GET");
+    }
+    
+    // Add Retry-After header for 503 Service Unavailable responses
+    // This tells the client when they should try again
+    if let ApplicationError::ServiceUnavailable { retry_after: Some(seconds), .. } = &error {
+        response_builder = response_builder.header("retry-after", seconds.to_string());
+    }
+    
+    // Build the final response with the generic user message including request ID
+    let response = response_builder
+        .body(user_message.into())
+        .map_err(Box::new)?;
+    
+    Ok(response)
+}
+
+/// Writes the two text-format log lines for an error response
+fn log_error_text(error: &ApplicationError, request_id: &str) {
     // Log the detailed error information for internal monitoring and debugging (Task 30 - Requirements 5.4)
     // This provides full context for developers and security teams while
     // keeping sensitive details away from end users
@@ -1061,7 +1074,7 @@ pub fn create_generic_error_response(error: ApplicationError) -> Result<Response
     
     // Additional structured logging for security monitoring and incident response
     // This separate log entry makes it easier to filter and alert on specific error types
-    match &error {
+    match error {
         ApplicationError::Security { security_error, context } => {
             log::warn!(
                 "[{}] [SECURITY_VIOLATION] [REQUEST_ID:{}] Security error in {}: {} (status={})",
@@ -1099,46 +1112,50 @@ pub fn create_generic_error_response(error: ApplicationError) -> Result<Response
                 retry_after.map(|s| s.to_string()).unwrap_or_else(|| "unknown".to_string())
             );
         }
+        ApplicationError::Gone { path, rule } => {
+            log::info!(
+                "[{}] [GONE] [REQUEST_ID:{}] Retired path requested: {} (rule: {})",
+                chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                request_id,
+                path,
+                rule
+            );
+        }
+        ApplicationError::NotFound { path } => {
+            log::info!(
+                "[{}] [NOT_FOUND] [REQUEST_ID:{}] No content for path: {}",
+                chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                request_id,
+                path
+            );
+        }
     }
-    
-    // Get the appropriate HTTP status code for this error type
-    let status_code = error.to_http_status_code();
-    
-    // Get the generic, user-safe error message with request ID for correlation
-    // This message is designed to be helpful to legitimate users while
-    // not revealing any sensitive information to potential attackers
-    // The request ID allows users to reference specific errors when reporting issues
-    let user_message = format!("{} (Request ID: {})", error.to_generic_user_message(), request_id);
-    
-    // Build the error response with consistent security headers
-    let mut response_builder = Response::builder()
-        .status(status_code)
-        .header("content-type", "text/plain")  // Plain text for error messages
-        // Include all security headers to maintain consistent security posture
-        .header("x-frame-options", "DENY")  // Prevent clickjacking attacks
-        .header("x-content-type-options", "nosniff")  // Prevent MIME type sniffing
-        .header("content-security-policy", "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; font-src 'self'; connect-src 'self'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'")  // Restrict resource loading
-        .header("x-xss-protection", "1; mode=block")  // Enable XSS filtering with blocking mode
-        .header("strict-transport-security", "max-age=31536000");  // Enforce HTTPS for 1 year
-    
-    // Add Allow header for 405 Method Not Allowed responses
-    // This tells the client which HTTP methods are supported
-    if status_code == 405 {
-        response_builder = response_builder.header("allow", "GET");
-    }
-    
-    // Add Retry-After header for 503 Service Unavailable responses
-    // This tells the client when they should try again
-    if let ApplicationError::ServiceUnavailable { retry_after: Some(seconds), .. } = &error {
-        response_builder = response_builder.header("retry-after", seconds.to_string());
+}
+
+/// Typed fields of the JSON `error` event
+///
+/// `request_id`, `status`, `error_type` and `detailed_error` are always
+/// present; the variant adds its own context (`cause`, `retry_after_seconds`,
+/// `rule`, ...) so Logs Insights can filter on it directly.
+pub(crate) fn error_log_fields(error: &ApplicationError, request_id: &str) -> serde_json::Value {
+    let mut fields = serde_json::json!({
+        "request_id": request_id,
+        "status": error.to_http_status_code(),
+        "error_type": error.error_type_name(),
+        "detailed_error": error.to_detailed_message(),
+    });
+    let extra = match error {
+        ApplicationError::Security { context, .. } => serde_json::json!({ "context": context }),
+        ApplicationError::InternalError { cause, .. } => serde_json::json!({ "cause": cause }),
+        ApplicationError::RequestError { component, .. } => serde_json::json!({ "component": component }),
+        ApplicationError::ServiceUnavailable { retry_after, .. } => serde_json::json!({ "retry_after_seconds": retry_after }),
+        ApplicationError::Gone { path, rule } => serde_json::json!({ "path": path, "rule": rule }),
+        ApplicationError::NotFound { path } => serde_json::json!({ "path": path }),
+    };
+    if let (Some(fields), serde_json::Value::Object(extra)) = (fields.as_object_mut(), extra) {
+        fields.extend(extra);
     }
-    
-    // Build the final response with the generic user message including request ID
-    let response = response_builder
-        .body(user_message.into())
-        .map_err(Box::new)?;
-    
-    Ok(response)
+    fields
 }
 
 /// Creates an error response with the specified status code and message
@@ -1181,4 +1198,92 @@ pub fn create_error_response(status_code: u16, message: &str) -> Result<Response
         .map_err(Box::new)?;
     
     Ok(response)
-}
\ No newline at end of file
+}
+
+/// Creates a redirect response pointing the client at another URL
+/// 
+/// Used by URL canonicalization (canonical host, HTTPS, trailing slashes) and
+/// by the redirect rules file. Redirect responses carry the same security
+/// headers as every other response, plus:
+/// 
+/// - **Location**: The target URL
+/// - **Cache-Control**: Permanent redirects (301/308) may be cached by browsers
+///   and CloudFront; temporary ones (302/303/307) must not be
+/// 
+/// The body is a short plain-text note for clients that don't follow redirects.
+/// 
+/// ## Parameters:
+/// - `status_code`: Redirect status code (301, 302, 303, 307 or 308)
+/// - `location`: Target URL for the `Location` header
+pub fn create_redirect_response(status_code: u16, location: &str) -> Result<Response<Body>, Error> {
+    let cache_control = match status_code {
+        301 | 308 => "public, max-age=3600",
+        _ => "no-store",
+    };
+    
+    let response = Response::builder()
+        .status(status_code)
+        .header("location", location)
+        .header("cache-control", cache_control)
+        .header("content-type", "text/plain")  // Plain text for the redirect note
+        .header("x-frame-options", "DENY")  // Prevent clickjacking attacks
+        .header("x-content-type-options", "nosniff")  // Prevent MIME type sniffing
+        .header("content-security-policy", "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; font-src 'self'; connect-src 'self'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'")  // Restrict resource loading
+        .header("x-xss-protection", "1; mode=block")  // Enable XSS filtering with blocking mode
+        .header("strict-transport-security", "max-age=31536000")  // Enforce HTTPS for 1 year
+        .body(format!("Redirecting to {}", location).into())
+        .map_err(Box::new)?;
+    
+    Ok(response)
+}
+
+/// Creates an HTTP 200 response for a static asset
+/// 
+/// Assets get the same security headers as the embedded page. The
+/// `Content-Type` comes from the file extension (see
+/// `content::content_type_for`). Text assets are sent as text bodies;
+/// everything else is sent as binary, which `lambda_http` base64-encodes
+/// for the Function URL response.
+/// 
+/// Validators reported by the source are passed through as `ETag` and
+/// `Last-Modified` so browsers and CloudFront can revalidate.
+/// 
+/// ## Parameters:
+/// - `asset`: The asset to serve
+pub fn create_asset_response(asset: Asset) -> Result<Response<Body>, Error> {
+    let is_text = asset.content_type.starts_with("text/")
+        || asset.content_type == "application/javascript"
+        || asset.content_type == "application/json"
+        || asset.content_type == "image/svg+xml";
+    
+    let body = if is_text {
+        match String::from_utf8(asset.body) {
+            Ok(text) => Body::Text(text),
+            Err(e) => Body::Binary(e.into_bytes()),
+        }
+    } else {
+        Body::Binary(asset.body)
+    };
+    
+    let mut response_builder = Response::builder();
+    if let Some(etag) = &asset.etag {
+        response_builder = response_builder.header("etag", etag.as_str());
+    }
+    if let Some(last_modified) = asset.last_modified {
+        // HTTP dates are always GMT (RFC 9110 section 5.6.7)
+        response_builder = response_builder.header("last-modified", last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string());
+    }
+    
+    let response = response_builder
+        .status(200)
+        .header("content-type", asset.content_type)
+        .header("x-frame-options", "DENY")  // Prevent clickjacking attacks
+        .header("x-content-type-options", "nosniff")  // Prevent MIME type sniffing
+        .header("content-security-policy", "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; font-src 'self'; connect-src 'self'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'")  // Restrict resource loading
+        .header("x-xss-protection", "1; mode=block")  // Enable XSS filtering with blocking mode
+        .header("strict-transport-security", "max-age=31536000")  // Enforce HTTPS for 1 year
+        .body(body)
+        .map_err(Box::new)?;
+    
+    Ok(response)
+}
@@ -0,0 +1,391 @@
+// Redirect and rewrite rules loaded from a Netlify-style `_redirects` file
+// This module parses the rules file and matches request paths against it
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Status codes a rule may use
+///
+/// - **200**: Rewrite - serve `to` without changing the URL in the browser
+/// - **301, 302, 303, 307, 308**: Redirect - send the client to `to`
+/// - **404**: Rewrite with a 404 status (custom not-found page)
+/// - **410**: Gone - the content was removed on purpose
+const SUPPORTED_STATUSES: [u16; 8] = [200, 301, 302, 303, 307, 308, 404, 410];
+
+/// A single line of the rules file
+///
+/// ## Syntax:
+///
+/// ```text
+/// # from                 [query]     to                   [status]
+/// /home                              /                    301
+/// /blog/*                            /posts/:splat        301
+/// /news/:year/:month                 /archive/:year/:month
+/// /store                 id=:id      /products/:id        302
+/// /app/*                             /index.html          200
+/// /old-campaign                      /                    410
+/// ```
+///
+/// - `*` at the end of `from` captures the rest of the path as `:splat`
+/// - `:name` segments capture one path segment each
+/// - `key=value` / `key=:name` tokens between `from` and `to` match query parameters
+/// - The status defaults to 301; a trailing `!` (`200!`) forces the rule
+///
+/// ## Shadowing:
+///
+/// As on Netlify, content wins over rules: a rule only applies when the
+/// request path doesn't resolve to a file, directory index or listing, so
+/// `/* /index.html 200` leaves `/app.js` alone. Forced rules apply even
+/// when content exists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedirectRule {
+    /// Line number in the rules file (1-based), used in logs and CLI output
+    pub line: usize,
+    /// Path pattern to match
+    pub from: String,
+    /// Query parameter conditions: (name, literal value or `:placeholder`)
+    pub query: Vec<(String, String)>,
+    /// Target path or absolute URL, may contain placeholders
+    pub to: String,
+    /// Response status code
+    pub status: u16,
+    /// Whether the status had a `!` suffix (the rule applies even over existing content)
+    pub force: bool,
+}
+
+/// The result of matching a request against the rule set
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch {
+    /// Line number of the matching rule
+    pub line: usize,
+    /// The rule's `from` pattern (for logging)
+    pub from: String,
+    /// Target with placeholders substituted
+    pub target: String,
+    /// Status code from the rule
+    pub status: u16,
+    /// Whether the rule applies even when the request path has content
+    pub force: bool,
+}
+
+impl RuleMatch {
+    /// Short identifier for logs: `<line>:<from>`
+    pub fn label(&self) -> String {
+        format!("{}:{}", self.line, self.from)
+    }
+
+    /// Returns true for redirect statuses (3xx)
+    pub fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.status)
+    }
+}
+
+/// A syntax error in the rules file
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleParseError {
+    /// Line number (1-based)
+    pub line: usize,
+    /// Human-readable description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// An ordered list of redirect rules; the first matching rule wins
+#[derive(Clone, Default, PartialEq)]
+pub struct RuleSet {
+    rules: Vec<RedirectRule>,
+}
+
+// Configuration is logged at startup; print a count instead of every rule
+impl fmt::Debug for RuleSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RuleSet({} rules)", self.rules.len())
+    }
+}
+
+impl RuleSet {
+    /// Parses a rules file, collecting every error instead of stopping at the first
+    ///
+    /// Blank lines and lines starting with `#` are ignored. A file with any
+    /// invalid line is rejected as a whole so a typo can't silently drop a
+    /// redirect in production.
+    pub fn parse(content: &str) -> Result<RuleSet, Vec<RuleParseError>> {
+        let mut rules = Vec::new();
+        let mut errors = Vec::new();
+
+        for (index, raw_line) in content.lines().enumerate() {
+            let line = index + 1;
+            let text = raw_line.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+
+            match parse_rule(line, text) {
+                Ok(rule) => rules.push(rule),
+                Err(message) => errors.push(RuleParseError { line, message }),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(RuleSet { rules })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Reads and parses a rules file from disk
+    pub fn load(path: &Path) -> Result<RuleSet, Vec<RuleParseError>> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            vec![RuleParseError {
+                line: 0,
+                message: format!("cannot read {}: {}", path.display(), e),
+            }]
+        })?;
+        RuleSet::parse(&content)
+    }
+
+    /// Returns the parsed rules in file order
+    pub fn rules(&self) -> &[RedirectRule] {
+        &self.rules
+    }
+
+    /// Returns true when no rules are configured
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Finds the first rule matching the request path and raw query string
+    ///
+    /// Trailing slashes are ignored when comparing (`/blog` matches `/blog/`).
+    /// For redirects whose rule has no query conditions and whose target has
+    /// no query of its own, the original query string is carried over.
+    pub fn find_match(&self, path: &str, query: Option<&str>) -> Option<RuleMatch> {
+        let params = parse_query(query.unwrap_or(""));
+
+        self.rules.iter().find_map(|rule| {
+            let mut captures = match_path(&rule.from, path)?;
+
+            for (name, expected) in &rule.query {
+                let actual = params.iter().find(|(key, _)| key == name).map(|(_, value)| value)?;
+                match expected.strip_prefix(':') {
+                    Some(placeholder) => captures.push((placeholder.to_string(), actual.clone())),
+                    None if expected == actual => {}
+                    None => return None,
+                }
+            }
+
+            let mut target = substitute(&rule.to, &captures);
+
+            let is_redirect = (300..400).contains(&rule.status);
+            if is_redirect && rule.query.is_empty() && !target.contains('?') {
+                if let Some(query) = query.filter(|q| !q.is_empty()) {
+                    target.push('?');
+                    target.push_str(query);
+                }
+            }
+
+            Some(RuleMatch {
+                line: rule.line,
+                from: rule.from.clone(),
+                target,
+                status: rule.status,
+                force: rule.force,
+            })
+        })
+    }
+}
+
+/// Parses one non-comment line into a rule
+fn parse_rule(line: usize, text: &str) -> Result<RedirectRule, String> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    if tokens.len() < 2 {
+        return Err("expected at least `from to`".to_string());
+    }
+
+    let from = tokens[0];
+    validate_from(from)?;
+
+    // Query conditions sit between `from` and `to` and always contain '='
+    let mut index = 1;
+    let mut query = Vec::new();
+    while index < tokens.len() && is_query_condition(tokens[index]) {
+        let (key, value) = tokens[index].split_once('=').unwrap_or((tokens[index], ""));
+        if key.is_empty() {
+            return Err(format!("query condition `{}` has no parameter name", tokens[index]));
+        }
+        query.push((key.to_string(), value.to_string()));
+        index += 1;
+    }
+
+    let to = *tokens.get(index).ok_or("missing target after query conditions")?;
+    index += 1;
+
+    let (status, force) = match tokens.get(index) {
+        None => (301, false),
+        Some(token) => {
+            let (number, force) = match token.strip_suffix('!') {
+                Some(number) => (number, true),
+                None => (*token, false),
+            };
+            let status: u16 = number
+                .parse()
+                .map_err(|_| format!("invalid status `{}`", token))?;
+            if !SUPPORTED_STATUSES.contains(&status) {
+                return Err(format!(
+                    "unsupported status {} (expected one of {:?})",
+                    status, SUPPORTED_STATUSES
+                ));
+            }
+            (status, force)
+        }
+    };
+    index += 1;
+
+    if let Some(extra) = tokens.get(index) {
+        return Err(format!("unexpected token `{}` after status", extra));
+    }
+
+    validate_to(to, status, from, &query)?;
+
+    Ok(RedirectRule {
+        line,
+        from: from.to_string(),
+        query,
+        to: to.to_string(),
+        status,
+        force,
+    })
+}
+
+/// A token is a query condition if it contains '=' and is not a path or URL
+fn is_query_condition(token: &str) -> bool {
+    token.contains('=') && !token.starts_with('/') && !token.contains("://")
+}
+
+/// Checks the `from` pattern: absolute path, splat only as the final segment
+fn validate_from(from: &str) -> Result<(), String> {
+    if !from.starts_with('/') {
+        return Err(format!("source `{}` must start with /", from));
+    }
+    let segments: Vec<&str> = from.split('/').collect();
+    for (position, segment) in segments.iter().enumerate() {
+        if segment.contains('*') && (*segment != "*" || position != segments.len() - 1) {
+            return Err(format!("splat in `{}` must be the entire last segment", from));
+        }
+        if segment.starts_with(':') && segment.len() == 1 {
+            return Err(format!("placeholder in `{}` has no name", from));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the target against the status and the placeholders `from` defines
+fn validate_to(to: &str, status: u16, from: &str, query: &[(String, String)]) -> Result<(), String> {
+    let is_absolute = to.starts_with("http://") || to.starts_with("https://");
+    if !is_absolute && !to.starts_with('/') {
+        return Err(format!("target `{}` must be a path starting with / or an http(s) URL", to));
+    }
+    if is_absolute && (status == 200 || status == 404) {
+        return Err(format!("status {} rewrites must target a local path, not `{}`", status, to));
+    }
+
+    let mut defined: Vec<&str> = from
+        .split('/')
+        .filter_map(|segment| segment.strip_prefix(':'))
+        .collect();
+    if from.ends_with("/*") || from == "*" {
+        defined.push("splat");
+    }
+    defined.extend(query.iter().filter_map(|(_, value)| value.strip_prefix(':')));
+
+    for segment in to.split(['/', '?', '&', '=']) {
+        if let Some(name) = segment.strip_prefix(':') {
+            if !defined.contains(&name) {
+                return Err(format!("target uses :{} which `{}` does not define", name, from));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Matches a request path against a pattern, returning captured placeholders
+fn match_path(pattern: &str, path: &str) -> Option<Vec<(String, String)>> {
+    let pattern = trim_trailing_slash(pattern);
+    let path = trim_trailing_slash(path);
+
+    let mut captures = Vec::new();
+    let mut pattern_segments = pattern.split('/');
+    let mut path_segments = path.split('/');
+
+    loop {
+        match (pattern_segments.next(), path_segments.next()) {
+            (None, None) => return Some(captures),
+            (Some("*"), Some(first)) => {
+                let rest: Vec<&str> = std::iter::once(first).chain(path_segments).collect();
+                captures.push(("splat".to_string(), rest.join("/")));
+                return Some(captures);
+            }
+            // `/blog/*` also matches `/blog` itself with an empty splat
+            (Some("*"), None) => {
+                captures.push(("splat".to_string(), String::new()));
+                return Some(captures);
+            }
+            (Some(expected), Some(actual)) => match expected.strip_prefix(':') {
+                Some(name) if !actual.is_empty() => captures.push((name.to_string(), actual.to_string())),
+                Some(_) => return None,
+                None if expected == actual => {}
+                None => return None,
+            },
+            _ => return None,
+        }
+    }
+}
+
+/// Replaces `:name` placeholders in the target with captured values
+///
+/// Longer names are substituted first so `:id` never clobbers `:identifier`.
+/// Local targets are normalized so a captured value can't turn the result
+/// into a protocol-relative URL (`//evil.example`), which browsers would
+/// treat as an off-site redirect.
+fn substitute(target: &str, captures: &[(String, String)]) -> String {
+    let mut ordered: Vec<&(String, String)> = captures.iter().collect();
+    ordered.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+
+    let mut result = target.to_string();
+    for (name, value) in ordered {
+        result = result.replace(&format!(":{}", name), value);
+    }
+
+    if result.starts_with('/') {
+        let trimmed = result.trim_start_matches(['/', '\\']);
+        result = format!("/{}", trimmed);
+    }
+    result
+}
+
+/// Removes a trailing slash, keeping the root path intact
+fn trim_trailing_slash(path: &str) -> &str {
+    if path.len() > 1 {
+        path.trim_end_matches('/')
+    } else {
+        path
+    }
+}
+
+/// Splits a raw query string into (name, value) pairs without decoding
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (pair.to_string(), String::new()),
+        })
+        .collect()
+}
@@ -0,0 +1,356 @@
+// S3 content source
+// This module serves site content from an S3 bucket and prefix using the AWS SDK
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::Client;
+use chrono::{DateTime, Utc};
+use log::info;
+use tokio::sync::OnceCell;
+
+use crate::content::{content_type_for, Asset, ContentError, Revalidation};
+use crate::listing::DirectoryEntry;
+
+/// Seconds clients are told to wait before retrying after an S3 failure
+const S3_RETRY_AFTER_SECONDS: u32 = 5;
+
+/// Upper bound for a single S3 call, so a slow bucket can't eat the Lambda timeout
+const S3_OPERATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lifetime of presigned URLs handed out for objects too large to proxy
+const PRESIGNED_URL_EXPIRY: Duration = Duration::from_secs(300);
+
+/// Maximum number of ListObjectsV2 pages read for one directory listing
+const MAX_LISTING_PAGES: usize = 10;
+
+/// Default `S3_MAX_OBJECT_BYTES`: Lambda responses are capped at 6 MB, and
+/// binary bodies grow by a third when base64-encoded
+pub const DEFAULT_MAX_OBJECT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Site content stored under a prefix of an S3 bucket
+///
+/// ## Configuration:
+///
+/// - `CONTENT_S3_BUCKET`: Bucket name (enables this source)
+/// - `CONTENT_S3_PREFIX`: Key prefix, e.g. `site/` (default: bucket root)
+/// - `CONTENT_S3_ENDPOINT`: Custom endpoint for S3-compatible servers such as
+///   MinIO or LocalStack (`http://127.0.0.1:9000`); enables path-style URLs
+/// - `S3_MAX_OBJECT_BYTES`: Objects larger than this are not proxied through
+///   the Lambda; the client is redirected to a short-lived presigned URL
+///
+/// Credentials and region come from the standard AWS environment (the Lambda
+/// execution role in production, `AWS_PROFILE` or `AWS_ACCESS_KEY_ID` locally).
+/// The role needs `s3:GetObject` and `s3:ListBucket`: without `ListBucket`,
+/// S3 answers 403 instead of 404 for missing keys and every miss looks like
+/// an outage.
+///
+/// The SDK client is created on first use because loading the AWS
+/// configuration is async, and is then reused for the life of the container.
+#[derive(Clone)]
+pub struct S3Source {
+    /// Bucket name
+    pub bucket: String,
+    /// Key prefix without a leading slash; empty or ending in `/`
+    pub prefix: String,
+    /// Custom endpoint URL, if any
+    pub endpoint: Option<String>,
+    /// Largest object served through the Lambda, in bytes
+    pub max_object_bytes: u64,
+    client: Arc<OnceCell<Client>>,
+}
+
+// Compared by configuration; the lazily created client is not part of identity
+impl PartialEq for S3Source {
+    fn eq(&self, other: &Self) -> bool {
+        self.bucket == other.bucket
+            && self.prefix == other.prefix
+            && self.endpoint == other.endpoint
+            && self.max_object_bytes == other.max_object_bytes
+    }
+}
+
+impl fmt::Debug for S3Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Source")
+            .field("bucket", &self.bucket)
+            .field("prefix", &self.prefix)
+            .field("endpoint", &self.endpoint)
+            .field("max_object_bytes", &self.max_object_bytes)
+            .finish()
+    }
+}
+
+impl S3Source {
+    /// Creates a source for `bucket`, normalizing the prefix to `dir/` form
+    pub fn new(bucket: &str, prefix: &str, endpoint: Option<String>, max_object_bytes: u64) -> S3Source {
+        let prefix = prefix.trim_matches('/');
+        S3Source {
+            bucket: bucket.to_string(),
+            prefix: if prefix.is_empty() { String::new() } else { format!("{}/", prefix) },
+            endpoint,
+            max_object_bytes,
+            client: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Uses a preconfigured SDK client instead of loading the AWS environment
+    ///
+    /// Useful for tests and tools that point at a local S3-compatible server
+    /// with static credentials.
+    pub fn with_client(self, client: Client) -> S3Source {
+        S3Source {
+            client: Arc::new(OnceCell::new_with(Some(client))),
+            ..self
+        }
+    }
+
+    /// Maps a sanitized, percent-decoded, root-relative path to an object key
+    ///
+    /// `.` and `..` segments are dropped, mirroring the filesystem source, so a
+    /// key can never point outside the configured prefix.
+    pub fn key_for(&self, path: &str) -> String {
+        let segments: Vec<&str> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty() && *segment != "." && *segment != "..")
+            .collect();
+        let mut key = format!("{}{}", self.prefix, segments.join("/"));
+        if path.ends_with('/') && !segments.is_empty() {
+            key.push('/');
+        }
+        key
+    }
+
+    /// Returns the shared SDK client, creating it on first use
+    async fn client(&self) -> &Client {
+        self.client
+            .get_or_init(|| async {
+                let timeouts = aws_config::timeout::TimeoutConfig::builder()
+                    .operation_timeout(S3_OPERATION_TIMEOUT)
+                    .build();
+                let shared = aws_config::from_env().timeout_config(timeouts).load().await;
+
+                let mut builder = aws_sdk_s3::config::Builder::from(&shared);
+                if let Some(endpoint) = &self.endpoint {
+                    // S3-compatible servers rarely support virtual-hosted bucket names
+                    builder = builder.endpoint_url(endpoint).force_path_style(true);
+                }
+
+                info!("[{}] [S3] Client initialized: bucket={} prefix={} endpoint={}",
+                      Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+                      self.bucket,
+                      self.prefix,
+                      self.endpoint.as_deref().unwrap_or("default"));
+
+                Client::from_conf(builder.build())
+            })
+            .await
+    }
+
+    /// Downloads an object (GetObject)
+    pub async fn get(&self, path: &str) -> Result<Option<Asset>, ContentError> {
+        match self.get_object(path, None).await? {
+            Revalidation::Modified(asset) => Ok(asset),
+            // Unreachable without If-None-Match; treat it like a miss rather than panic
+            Revalidation::NotModified => Ok(None),
+        }
+    }
+
+    /// Downloads an object only if its ETag differs (conditional GetObject)
+    pub async fn revalidate(&self, path: &str, etag: &str) -> Result<Revalidation, ContentError> {
+        self.get_object(path, Some(etag)).await
+    }
+
+    /// Sends GetObject, optionally with `If-None-Match`, and reads the body
+    ///
+    /// The body is read chunk by chunk as it streams in from S3 and the
+    /// download is abandoned as soon as it exceeds `max_object_bytes`, so an
+    /// unexpectedly large upload can't exhaust the function's memory.
+    async fn get_object(&self, path: &str, if_none_match: Option<&str>) -> Result<Revalidation, ContentError> {
+        let key = self.key_for(path);
+        let request = self
+            .client()
+            .await
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .set_if_none_match(if_none_match.map(|etag| etag.to_string()));
+        let output = match request.send().await {
+            Ok(output) => output,
+            Err(e) if is_not_modified(&e) => return Ok(Revalidation::NotModified),
+            Err(e) if is_not_found(&e) => return Ok(Revalidation::Modified(None)),
+            Err(e) => return Err(unavailable(path, "GetObject", &key, &e)),
+        };
+
+        if let Some(size) = output.content_length().filter(|size| *size as u64 > self.max_object_bytes) {
+            return Err(ContentError::TooLarge { path: path.to_string(), size: size as u64 });
+        }
+
+        let etag = output.e_tag().map(|etag| etag.to_string());
+        let last_modified = output.last_modified().and_then(to_chrono);
+
+        let mut body = Vec::with_capacity(output.content_length().unwrap_or(0).max(0) as usize);
+        let mut stream = output.body;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| ContentError::Unavailable {
+                path: path.to_string(),
+                reason: format!("S3 body read failed for s3://{}/{}: {}", self.bucket, key, e),
+                retry_after: Some(S3_RETRY_AFTER_SECONDS),
+            })?;
+            body.extend_from_slice(&chunk);
+            if body.len() as u64 > self.max_object_bytes {
+                return Err(ContentError::TooLarge { path: path.to_string(), size: body.len() as u64 });
+            }
+        }
+
+        Ok(Revalidation::Modified(Some(Asset {
+            path: path.to_string(),
+            body,
+            content_type: content_type_for(path),
+            etag,
+            last_modified,
+        })))
+    }
+
+    /// Checks whether an object exists without downloading it (HeadObject)
+    pub async fn exists(&self, path: &str) -> Result<bool, ContentError> {
+        let key = self.key_for(path);
+        match self.client().await.head_object().bucket(&self.bucket).key(&key).send().await {
+            Ok(_) => Ok(true),
+            Err(e) if is_not_found(&e) => Ok(false),
+            Err(e) => Err(unavailable(path, "HeadObject", &key, &e)),
+        }
+    }
+
+    /// Lists the objects and common prefixes directly under a path (ListObjectsV2)
+    ///
+    /// S3 has no directories; a path counts as one when any key starts with
+    /// `<path>/`. Returns `Ok(None)` when nothing does.
+    pub async fn list(&self, path: &str) -> Result<Option<Vec<DirectoryEntry>>, ContentError> {
+        let mut key_prefix = self.key_for(path);
+        if !key_prefix.is_empty() && !key_prefix.ends_with('/') {
+            key_prefix.push('/');
+        }
+
+        let mut entries = Vec::new();
+        let mut continuation: Option<String> = None;
+        for _ in 0..MAX_LISTING_PAGES {
+            let output = self
+                .client()
+                .await
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(&key_prefix)
+                .delimiter("/")
+                .set_continuation_token(continuation.take())
+                .send()
+                .await
+                .map_err(|e| unavailable(path, "ListObjectsV2", &key_prefix, &e))?;
+
+            for common_prefix in output.common_prefixes() {
+                if let Some(name) = common_prefix
+                    .prefix()
+                    .and_then(|prefix| prefix.strip_prefix(key_prefix.as_str()))
+                    .map(|name| name.trim_end_matches('/'))
+                    .filter(|name| !name.is_empty())
+                {
+                    entries.push(DirectoryEntry { name: name.to_string(), is_dir: true, size: 0, modified: None });
+                }
+            }
+
+            for object in output.contents() {
+                if let Some(name) = object
+                    .key()
+                    .and_then(|key| key.strip_prefix(key_prefix.as_str()))
+                    .filter(|name| !name.is_empty() && !name.starts_with('.'))
+                {
+                    entries.push(DirectoryEntry {
+                        name: name.to_string(),
+                        is_dir: false,
+                        size: object.size().unwrap_or(0).max(0) as u64,
+                        modified: object.last_modified().and_then(to_chrono),
+                    });
+                }
+            }
+
+            match output.next_continuation_token() {
+                Some(token) if output.is_truncated().unwrap_or(false) => continuation = Some(token.to_string()),
+                _ => break,
+            }
+        }
+
+        if entries.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(entries))
+        }
+    }
+
+    /// Creates a short-lived presigned GetObject URL for an object
+    pub async fn presign(&self, path: &str) -> Result<String, ContentError> {
+        let key = self.key_for(path);
+        let presigning = PresigningConfig::expires_in(PRESIGNED_URL_EXPIRY).map_err(|e| ContentError::Io {
+            path: path.to_string(),
+            cause: e.to_string(),
+        })?;
+
+        let request = self
+            .client()
+            .await
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .presigned(presigning)
+            .await
+            .map_err(|e| unavailable(path, "presign GetObject", &key, &e))?;
+
+        Ok(request.uri().to_string())
+    }
+}
+
+/// Returns true for errors meaning "no such key" rather than a failure
+fn is_not_found<E, R>(error: &SdkError<E, R>) -> bool
+where
+    E: ProvideErrorMetadata,
+{
+    match error {
+        SdkError::ServiceError(service_error) => {
+            matches!(service_error.err().code(), Some("NoSuchKey") | Some("NotFound"))
+        }
+        _ => false,
+    }
+}
+
+/// Returns true when a conditional request was answered with 304 Not Modified
+fn is_not_modified<E>(error: &SdkError<E, aws_sdk_s3::config::http::HttpResponse>) -> bool {
+    error.raw_response().map(|response| response.status().as_u16()) == Some(304)
+}
+
+/// Maps an SDK failure to a 503 with a retry hint
+fn unavailable<E, R>(path: &str, operation: &str, key: &str, error: &SdkError<E, R>) -> ContentError
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+    R: fmt::Debug,
+{
+    let detail = match error {
+        SdkError::ServiceError(service_error) => {
+            let err = service_error.err();
+            format!("{} ({})", err.code().unwrap_or("unknown"), err.message().unwrap_or("no message"))
+        }
+        other => format!("{}", aws_sdk_s3::error::DisplayErrorContext(other)),
+    };
+
+    ContentError::Unavailable {
+        path: path.to_string(),
+        reason: format!("S3 {} failed for key {}: {}", operation, key, detail),
+        retry_after: Some(S3_RETRY_AFTER_SECONDS),
+    }
+}
+
+/// Converts an SDK timestamp to chrono
+fn to_chrono(time: &aws_sdk_s3::primitives::DateTime) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(time.secs(), time.subsec_nanos())
+}
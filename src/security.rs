@@ -3,7 +3,8 @@
 
 use std::path::Path;
 use std::fmt;
-use log::{info, warn};
+use log::{debug, warn};
+use crate::config::ServerConfig;
 
 /// Security error types for different security violation scenarios
 /// 
@@ -113,6 +114,24 @@ pub enum SecurityError {
         /// Description of why the header is suspicious
         reason: String,
     },
+
+    /// Request targets a host this server is not configured to serve
+    /// 
+    /// This error occurs when `ALLOWED_HOSTS` is configured and the request's
+    /// `Host` header names a different domain. Accepting arbitrary hosts lets
+    /// the same content appear under unexpected domains (duplicate content,
+    /// host header poisoning of absolute URLs).
+    /// 
+    /// **HTTP Status Code**: 421 Misdirected Request
+    /// **Security Impact**: Prevents host header abuse and domain spoofing
+    /// **User Message**: Generic misdirected request message
+    /// **Logging**: Requested host and path for monitoring
+    DisallowedHost {
+        /// The host that was requested (normalized, without port)
+        host: String,
+        /// The request path for context
+        path: String,
+    },
 }
 
 impl SecurityError {
@@ -134,6 +153,9 @@ impl SecurityError {
     /// - **413 Request Entity Too Large**: For oversized requests
     ///   - RequestTooLarge: Request exceeds configured size limits
     /// 
+    /// - **421 Misdirected Request**: For hosts outside the allowlist
+    ///   - DisallowedHost: Request targets a host we don't serve
+    /// 
     /// ## Security Considerations:
     /// 
     /// - Status codes follow HTTP standards for consistent client behavior
@@ -147,6 +169,7 @@ impl SecurityError {
             SecurityError::MaliciousPath { .. } => 400, // Bad Request
             SecurityError::InvalidCharacters { .. } => 400, // Bad Request
             SecurityError::SuspiciousHeaders { .. } => 400, // Bad Request
+            SecurityError::DisallowedHost { .. } => 421, // Misdirected Request
         }
     }
 
@@ -185,6 +208,9 @@ impl SecurityError {
             SecurityError::SuspiciousHeaders { .. } => {
                 "Bad Request. Request headers contain invalid content.".to_string()
             }
+            SecurityError::DisallowedHost { .. } => {
+                "Misdirected Request. This server does not serve the requested host.".to_string()
+            }
         }
     }
 
@@ -227,6 +253,24 @@ impl SecurityError {
             SecurityError::SuspiciousHeaders { header_name, reason } => {
                 format!("Suspicious header '{}': {}", header_name, reason)
             }
+            SecurityError::DisallowedHost { host, path } => {
+                format!("Host '{}' is not in the allowed host list (path '{}')", host, path)
+            }
+        }
+    }
+
+    /// Returns the variant name, e.g. `MaliciousPath`
+    ///
+    /// Used as the `SecurityError` metric dimension, so rejections can be
+    /// graphed and alarmed on by type without parsing detailed messages.
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            SecurityError::InvalidMethod { .. } => "InvalidMethod",
+            SecurityError::RequestTooLarge { .. } => "RequestTooLarge",
+            SecurityError::MaliciousPath { .. } => "MaliciousPath",
+            SecurityError::InvalidCharacters { .. } => "InvalidCharacters",
+            SecurityError::SuspiciousHeaders { .. } => "SuspiciousHeaders",
+            SecurityError::DisallowedHost { .. } => "DisallowedHost",
         }
     }
 }
@@ -306,9 +350,8 @@ impl fmt::Display for SecurityError {
 /// 3. Only contain safe, printable characters
 /// 4. Be safe for logging and display to administrators
 pub fn sanitize_path(path: &str) -> Result<String, SecurityError> {
-    // Log the original path for security monitoring
-    // This helps detect attack attempts and patterns
-    info!("Sanitizing request path: {}", path);
+    // Log the original path for debugging; rejected paths are logged at warn below
+    debug!("Sanitizing request path: {}", path);
     
     // Check for excessively long paths that could indicate DoS attempts
     // Long paths can consume memory and processing time
@@ -434,10 +477,83 @@ pub fn sanitize_path(path: &str) -> Result<String, SecurityError> {
     // We could normalize it further, but for our static server, the original is fine
     let sanitized = path.to_string();
     
-    info!("Path sanitization successful: {} -> {}", path, sanitized);
+    debug!("Path sanitization successful: {} -> {}", path, sanitized);
     Ok(sanitized)
 }
 
+/// Percent-decodes a sanitized request path for content lookup
+/// 
+/// Browsers send `caf%C3%A9.html` for `café.html`, and directory listings
+/// link to `%3Cscript%3E.txt`, so the path is decoded once, after
+/// `sanitize_path` and before it becomes a file path or S3 key. Decoding must
+/// not produce anything sanitization would have rejected, so the path is
+/// rejected when a segment:
+/// 
+/// - decodes to a `/` (`%2F`), which would split it into two segments
+/// - decodes to a NUL or other control character
+/// - decodes to `.` or `..`
+/// - contains a malformed escape (`%zz`) or isn't UTF-8 once decoded
+/// 
+/// Characters such as `<` or a space are allowed: they are valid in file
+/// names, and paths never reach a response body.
+pub fn decode_path(path: &str) -> Result<String, SecurityError> {
+    let mut decoded = String::with_capacity(path.len());
+    for (index, segment) in path.split('/').enumerate() {
+        if index > 0 {
+            decoded.push('/');
+        }
+        let segment = decode_segment(segment).ok_or_else(|| {
+            let error = SecurityError::InvalidCharacters {
+                field: "request_path".to_string(),
+                details: "Path contains an invalid percent-encoding".to_string(),
+            };
+            warn!("Security violation: {}", error.to_detailed_message());
+            error
+        })?;
+        
+        let reason = if segment.contains('/') {
+            Some("Path contains an encoded slash")
+        } else if segment.chars().any(char::is_control) {
+            Some("Path contains an encoded control character")
+        } else if segment == "." || segment == ".." {
+            Some("Path contains an encoded directory reference")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            let error = SecurityError::MaliciousPath {
+                path: path.to_string(),
+                reason: reason.to_string(),
+            };
+            warn!("Security violation: {}", error.to_detailed_message());
+            return Err(error);
+        }
+        decoded.push_str(&segment);
+    }
+    Ok(decoded)
+}
+
+/// Decodes the `%XX` escapes of one path segment; `None` if malformed or not UTF-8
+fn decode_segment(segment: &str) -> Option<String> {
+    if !segment.contains('%') {
+        return Some(segment.to_string());
+    }
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = segment.get(index + 1..index + 3).filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
 /// Validates the size of an HTTP request to prevent DoS attacks
 /// 
 /// ## Security Requirement:
@@ -505,7 +621,7 @@ pub fn validate_request_size(request: &lambda_http::Request) -> Result<(), Secur
     }
     
     // Log successful size validation for debugging
-    info!(
+    debug!(
         "Request size validation successful: {} bytes (limit: {} bytes)", 
         total_size, 
         MAX_REQUEST_SIZE
@@ -545,4 +661,72 @@ pub fn validate_http_method(method: &str) -> Result<(), SecurityError> {
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Validates the request host against the configured allowlist
+/// 
+/// ## Security Requirement:
+/// 
+/// A Lambda Function URL answers for its own `*.lambda-url.*.on.aws` domain and
+/// for any custom domain pointed at it. Without a host check the same content is
+/// served under every name, which causes duplicate-content SEO problems and lets
+/// clients supply arbitrary `Host` values.
+/// 
+/// ## Matching Rules:
+/// 
+/// - An empty `ALLOWED_HOSTS` list disables the check (every host is accepted)
+/// - When `CANONICAL_HOST` is set every host is accepted: `canonical_redirect`
+///   then sends anything but the canonical host to it (so the raw Function URL
+///   gets a 301 to the primary domain rather than a 421), and the allowlist only
+///   rejects requests without a host
+/// - Entries match exactly, or by suffix when written as `*.example.com`
+///   (which matches `www.example.com` but not `example.com` itself)
+/// 
+/// ## Parameters:
+/// - `host`: The normalized request host (lowercase, no port), if any
+/// - `path`: The request path, used for logging context
+/// - `config`: The server configuration holding the allowlist
+/// 
+/// ## Return Value:
+/// - `Ok(())`: Host is allowed
+/// - `Err(SecurityError::SuspiciousHeaders)`: No host was supplied (400)
+/// - `Err(SecurityError::DisallowedHost)`: Host is not in the allowlist (421)
+pub fn validate_host(host: Option<&str>, path: &str, config: &ServerConfig) -> Result<(), SecurityError> {
+    if config.allowed_hosts.is_empty() {
+        return Ok(());
+    }
+    
+    let host = match host {
+        Some(host) if !host.is_empty() => host,
+        _ => {
+            let error = SecurityError::SuspiciousHeaders {
+                header_name: "host".to_string(),
+                reason: "Host header is missing while a host allowlist is configured".to_string(),
+            };
+            warn!("Security violation: {}", error.to_detailed_message());
+            return Err(error);
+        }
+    };
+    
+    if config.canonical_host.is_some() {
+        return Ok(());
+    }
+    
+    let allowed = config.allowed_hosts.iter().any(|pattern| match pattern.strip_prefix("*.") {
+        Some(suffix) => host.len() > suffix.len() + 1
+            && host.ends_with(suffix)
+            && host[..host.len() - suffix.len()].ends_with('.'),
+        None => pattern == host,
+    });
+    
+    if !allowed {
+        let error = SecurityError::DisallowedHost {
+            host: host.to_string(),
+            path: path.to_string(),
+        };
+        warn!("Security violation: {}", error.to_detailed_message());
+        return Err(error);
+    }
+    
+    Ok(())
+}
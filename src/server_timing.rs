@@ -0,0 +1,78 @@
+// Server-Timing response header
+// This module times the pipeline stages of a request and formats them for browser devtools
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::Duration;
+
+use crate::lifecycle::ColdStart;
+
+tokio::task_local! {
+    /// Stage durations of the request being handled by the current task
+    static TIMINGS: RefCell<Vec<(&'static str, Duration)>>;
+}
+
+/// Server-Timing metric a pipeline stage is reported under
+///
+/// The validation stages are summed into one metric; they take microseconds
+/// each and only matter together.
+fn metric_name(stage: &'static str) -> &'static str {
+    match stage {
+        "size_check" | "method_check" | "sanitize_path" => "validate",
+        "content_lookup" => "lookup",
+        "response_building" => "render",
+        other => other,
+    }
+}
+
+/// Runs `future` while recording stage durations, and returns them
+///
+/// Durations are summed per metric, in the order each metric first ran.
+pub async fn collect<F: Future>(future: F) -> (F::Output, Vec<(&'static str, Duration)>) {
+    TIMINGS
+        .scope(RefCell::new(Vec::new()), async {
+            let output = future.await;
+            (output, TIMINGS.with(|timings| timings.take()))
+        })
+        .await
+}
+
+/// Adds the duration of a pipeline stage to the current request
+///
+/// Called by `telemetry::stage` and `telemetry::stage_async`; outside
+/// `collect` it does nothing.
+pub fn record(stage: &'static str, duration: Duration) {
+    let name = metric_name(stage);
+    let _ = TIMINGS.try_with(|timings| {
+        let mut timings = timings.borrow_mut();
+        match timings.iter_mut().find(|(metric, _)| *metric == name) {
+            Some((_, total)) => *total += duration,
+            None => timings.push((name, duration)),
+        }
+    });
+}
+
+/// Formats the `Server-Timing` header value
+///
+/// ## Format:
+///
+/// `cold-start;dur=41.870, validate;dur=0.012, lookup;dur=0.304, render;dur=0.051, total;dur=0.498`
+///
+/// Durations are milliseconds. `total` is the whole handler, so the rest of
+/// the time the browser shows as waiting is network, CloudFront and the
+/// Lambda service. `cold-start` marks the first request of a container; its
+/// duration is the init phase (see `lifecycle::mark_init_complete`), which
+/// happened before the request and is not part of `total`.
+pub fn header_value(timings: &[(&'static str, Duration)], total: Duration, cold_start: Option<ColdStart>) -> String {
+    let mut metrics: Vec<String> = Vec::with_capacity(timings.len() + 2);
+    match cold_start.map(|cold_start| cold_start.init_duration) {
+        Some(Some(init)) => metrics.push(format!("cold-start;dur={:.3}", init.as_secs_f64() * 1000.0)),
+        Some(None) => metrics.push("cold-start".to_string()),
+        None => {}
+    }
+    for (name, duration) in timings {
+        metrics.push(format!("{};dur={:.3}", name, duration.as_secs_f64() * 1000.0));
+    }
+    metrics.push(format!("total;dur={:.3}", total.as_secs_f64() * 1000.0));
+    metrics.join(", ")
+}
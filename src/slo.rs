@@ -0,0 +1,226 @@
+// Latency objectives: slow request thresholds and a rolling latency summary
+// This module decides which requests count as slow and keeps p50/p95/p99 over the most recent requests
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{info, Level};
+use serde_json::json;
+
+use crate::content::matches_prefix;
+use crate::logging::{json_event, log_format, LogFormat};
+
+/// Slow request threshold used when `SLOW_REQUEST_MS` is unset
+pub const DEFAULT_SLOW_REQUEST: Duration = Duration::from_millis(1000);
+
+/// Requests kept in the rolling latency window when `LATENCY_WINDOW` is unset
+pub const DEFAULT_LATENCY_WINDOW: usize = 1000;
+
+/// How often the local server logs the latency summary when `LATENCY_SUMMARY_SECONDS` is unset
+pub const DEFAULT_SUMMARY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Window installed by `install`; `None` until then
+static GLOBAL: OnceLock<LatencyWindow> = OnceLock::new();
+
+/// When a request is logged as slow
+///
+/// `default` comes from `SLOW_REQUEST_MS` and `routes` from
+/// `SLOW_REQUEST_ROUTES`, a comma-separated list of `prefix=ms` entries
+/// such as `/assets=20,/=50,/search=250`. Prefixes match like
+/// `SPA_EXCLUDE` (`/assets` covers `/assets/app.js` but not `/assets2`),
+/// and the longest matching prefix wins, so `/` only applies to paths no
+/// other entry covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowRequests {
+    /// Threshold for paths no route entry matches
+    pub default: Duration,
+    /// Per-prefix thresholds
+    pub routes: Vec<(String, Duration)>,
+}
+
+impl Default for SlowRequests {
+    fn default() -> Self {
+        SlowRequests { default: DEFAULT_SLOW_REQUEST, routes: Vec::new() }
+    }
+}
+
+impl SlowRequests {
+    /// Parses a `SLOW_REQUEST_ROUTES` value
+    ///
+    /// ## Errors:
+    ///
+    /// Returns a message naming the first entry that is not `/prefix=ms`.
+    pub fn parse_routes(value: &str) -> Result<Vec<(String, Duration)>, String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (prefix, ms) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("{} (expected /prefix=ms)", entry))?;
+                let prefix = prefix.trim();
+                if !prefix.starts_with('/') {
+                    return Err(format!("{} (prefix must start with /)", entry));
+                }
+                let ms: u64 = ms.trim().parse().map_err(|_| format!("{} (threshold must be milliseconds)", entry))?;
+                Ok((prefix.to_string(), Duration::from_millis(ms)))
+            })
+            .collect()
+    }
+
+    /// Returns the slow request threshold for `path`
+    pub fn threshold(&self, path: &str) -> Duration {
+        self.routes
+            .iter()
+            .filter(|(prefix, _)| matches_prefix(path, prefix))
+            .max_by_key(|(prefix, _)| prefix.trim_end_matches('*').trim_end_matches('/').len())
+            .map_or(self.default, |(_, threshold)| *threshold)
+    }
+}
+
+/// Latency percentiles over the requests in the window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencySummary {
+    /// Requests in the window
+    pub count: usize,
+    /// Requests handled since the previous summary was logged
+    pub new: u64,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Requests in the window that were over their slow request threshold
+    pub slow: usize,
+}
+
+/// Latencies of the most recent requests
+///
+/// A long-running local server logs a summary every
+/// `LATENCY_SUMMARY_SECONDS` and both modes log one at shutdown (see
+/// `main`). Per-request `[PERFORMANCE]` warnings only show the outliers;
+/// the summary shows whether the site as a whole meets its objective
+/// (a static site should answer p99 in single-digit milliseconds).
+///
+/// The window holds `LATENCY_WINDOW` samples of 16 bytes each; sorting a
+/// copy for the percentiles only happens when a summary is logged.
+#[derive(Debug)]
+pub struct LatencyWindow {
+    capacity: usize,
+    state: Mutex<WindowState>,
+}
+
+/// Samples and the count since the last summary
+#[derive(Debug, Default)]
+struct WindowState {
+    /// Latency and whether it was over the request's threshold, oldest first
+    samples: VecDeque<(Duration, bool)>,
+    new: u64,
+}
+
+/// Installs the process-wide window and returns it
+///
+/// The first call decides the capacity; `main` calls this unless
+/// `LATENCY_WINDOW=0`.
+pub fn install(capacity: usize) -> &'static LatencyWindow {
+    GLOBAL.get_or_init(|| LatencyWindow::new(capacity))
+}
+
+/// Returns the process-wide window, if one was installed
+pub fn global() -> Option<&'static LatencyWindow> {
+    GLOBAL.get()
+}
+
+impl LatencyWindow {
+    /// Creates an empty window keeping the latest `capacity` requests (at least one)
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        LatencyWindow {
+            capacity,
+            state: Mutex::new(WindowState { samples: VecDeque::with_capacity(capacity), new: 0 }),
+        }
+    }
+
+    /// Records one handled request
+    pub fn record(&self, latency: Duration, slow: bool) {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.samples.len() == self.capacity {
+            state.samples.pop_front();
+        }
+        state.samples.push_back((latency, slow));
+        state.new += 1;
+    }
+
+    /// Returns true when requests were recorded since the last summary
+    pub fn has_new(&self) -> bool {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).new > 0
+    }
+
+    /// Computes the percentiles of the window; `None` while it is empty
+    ///
+    /// Percentiles use the nearest-rank method, so every value reported is
+    /// the latency of an actual request.
+    pub fn summary(&self) -> Option<LatencySummary> {
+        let state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.samples.is_empty() {
+            return None;
+        }
+        let mut latencies: Vec<Duration> = state.samples.iter().map(|(latency, _)| *latency).collect();
+        latencies.sort_unstable();
+        let percentile = |p: f64| {
+            let rank = ((p / 100.0) * latencies.len() as f64).ceil() as usize;
+            latencies[rank.clamp(1, latencies.len()) - 1]
+        };
+        Some(LatencySummary {
+            count: latencies.len(),
+            new: state.new,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: latencies[latencies.len() - 1],
+            slow: state.samples.iter().filter(|(_, slow)| *slow).count(),
+        })
+    }
+
+    /// Logs the summary and starts counting new requests again
+    ///
+    /// ## Format:
+    ///
+    /// `[LATENCY] trigger=interval requests=1000 new=37 p50_ms=0.812 p95_ms=2.104 p99_ms=4.733 max_ms=12.019 slow=3`
+    ///
+    /// `trigger` is `interval` or `shutdown`. Nothing is logged while the
+    /// window is empty.
+    pub fn log_summary(&self, trigger: &str) {
+        let Some(summary) = self.summary() else {
+            return;
+        };
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).new = 0;
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+
+        if log_format() == LogFormat::Json {
+            info!(json_event = true; "{}", json_event(Level::Info, "latency_summary", json!({
+                "trigger": trigger,
+                "requests": summary.count,
+                "new": summary.new,
+                "p50_ms": ms(summary.p50),
+                "p95_ms": ms(summary.p95),
+                "p99_ms": ms(summary.p99),
+                "max_ms": ms(summary.max),
+                "slow": summary.slow,
+            })));
+            return;
+        }
+        info!("[{}] [LATENCY] trigger={} requests={} new={} p50_ms={:.3} p95_ms={:.3} p99_ms={:.3} max_ms={:.3} slow={}",
+              Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+              trigger,
+              summary.count,
+              summary.new,
+              ms(summary.p50),
+              ms(summary.p95),
+              ms(summary.p99),
+              ms(summary.max),
+              summary.slow);
+    }
+}
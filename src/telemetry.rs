@@ -0,0 +1,241 @@
+// OpenTelemetry tracing (optional `otel` feature)
+// This module wraps the request pipeline in spans and exports them over OTLP
+
+use std::future::Future;
+use std::time::Instant;
+
+use lambda_http::Request;
+
+use crate::context::RequestContext;
+#[cfg(feature = "otel")]
+use crate::context::TraceContext;
+use crate::server_timing;
+
+#[cfg(feature = "otel")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "otel")]
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, TracerProvider};
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::WithExportConfig;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::trace::SdkTracerProvider;
+#[cfg(feature = "otel")]
+use tracing::Instrument;
+#[cfg(feature = "otel")]
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+#[cfg(feature = "otel")]
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Service name reported when `OTEL_SERVICE_NAME` isn't set
+pub const DEFAULT_SERVICE_NAME: &str = "static-web-lambda";
+
+/// Tracer provider installed by `init`, kept for flushing
+#[cfg(feature = "otel")]
+static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Returns true when this binary was built with the `otel` feature
+pub const fn enabled() -> bool {
+    cfg!(feature = "otel")
+}
+
+/// Installs the OTLP exporter when an endpoint is configured
+///
+/// Tracing is on when `OTEL_EXPORTER_OTLP_ENDPOINT` (or the traces-only
+/// `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) is set; the exporter reads those and
+/// the other standard `OTEL_EXPORTER_OTLP_*` variables (headers, timeout)
+/// itself. Spans are sent as OTLP/HTTP protobuf, which every collector and
+/// the AWS Distro for OpenTelemetry Lambda layer accept.
+///
+/// Returns `Ok(false)` when no endpoint is set or the feature is off, so the
+/// caller can log whether tracing is active.
+pub fn init() -> Result<bool, String> {
+    #[cfg(feature = "otel")]
+    {
+        let configured = ["OTEL_EXPORTER_OTLP_ENDPOINT", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"]
+            .iter()
+            .any(|name| std::env::var(name).is_ok_and(|value| !value.trim().is_empty()));
+        if !configured {
+            return Ok(false);
+        }
+
+        let provider = tracer_provider(None)?;
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer(DEFAULT_SERVICE_NAME)));
+        tracing::subscriber::set_global_default(subscriber).map_err(|e| format!("Failed to install tracing: {}", e))?;
+        let _ = PROVIDER.set(provider);
+        Ok(true)
+    }
+    #[cfg(not(feature = "otel"))]
+    Ok(false)
+}
+
+/// Builds a provider that batches spans to an OTLP/HTTP endpoint
+///
+/// `endpoint` is the full traces URL (`http://collector:4318/v1/traces`);
+/// `None` uses the `OTEL_EXPORTER_OTLP_*` environment variables.
+#[cfg(feature = "otel")]
+pub fn tracer_provider(endpoint: Option<&str>) -> Result<SdkTracerProvider, String> {
+    let mut builder = opentelemetry_otlp::SpanExporter::builder().with_http();
+    if let Some(endpoint) = endpoint {
+        builder = builder.with_endpoint(endpoint);
+    }
+    let exporter = builder.build().map_err(|e| format!("Failed to create OTLP exporter: {}", e))?;
+
+    let service_name = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string());
+    let resource = opentelemetry_sdk::Resource::builder().with_service_name(service_name).build();
+
+    Ok(SdkTracerProvider::builder().with_batch_exporter(exporter).with_resource(resource).build())
+}
+
+/// Sends buffered spans now
+///
+/// Lambda freezes the container between invocations, so the batch exporter
+/// would otherwise hold spans until the next request (or lose them when the
+/// container is reclaimed). This blocks until the export completes; call it
+/// from `spawn_blocking` in async code.
+pub fn flush() {
+    #[cfg(feature = "otel")]
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(e) = provider.force_flush() {
+            log::warn!("Failed to flush spans: {}", e);
+        }
+    }
+}
+
+/// Exports remaining spans and stops the exporter
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            log::warn!("Failed to shut down tracing: {}", e);
+        }
+    }
+}
+
+/// The root span of one request
+///
+/// Without the `otel` feature this is an empty struct and every method is a
+/// no-op, so the handler has no feature checks of its own.
+pub struct RequestSpan {
+    #[cfg(feature = "otel")]
+    span: tracing::Span,
+}
+
+impl RequestSpan {
+    /// Opens the `request` span and aligns the request context with it
+    ///
+    /// An inbound `traceparent` becomes the remote parent. A trace adopted
+    /// from X-Ray is continued the same way, with the X-Ray `Parent` segment
+    /// as the parent span (as the OpenTelemetry X-Ray propagator maps it).
+    /// When a tracer is installed, the trace and span IDs it assigns replace
+    /// the generated ones in `context`, so log lines and the echoed
+    /// `traceparent` name the exported span. An X-Ray trace without a
+    /// `Parent` can't be continued and keeps its IDs, so logs still match X-Ray.
+    #[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+    pub fn start(context: &mut RequestContext, request: &Request) -> RequestSpan {
+        #[cfg(feature = "otel")]
+        {
+            let span = tracing::info_span!(
+                "request",
+                otel.kind = "server",
+                http.request.method = %request.method(),
+                url.path = %request.uri().path(),
+                http.response.status_code = tracing::field::Empty,
+                request_id = %context.request_id,
+            );
+
+            let from_xray = context.trace.parent_span_id.is_none()
+                && context
+                    .xray_trace_id
+                    .as_deref()
+                    .and_then(TraceContext::from_xray)
+                    .is_some_and(|xray| xray.trace_id == context.trace.trace_id);
+            let parent = match &context.trace.parent_span_id {
+                Some(parent) => Some(parent.as_str()),
+                None if from_xray => context.xray_trace_id.as_deref().and_then(xray_parent),
+                None => None,
+            };
+
+            let mut continued = false;
+            if let Some(parent) = parent {
+                let remote = SpanContext::new(
+                    TraceId::from_hex(&context.trace.trace_id).unwrap_or(TraceId::INVALID),
+                    SpanId::from_hex(parent).unwrap_or(SpanId::INVALID),
+                    TraceFlags::new(context.trace.flags),
+                    true,
+                    TraceState::default(),
+                );
+                if remote.is_valid() {
+                    continued = span.set_parent(opentelemetry::Context::new().with_remote_span_context(remote)).is_ok();
+                }
+            }
+
+            let otel_context = span.context();
+            let assigned = otel_context.span().span_context().clone();
+            if assigned.is_valid() && (continued || !from_xray) {
+                context.trace.trace_id = assigned.trace_id().to_string();
+                context.trace.span_id = assigned.span_id().to_string();
+            }
+
+            RequestSpan { span }
+        }
+        #[cfg(not(feature = "otel"))]
+        RequestSpan {}
+    }
+
+    /// Runs the pipeline inside the span
+    pub async fn instrument<F: Future>(&self, future: F) -> F::Output {
+        #[cfg(feature = "otel")]
+        return future.instrument(self.span.clone()).await;
+        #[cfg(not(feature = "otel"))]
+        future.await
+    }
+
+    /// Records the response status on the span
+    #[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+    pub fn record_status(&self, status: u16) {
+        #[cfg(feature = "otel")]
+        {
+            self.span.record("http.response.status_code", status);
+            if status >= 500 {
+                self.span.set_status(opentelemetry::trace::Status::error(format!("HTTP {}", status)));
+            }
+        }
+    }
+}
+
+/// Returns the `Parent` segment ID of an X-Ray trace header, if any
+#[cfg(feature = "otel")]
+fn xray_parent(header: &str) -> Option<&str> {
+    header
+        .split(';')
+        .find_map(|part| part.trim().strip_prefix("Parent="))
+        .filter(|parent| !parent.is_empty())
+}
+
+/// Runs a synchronous pipeline stage inside a child span named `name`
+///
+/// The stage's duration is also recorded for the `Server-Timing` header
+/// (see `server_timing::record`), with or without the `otel` feature.
+pub fn stage<T>(name: &'static str, work: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "otel")]
+    let _entered = tracing::info_span!("stage", otel.name = name).entered();
+    let started = Instant::now();
+    let output = work();
+    server_timing::record(name, started.elapsed());
+    output
+}
+
+/// Runs an asynchronous pipeline stage inside a child span named `name`
+///
+/// Timed for `Server-Timing` like `stage`.
+pub async fn stage_async<F: Future>(name: &'static str, future: F) -> F::Output {
+    let started = Instant::now();
+    #[cfg(feature = "otel")]
+    let output = future.instrument(tracing::info_span!("stage", otel.name = name)).await;
+    #[cfg(not(feature = "otel"))]
+    let output = future.await;
+    server_timing::record(name, started.elapsed());
+    output
+}
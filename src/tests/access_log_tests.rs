@@ -0,0 +1,102 @@
+// Tests for the access log
+// These tests render entries in each format and check client IP selection
+
+use crate::access_log::{client_ip, AccessLogEntry, AccessLogFormat};
+use crate::config::ServerConfig;
+use chrono::TimeZone;
+use lambda_http::aws_lambda_events::apigw::ApiGatewayV2httpRequestContext;
+use lambda_http::request::RequestContext;
+use lambda_http::{Body, RequestExt, http};
+use std::time::Duration;
+
+/// An entry for `GET /docs/?page=2` answered with 200
+fn sample_entry() -> AccessLogEntry {
+    AccessLogEntry {
+        remote_addr: Some("203.0.113.7".to_string()),
+        time: chrono::Utc.with_ymd_and_hms(2025, 10, 10, 13, 55, 36).unwrap(),
+        method: "GET".to_string(),
+        uri: "/docs/?page=2".to_string(),
+        protocol: "HTTP/1.1".to_string(),
+        host: Some("example.com".to_string()),
+        referer: None,
+        user_agent: Some("Mozilla/5.0 \"quoted\"\n".to_string()),
+        request_id: "req-1".to_string(),
+        status: 200,
+        bytes: 2326,
+        latency: Duration::from_micros(12_345),
+    }
+}
+
+/// Test the Common and Combined Log Formats
+#[test]
+fn test_standard_formats() {
+    let entry = sample_entry();
+    assert_eq!(
+        entry.render(&AccessLogFormat::Common),
+        "203.0.113.7 - - [10/Oct/2025:13:55:36 +0000] \"GET /docs/?page=2 HTTP/1.1\" 200 2326"
+    );
+    assert_eq!(
+        entry.render(&AccessLogFormat::Combined),
+        "203.0.113.7 - - [10/Oct/2025:13:55:36 +0000] \"GET /docs/?page=2 HTTP/1.1\" 200 2326 \"-\" \"Mozilla/5.0 \\\"quoted\\\"\\x0A\""
+    );
+
+    let empty = AccessLogEntry { remote_addr: None, bytes: 0, status: 304, ..sample_entry() };
+    assert!(empty.render(&AccessLogFormat::Common).starts_with("- - - ["));
+    assert!(empty.render(&AccessLogFormat::Common).ends_with("\" 304 -"));
+}
+
+/// Test custom templates, including braces and unknown variables
+#[test]
+fn test_template_format() {
+    let format = AccessLogFormat::parse("$remote_addr $status ${bytes}B $request_time $uri $args $host $request_id $latency_ms").unwrap().unwrap();
+    assert_eq!(sample_entry().render(&format), "203.0.113.7 200 2326B 0.012 /docs/ page=2 example.com req-1 12");
+
+    assert_eq!(AccessLogFormat::parse("combined").unwrap(), Some(AccessLogFormat::Combined));
+    assert_eq!(AccessLogFormat::parse("CLF").unwrap(), Some(AccessLogFormat::Common));
+    assert_eq!(AccessLogFormat::parse("off").unwrap(), None);
+    assert!(AccessLogFormat::parse("$remote_adr $status").unwrap_err().contains("$remote_adr"));
+    assert!(AccessLogFormat::parse("fancy").is_err());
+    // A lone `$` is kept literally
+    let format = AccessLogFormat::parse("$status $ ok").unwrap().unwrap();
+    assert_eq!(sample_entry().render(&format), "200 $ ok");
+}
+
+/// Test client IP selection from the request context and X-Forwarded-For
+#[test]
+fn test_client_ip_sources() {
+    let mut context = ApiGatewayV2httpRequestContext::default();
+    context.http.source_ip = Some("198.51.100.20".to_string());
+    let request = http::Request::builder()
+        .uri("/")
+        .header("x-forwarded-for", "203.0.113.7, 198.51.100.20")
+        .body(Body::Empty)
+        .unwrap()
+        .with_request_context(RequestContext::ApiGatewayV2(context));
+
+    let config = ServerConfig::default();
+    assert_eq!(client_ip(&request, &config).as_deref(), Some("198.51.100.20"), "The header is spoofable");
+    let trusting = ServerConfig { trust_forwarded_for: true, ..ServerConfig::default() };
+    assert_eq!(client_ip(&request, &trusting).as_deref(), Some("203.0.113.7"));
+
+    let bare = http::Request::builder()
+        .uri("/")
+        .header("x-forwarded-for", "192.0.2.1")
+        .body(Body::Empty)
+        .unwrap();
+    assert_eq!(client_ip(&bare, &config).as_deref(), Some("192.0.2.1"), "Fallback without a request context");
+}
+
+/// Test that an entry captures the request side from the handler's request
+#[test]
+fn test_entry_from_request() {
+    let request = http::Request::builder()
+        .uri("https://example.com/search?q=rust")
+        .header("referer", "https://example.com/")
+        .body(Body::Empty)
+        .unwrap();
+    let entry = AccessLogEntry::from_request(&request, &ServerConfig::default(), "req-2");
+    assert_eq!(entry.uri, "/search?q=rust");
+    assert_eq!(entry.referer.as_deref(), Some("https://example.com/"));
+    assert_eq!(entry.user_agent, None);
+    assert_eq!(entry.request_id, "req-2");
+}
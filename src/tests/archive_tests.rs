@@ -0,0 +1,149 @@
+// Tests for the zip and tar(.zst) site bundle source
+// These tests build small bundles in memory with the same crates used to read them
+
+use crate::archive::{ArchiveFormat, ArchiveSource};
+use crate::config::ServerConfig;
+use crate::content::{resolve, ContentSource, Resolution};
+use crate::handler::handle_request;
+use crate::tests::support::{get, TempSite};
+use std::io::{Cursor, Write};
+
+/// Files shared by every test bundle
+const FILES: [(&str, &str); 4] = [
+    ("./index.html", "<h1>bundle</h1>"),
+    ("./assets/app.js", "console.log('bundle');"),
+    ("./docs/index.html", "docs"),
+    ("./docs/guide/intro.html", "intro"),
+];
+
+/// Builds a tar archive from `(name, contents)` pairs
+fn tar_bundle(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(1_700_000_000);
+        header.set_cksum();
+        builder.append_data(&mut header, name, contents.as_bytes()).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+/// Builds a zip archive from `(name, contents)` pairs
+fn zip_bundle(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    writer.add_directory("docs/", options).unwrap();
+    for (name, contents) in files {
+        writer.start_file(name.trim_start_matches("./"), options).unwrap();
+        writer.write_all(contents.as_bytes()).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// Compresses a bundle the way `tar --zstd` does
+fn zstd(bytes: &[u8]) -> Vec<u8> {
+    ruzstd::encoding::compress_to_vec(bytes, ruzstd::encoding::CompressionLevel::Fastest)
+}
+
+/// Builds a configuration serving the given bundle
+fn bundle_config(archive: ArchiveSource) -> ServerConfig {
+    ServerConfig {
+        content_source: ContentSource::Archive(archive),
+        listing_prefixes: vec!["/docs".to_string()],
+        spa_fallback: false,
+        ..ServerConfig::default()
+    }
+}
+
+/// Test format detection from magic bytes
+#[test]
+fn test_detect_format() {
+    let tar = tar_bundle(&FILES);
+    assert_eq!(ArchiveFormat::detect(&tar), Some(ArchiveFormat::Tar));
+    assert_eq!(ArchiveFormat::detect(&zstd(&tar)), Some(ArchiveFormat::TarZstd));
+    assert_eq!(ArchiveFormat::detect(&zip_bundle(&FILES)), Some(ArchiveFormat::Zip));
+    assert_eq!(ArchiveFormat::detect(b"<html>not an archive</html>"), None);
+
+    let error = ArchiveSource::from_bytes("site.txt", b"plain text").unwrap_err();
+    assert!(error.contains("not a zip, tar or tar.zst"), "Got: {}", error);
+}
+
+/// Test that every format produces the same index and ETags
+#[test]
+fn test_formats_index_identically() {
+    let tar = tar_bundle(&FILES);
+    let bundles = [
+        ArchiveSource::from_bytes("site.tar", &tar).unwrap(),
+        ArchiveSource::from_bytes("site.tar.zst", &zstd(&tar)).unwrap(),
+        ArchiveSource::from_bytes("site.zip", &zip_bundle(&FILES)).unwrap(),
+    ];
+
+    for bundle in &bundles {
+        let paths: Vec<&str> = bundle.paths().collect();
+        assert_eq!(paths, ["/assets/app.js", "/docs/guide/intro.html", "/docs/index.html", "/index.html"]);
+    }
+
+    // ETags are content hashes, so they match across formats and rebuilt bundles
+    let etags: Vec<Option<String>> = bundles.iter().map(|b| b.get("/assets/app.js").unwrap().etag).collect();
+    assert!(etags[0].as_deref().unwrap().starts_with('"'));
+    assert!(etags.iter().all(|etag| *etag == etags[0]));
+    assert_ne!(bundles[0].get("/index.html").unwrap().etag, etags[0]);
+
+    let modified = bundles[0].get("/index.html").unwrap().last_modified.unwrap();
+    assert_eq!(modified.timestamp(), 1_700_000_000);
+}
+
+/// Test that entries escaping the site root are skipped
+#[test]
+fn test_unsafe_entries_are_skipped() {
+    let bundle = zip_bundle(&[("../outside.txt", "escape"), ("ok/../../up.txt", "escape"), ("safe.txt", "ok")]);
+    let archive = ArchiveSource::from_bytes("evil.zip", &bundle).unwrap();
+    let paths: Vec<&str> = archive.paths().collect();
+    assert_eq!(paths, ["/safe.txt"]);
+}
+
+/// Test serving a tar.zst bundle loaded from disk through the handler
+#[tokio::test]
+async fn test_serve_bundle_from_path() {
+    let dir = TempSite::new("bundle");
+    dir.file("site.tar.zst", zstd(&tar_bundle(&FILES)));
+    let path = dir.path().join("site.tar.zst");
+    let config = bundle_config(ArchiveSource::load(&path).unwrap());
+
+    let response = handle_request(get("/assets/app.js"), &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "application/javascript");
+    assert!(response.headers().get("etag").is_some());
+    assert!(response.headers().get("last-modified").is_some());
+
+    let response = handle_request(get("/docs"), &config).await.unwrap();
+    assert_eq!(response.status(), 301, "Directories are inferred from file paths");
+    assert_eq!(response.headers().get("location").unwrap(), "/docs/");
+
+    let response = handle_request(get("/missing.js"), &config).await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    assert!(ArchiveSource::load(&path.with_extension("missing")).is_err());
+}
+
+/// Test listings of directories that only exist as path prefixes
+#[tokio::test]
+async fn test_bundle_listing() {
+    let archive = ArchiveSource::from_bytes("site.tar", &tar_bundle(&FILES)).unwrap();
+    let config = bundle_config(archive);
+
+    match resolve("/docs/guide/", &config, true).await.unwrap() {
+        Resolution::Listing(entries) => {
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].name, "intro.html");
+            assert_eq!(entries[0].size, 5);
+        }
+        other => panic!("Expected listing, got {:?}", other),
+    }
+
+    let entries = config.content_source.list("/").await.unwrap().unwrap();
+    let names: Vec<(&str, bool)> = entries.iter().map(|e| (e.name.as_str(), e.is_dir)).collect();
+    assert_eq!(names, [("assets", true), ("docs", true), ("index.html", false)]);
+}
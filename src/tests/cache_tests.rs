@@ -0,0 +1,141 @@
+// Tests for the in-memory content cache
+// These tests serve a small directory from the system temp directory through the cache
+
+use crate::cache::{CacheStats, ContentCache};
+use crate::config::ServerConfig;
+use crate::content::{resolve, Resolution};
+use crate::handler::handle_request;
+use crate::tests::support::{get, TempSite};
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Creates a fresh content directory with two small files
+fn site(name: &str) -> TempSite {
+    let site = TempSite::new(&format!("cache-{}", name));
+    site.file("index.html", "home").file("app.js", "console.log(1);");
+    site
+}
+
+/// Builds a cache with the given byte limit and TTLs (in milliseconds)
+fn cache(max_bytes: u64, ttl_ms: u64, negative_ttl_ms: u64) -> Arc<ContentCache> {
+    Arc::new(ContentCache::new(max_bytes, Duration::from_millis(ttl_ms), Duration::from_millis(negative_ttl_ms)))
+}
+
+/// Returns the body of a found asset
+fn body(resolution: Resolution) -> String {
+    match resolution {
+        Resolution::Found(asset) => String::from_utf8(asset.body).unwrap(),
+        other => panic!("Expected asset, got {:?}", other),
+    }
+}
+
+/// Test that repeated lookups are served from memory
+#[tokio::test]
+async fn test_cache_hits_and_misses() {
+    let site = site("hits");
+    let source = site.source();
+    let cache = cache(1024 * 1024, 60_000, 60_000);
+
+    let first = cache.get(&source, "/app.js").await.unwrap().unwrap();
+    assert!(first.etag.is_some(), "Files get an ETag so they can be revalidated");
+
+    // Served from memory: deleting the file doesn't change the answer until the TTL expires
+    fs::remove_file(site.path().join("app.js")).unwrap();
+    assert_eq!(cache.get(&source, "/app.js").await.unwrap(), Some(first));
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+}
+
+/// Test that missing paths are cached for the negative TTL only
+#[tokio::test]
+async fn test_negative_caching() {
+    let site = site("negative");
+    let source = site.source();
+    let cache = cache(1024 * 1024, 60_000, 50);
+
+    assert_eq!(cache.get(&source, "/late.js").await.unwrap(), None);
+    site.file("late.js", "late");
+    assert_eq!(cache.get(&source, "/late.js").await.unwrap(), None, "Misses are remembered");
+    assert!(!cache.exists(&source, "/late.js").await.unwrap());
+
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert!(cache.get(&source, "/late.js").await.unwrap().is_some(), "Negative entries expire");
+}
+
+/// Test that expired entries are revalidated instead of re-read
+#[tokio::test]
+async fn test_revalidation() {
+    let site = site("revalidate");
+    let source = site.source();
+    let cache = cache(1024 * 1024, 0, 0);
+
+    cache.get(&source, "/app.js").await.unwrap();
+    cache.get(&source, "/app.js").await.unwrap();
+    assert_eq!(cache.stats().revalidations, 1, "An unchanged file is only revalidated");
+
+    site.file("app.js", "console.log('deployed');");
+    let asset = cache.get(&source, "/app.js").await.unwrap().unwrap();
+    assert_eq!(asset.body, b"console.log('deployed');");
+    assert_eq!(cache.stats().revalidations, 1);
+
+    fs::remove_file(site.path().join("app.js")).unwrap();
+    assert_eq!(cache.get(&source, "/app.js").await.unwrap(), None, "Deleted files stop being served");
+}
+
+/// Test least-recently-used eviction under the byte limit
+#[tokio::test]
+async fn test_lru_eviction() {
+    let site = site("evict");
+    for name in ["a", "b", "c"] {
+        site.file(&format!("{}.txt", name), "x".repeat(400));
+    }
+    let source = site.source();
+    // Room for two 400-byte files plus overhead, not three
+    let cache = cache(1500, 60_000, 60_000);
+
+    cache.get(&source, "/a.txt").await.unwrap();
+    cache.get(&source, "/b.txt").await.unwrap();
+    cache.get(&source, "/a.txt").await.unwrap();
+    cache.get(&source, "/c.txt").await.unwrap();
+
+    let stats = cache.stats();
+    assert_eq!(stats.evictions, 1);
+    assert_eq!(stats.entries, 2);
+    assert!(stats.bytes <= 1500);
+
+    // `b` was least recently used, so `a` is still a hit and `b` a miss
+    cache.get(&source, "/a.txt").await.unwrap();
+    cache.get(&source, "/b.txt").await.unwrap();
+    let after = cache.stats();
+    assert_eq!(after.hits, stats.hits + 1);
+    assert_eq!(after.misses, stats.misses + 1);
+
+    // Assets larger than the whole cache are served but never stored
+    let tiny = self::cache(100, 60_000, 60_000);
+    assert!(tiny.get(&source, "/a.txt").await.unwrap().is_some());
+    assert_eq!(tiny.stats(), CacheStats { misses: 1, ..CacheStats::default() });
+}
+
+/// Test that configuration clones share one cache, as warm invocations do
+#[tokio::test]
+async fn test_cache_shared_across_requests() {
+    let site = site("shared");
+    let config = ServerConfig {
+        content_source: site.source(),
+        content_cache: Some(cache(1024 * 1024, 60_000, 60_000)),
+        ..ServerConfig::default()
+    };
+    let clone = config.clone();
+
+    assert_eq!(body(resolve("/app.js", &config, true).await.unwrap()), "console.log(1);");
+    assert_eq!(body(resolve("/app.js", &clone, true).await.unwrap()), "console.log(1);");
+
+    let response = handle_request(get("/app.js"), &clone).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("etag").unwrap().to_str().unwrap().starts_with("W/\""));
+
+    let stats = config.content_cache.as_ref().unwrap().stats();
+    assert_eq!((stats.hits, stats.misses), (2, 1));
+}
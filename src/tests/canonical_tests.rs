@@ -0,0 +1,293 @@
+// Tests for host validation and canonical URL redirects
+// These tests use explicit ServerConfig values instead of environment variables
+// so they can run in parallel with the rest of the suite
+
+use crate::canonical::{canonical_path, canonical_redirect};
+use crate::config::{normalize_host, ServerConfig, TrailingSlash};
+use crate::handler::handle_request;
+use crate::security::{validate_host, SecurityError};
+use lambda_http::{Body, http};
+
+/// Builds a configuration with the given allowlist and canonical host
+fn host_config(allowed: &[&str], canonical: Option<&str>) -> ServerConfig {
+    ServerConfig {
+        allowed_hosts: allowed.iter().map(|host| host.to_string()).collect(),
+        canonical_host: canonical.map(|host| host.to_string()),
+        ..ServerConfig::default()
+    }
+}
+
+/// Test that host normalization lowercases and strips ports
+#[test]
+fn test_normalize_host() {
+    assert_eq!(normalize_host("Example.COM"), "example.com");
+    assert_eq!(normalize_host("127.0.0.1:3000"), "127.0.0.1");
+    assert_eq!(normalize_host("[::1]:3000"), "[::1]");
+    assert_eq!(normalize_host(" www.example.com "), "www.example.com");
+}
+
+/// Test that an empty allowlist accepts any host, including a missing one
+#[test]
+fn test_validate_host_disabled_by_default() {
+    let config = ServerConfig::default();
+
+    assert!(validate_host(Some("anything.example"), "/", &config).is_ok());
+    assert!(validate_host(None, "/", &config).is_ok());
+}
+
+/// Test exact and wildcard allowlist matching
+#[test]
+fn test_validate_host_allowlist() {
+    let config = host_config(&["example.com", "*.example.org"], None);
+
+    assert!(validate_host(Some("example.com"), "/", &config).is_ok());
+    assert!(validate_host(Some("www.example.org"), "/", &config).is_ok());
+
+    // Wildcards only match subdomains, not the bare domain or lookalikes
+    for host in ["example.org", "evilexample.org", "www.example.com", "attacker.test"] {
+        let result = validate_host(Some(host), "/", &config);
+        assert_eq!(
+            result,
+            Err(SecurityError::DisallowedHost { host: host.to_string(), path: "/".to_string() }),
+            "Host '{}' should be rejected",
+            host
+        );
+    }
+}
+
+/// Test that disallowed hosts map to 421 and missing hosts to 400
+#[test]
+fn test_validate_host_status_codes() {
+    let config = host_config(&["example.com"], None);
+
+    let disallowed = validate_host(Some("other.example"), "/", &config).unwrap_err();
+    assert_eq!(disallowed.to_http_status_code(), 421);
+
+    let missing = validate_host(None, "/", &config).unwrap_err();
+    assert_eq!(missing.to_http_status_code(), 400);
+}
+
+/// Test that the handler rejects requests for hosts outside the allowlist
+#[tokio::test]
+async fn test_handler_rejects_disallowed_host() {
+    let config = host_config(&["www.example.com"], None);
+
+    let request = http::Request::builder()
+        .method("GET")
+        .uri("/")
+        .header("host", "spoofed.example.net")
+        .body(Body::Empty)
+        .expect("Failed to build request");
+
+    let response = handle_request(request, &config).await.unwrap();
+    assert_eq!(response.status(), 421, "Disallowed host should return 421");
+
+    let allowed = http::Request::builder()
+        .method("GET")
+        .uri("/")
+        .header("host", "WWW.Example.com:443")
+        .body(Body::Empty)
+        .expect("Failed to build request");
+
+    let response = handle_request(allowed, &config).await.unwrap();
+    assert_eq!(response.status(), 200, "Allowed host should be served");
+}
+
+/// Test that non-canonical hosts are redirected with path and query preserved
+#[tokio::test]
+async fn test_handler_redirects_to_canonical_host() {
+    let config = host_config(
+        &["www.example.com", "*.lambda-url.eu-west-1.on.aws"],
+        Some("www.example.com"),
+    );
+
+    let request = http::Request::builder()
+        .method("GET")
+        .uri("/blog/post?page=2&sort=asc")
+        .header("host", "abc123.lambda-url.eu-west-1.on.aws")
+        .body(Body::Empty)
+        .expect("Failed to build request");
+
+    let response = handle_request(request, &config).await.unwrap();
+    assert_eq!(response.status(), 301, "Non-canonical host should be redirected");
+    assert_eq!(
+        response.headers().get("location").unwrap(),
+        "https://www.example.com/blog/post?page=2&sort=asc"
+    );
+    assert_eq!(response.headers().get("x-frame-options").unwrap(), "DENY");
+
+    // Requests already on the canonical host are served normally
+    let canonical = http::Request::builder()
+        .method("GET")
+        .uri("/")
+        .header("host", "www.example.com")
+        .body(Body::Empty)
+        .expect("Failed to build request");
+
+    let response = handle_request(canonical, &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+/// Test that the canonical-host redirect wins over the allowlist
+#[tokio::test]
+async fn test_handler_redirects_unlisted_host_to_canonical_host() {
+    let config = host_config(&["www.example.com"], Some("www.example.com"));
+
+    let request = http::Request::builder()
+        .method("GET")
+        .uri("/docs?v=2")
+        .header("host", "abc123.lambda-url.us-east-1.on.aws")
+        .body(Body::Empty)
+        .expect("Failed to build request");
+
+    let response = handle_request(request, &config).await.unwrap();
+    assert_eq!(response.status(), 301, "Function URL host should be redirected, not rejected");
+    assert_eq!(response.headers().get("location").unwrap(), "https://www.example.com/docs?v=2");
+
+    // Without a host there is nothing to redirect from
+    let request = http::Request::builder()
+        .method("GET")
+        .uri("/docs")
+        .body(Body::Empty)
+        .expect("Failed to build request");
+
+    let response = handle_request(request, &config).await.unwrap();
+    assert_eq!(response.status(), 400);
+}
+
+/// Test that X-Forwarded-Host is only honored when explicitly trusted
+#[tokio::test]
+async fn test_forwarded_host_requires_trust() {
+    let mut config = host_config(&["www.example.com"], None);
+
+    let build_request = || {
+        http::Request::builder()
+            .method("GET")
+            .uri("/")
+            .header("host", "abc123.lambda-url.eu-west-1.on.aws")
+            .header("x-forwarded-host", "www.example.com")
+            .body(Body::Empty)
+            .expect("Failed to build request")
+    };
+
+    let response = handle_request(build_request(), &config).await.unwrap();
+    assert_eq!(response.status(), 421, "Untrusted X-Forwarded-Host should be ignored");
+
+    config.trust_forwarded_host = true;
+    let response = handle_request(build_request(), &config).await.unwrap();
+    assert_eq!(response.status(), 200, "Trusted X-Forwarded-Host should be used");
+}
+
+/// Test the individual path canonicalization rules
+#[test]
+fn test_canonical_path_rules() {
+    let config = ServerConfig {
+        collapse_slashes: true,
+        lowercase_paths: true,
+        trailing_slash: TrailingSlash::Add,
+        ..ServerConfig::default()
+    };
+
+    let (path, rules) = canonical_path("/Docs//Intro", &config);
+    assert_eq!(path, "/docs/intro/");
+    assert_eq!(rules, vec!["collapse_slashes", "lowercase", "trailing_slash"]);
+
+    // Paths that look like files never get a trailing slash
+    let (path, rules) = canonical_path("/assets/app.js", &config);
+    assert_eq!(path, "/assets/app.js");
+    assert!(rules.is_empty());
+
+    let strip = ServerConfig { trailing_slash: TrailingSlash::Strip, ..ServerConfig::default() };
+    assert_eq!(canonical_path("/docs/", &strip).0, "/docs");
+    assert_eq!(canonical_path("/", &strip).0, "/", "Root path must keep its slash");
+}
+
+/// Test that default configuration never redirects
+#[test]
+fn test_canonical_redirect_disabled_by_default() {
+    let config = ServerConfig::default();
+
+    assert_eq!(canonical_redirect(Some("example.com"), "http", "/Docs//x/", Some("a=1"), &config), None);
+}
+
+/// Test that HTTPS upgrades use X-Forwarded-Proto and preserve the query
+#[tokio::test]
+async fn test_handler_redirects_http_to_https() {
+    let config = ServerConfig { force_https: true, redirect_status: 308, ..ServerConfig::default() };
+
+    let request = http::Request::builder()
+        .method("GET")
+        .uri("/pricing?plan=pro")
+        .header("host", "www.example.com")
+        .header("x-forwarded-proto", "http")
+        .body(Body::Empty)
+        .expect("Failed to build request");
+
+    let response = handle_request(request, &config).await.unwrap();
+    assert_eq!(response.status(), 308);
+    assert_eq!(
+        response.headers().get("location").unwrap(),
+        "https://www.example.com/pricing?plan=pro"
+    );
+
+    // Requests that already arrived over HTTPS are served
+    let secure = http::Request::builder()
+        .method("GET")
+        .uri("/pricing")
+        .header("host", "www.example.com")
+        .header("x-forwarded-proto", "https")
+        .body(Body::Empty)
+        .expect("Failed to build request");
+
+    let response = handle_request(secure, &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+/// Test that the HTTPS upgrade is skipped when there is no host to redirect to
+#[test]
+fn test_https_upgrade_requires_host() {
+    let config = ServerConfig { force_https: true, trailing_slash: TrailingSlash::Strip, ..ServerConfig::default() };
+
+    assert_eq!(canonical_redirect(None, "http", "/pricing", None, &config), None);
+
+    // Path rules still apply, without claiming an upgrade they can't perform
+    let redirect = canonical_redirect(None, "http", "/pricing/", Some("plan=pro"), &config).unwrap();
+    assert_eq!(redirect.location, "/pricing?plan=pro");
+    assert_eq!(redirect.rule, "trailing_slash");
+}
+
+/// Test that path-only redirects are root-relative and combine into one hop
+#[tokio::test]
+async fn test_handler_path_canonicalization_single_redirect() {
+    let config = ServerConfig {
+        canonical_host: Some("www.example.com".to_string()),
+        collapse_slashes: true,
+        lowercase_paths: true,
+        trailing_slash: TrailingSlash::Strip,
+        ..ServerConfig::default()
+    };
+
+    // Path-only change: root-relative Location
+    let request = http::Request::builder()
+        .method("GET")
+        .uri("/Blog//Post/?id=7")
+        .header("host", "www.example.com")
+        .body(Body::Empty)
+        .expect("Failed to build request");
+
+    let response = handle_request(request, &config).await.unwrap();
+    assert_eq!(response.status(), 301);
+    assert_eq!(response.headers().get("location").unwrap(), "/blog/post?id=7");
+
+    // Host and path changes are combined into a single absolute redirect
+    let request = http::Request::builder()
+        .method("GET")
+        .uri("/Blog/")
+        .header("host", "example.com")
+        .body(Body::Empty)
+        .expect("Failed to build request");
+
+    let response = handle_request(request, &config).await.unwrap();
+    assert_eq!(response.status(), 301);
+    assert_eq!(response.headers().get("location").unwrap(), "https://www.example.com/blog");
+}
@@ -0,0 +1,112 @@
+// Tests for directory index and clean URL resolution
+// These tests build a small documentation site under the system temp directory
+
+use crate::config::{ServerConfig, TrailingSlash};
+use crate::content::{resolve, Candidate, Resolution};
+use crate::handler::handle_request;
+use crate::rules::RuleSet;
+use crate::tests::support::{get, TempSite};
+
+/// Creates a fresh content directory with pages in every clean URL form
+fn site(name: &str) -> TempSite {
+    let site = TempSite::new(&format!("clean-{}", name));
+    site.file("index.html", "home")
+        .file("about.html", "about page")
+        .file("docs/index.html", "docs index")
+        .file("both.html", "both as file")
+        .file("both/index.html", "both as directory");
+    site
+}
+
+/// Builds a configuration serving the given directory without SPA fallback
+fn site_config(site: &TempSite) -> ServerConfig {
+    ServerConfig {
+        content_source: site.source(),
+        spa_fallback: false,
+        ..ServerConfig::default()
+    }
+}
+
+/// Returns the served body for a resolution that found an asset
+fn found_body(resolution: Resolution) -> String {
+    match resolution {
+        Resolution::Found(asset) => String::from_utf8(asset.body).unwrap(),
+        other => panic!("Expected asset, got {:?}", other),
+    }
+}
+
+/// Test parsing of RESOLUTION_ORDER entries
+#[test]
+fn test_candidate_parse() {
+    assert_eq!(Candidate::parse(" HTML "), Some(Candidate::Html));
+    assert_eq!(Candidate::parse("index"), Some(Candidate::Index));
+    assert_eq!(Candidate::parse("php"), None);
+}
+
+/// Test the default exact, html, index order
+#[tokio::test]
+async fn test_resolve_clean_urls() {
+    let site = site("resolve");
+    let config = site_config(&site);
+
+    assert_eq!(found_body(resolve("/docs/", &config, true).await.unwrap()), "docs index");
+    assert_eq!(found_body(resolve("/about", &config, true).await.unwrap()), "about page");
+    assert_eq!(found_body(resolve("/both", &config, true).await.unwrap()), "both as file", "html comes before index");
+    assert_eq!(resolve("/docs", &config, true).await.unwrap(), Resolution::DirectoryRedirect("/docs/".to_string()));
+    assert_eq!(found_body(resolve("/docs", &config, false).await.unwrap()), "docs index");
+    assert_eq!(resolve("/about/", &config, true).await.unwrap(), Resolution::NotFound, "Slash paths only try the index");
+    assert_eq!(resolve("/about.html.html", &config, true).await.unwrap(), Resolution::NotFound);
+}
+
+/// Test a custom resolution order
+#[tokio::test]
+async fn test_resolve_custom_order() {
+    let site = site("order");
+    let config = ServerConfig {
+        resolution_order: vec![Candidate::Index, Candidate::Html],
+        ..site_config(&site)
+    };
+
+    assert_eq!(resolve("/both", &config, true).await.unwrap(), Resolution::DirectoryRedirect("/both/".to_string()));
+    assert_eq!(found_body(resolve("/about", &config, true).await.unwrap()), "about page");
+
+    let exact_only = ServerConfig { resolution_order: vec![Candidate::Exact], ..site_config(&site) };
+    assert_eq!(resolve("/about", &exact_only, true).await.unwrap(), Resolution::NotFound);
+}
+
+/// Test directory redirects through the handler
+#[tokio::test]
+async fn test_handler_directory_redirect() {
+    let site = site("handler");
+    let config = site_config(&site);
+
+    let response = handle_request(get("/docs?page=2"), &config).await.unwrap();
+    assert_eq!(response.status(), 301);
+    assert_eq!(response.headers().get("location").unwrap(), "/docs/?page=2");
+
+    let response = handle_request(get("/docs/"), &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/html");
+
+    let response = handle_request(get("/about"), &config).await.unwrap();
+    assert_eq!(response.status(), 200, "Clean URLs are served without redirecting");
+}
+
+/// Test that directory redirects never fight TRAILING_SLASH=strip or rewrites
+#[tokio::test]
+async fn test_handler_directory_without_redirect() {
+    let site = site("without-redirect");
+    let strip = ServerConfig { trailing_slash: TrailingSlash::Strip, ..site_config(&site) };
+
+    let response = handle_request(get("/docs"), &strip).await.unwrap();
+    assert_eq!(response.status(), 200, "Strip mode serves the index at the slashless URL");
+
+    let rewrite = ServerConfig {
+        redirect_rules: RuleSet::parse("/guide /docs 200").unwrap(),
+        ..site_config(&site)
+    };
+
+    let response = handle_request(get("/guide"), &rewrite).await.unwrap();
+    assert_eq!(response.status(), 200, "Rewrite targets are served, not redirected");
+    assert!(response.headers().get("location").is_none());
+}
@@ -0,0 +1,93 @@
+// Tests for request and trace IDs
+// These tests cover adopting inbound X-Request-Id and traceparent headers
+
+use crate::config::ServerConfig;
+use crate::context::{is_valid_request_id, RequestContext, TraceContext};
+use crate::handler::handle_request;
+use crate::tests::support::{get, get_with};
+use lambda_http::RequestExt;
+
+/// A valid traceparent from the W3C specification examples
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+/// Test which inbound request IDs are adopted
+#[test]
+fn test_request_id_validation() {
+    assert!(is_valid_request_id("8f5c8f5e-1b2a-4c3d-9e8f-0a1b2c3d4e5f"));
+    assert!(is_valid_request_id("Rk9vQmFy_cloudfront-ID=="));
+    assert!(!is_valid_request_id(""));
+    assert!(!is_valid_request_id("id with spaces"));
+    assert!(!is_valid_request_id("id\" status=200"));
+    assert!(!is_valid_request_id(&"a".repeat(129)));
+    assert!(is_valid_request_id(&"a".repeat(128)));
+
+    // Adopted over the Lambda context, which is only the fallback
+    let mut lambda_context = lambda_http::Context::default();
+    lambda_context.request_id = "aws-request-id".to_string();
+    let request = get_with("/", &[("x-request-id", "edge-1234")]).with_lambda_context(lambda_context.clone());
+    assert_eq!(RequestContext::from_request(&request).request_id, "edge-1234");
+
+    let request = get_with("/", &[("x-request-id", "bad id; status=200")]).with_lambda_context(lambda_context);
+    assert_eq!(RequestContext::from_request(&request).request_id, "aws-request-id");
+}
+
+/// Test continuing an inbound trace
+#[test]
+fn test_traceparent() {
+    let trace = TraceContext::from_traceparent(TRACEPARENT).unwrap();
+    assert_eq!(trace.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(trace.parent_span_id.as_deref(), Some("00f067aa0ba902b7"));
+    assert_eq!(trace.flags, 1);
+    assert_eq!(trace.span_id.len(), 16);
+    assert_ne!(trace.span_id, "00f067aa0ba902b7", "This invocation is a new span");
+    assert_eq!(trace.traceparent(), format!("00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01", trace.span_id));
+
+    // Later versions may append fields
+    assert!(TraceContext::from_traceparent("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra").is_some());
+
+    for invalid in [
+        "",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+        "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+    ] {
+        assert!(TraceContext::from_traceparent(invalid).is_none(), "Accepted: {}", invalid);
+    }
+}
+
+/// Test deriving the trace ID from X-Ray and generating one as a last resort
+#[test]
+fn test_trace_sources() {
+    let trace = TraceContext::from_xray("Root=1-5e1b4151-5ac6c58f5b5dcc1e1e0a7e1c;Parent=53995c3f42cd8ad8;Sampled=0").unwrap();
+    assert_eq!(trace.trace_id, "5e1b41515ac6c58f5b5dcc1e1e0a7e1c");
+    assert_eq!(trace.flags, 0);
+    assert!(trace.parent_span_id.is_none());
+    assert!(TraceContext::from_xray("Self=1-abc").is_none());
+
+    // traceparent wins over X-Ray
+    let request = get_with("/", &[("traceparent", TRACEPARENT), ("x-amzn-trace-id", "Root=1-5e1b4151-5ac6c58f5b5dcc1e1e0a7e1c")]);
+    assert_eq!(RequestContext::from_request(&request).trace.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+
+    let first = RequestContext::from_request(&get_with("/", &[("traceparent", "garbage")]));
+    let second = RequestContext::from_request(&get("/"));
+    assert_eq!(first.trace.trace_id.len(), 32);
+    assert_ne!(first.trace.trace_id, second.trace.trace_id);
+}
+
+/// Test that the handler echoes both IDs
+#[tokio::test]
+async fn test_ids_echoed_in_response() {
+    let config = ServerConfig::default();
+    let request = get_with("/", &[("x-request-id", "edge-5678"), ("traceparent", TRACEPARENT)]);
+    let response = handle_request(request, &config).await.unwrap();
+
+    assert_eq!(response.headers().get("x-request-id").unwrap(), "edge-5678");
+    let traceparent = response.headers().get("traceparent").unwrap().to_str().unwrap();
+    assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"), "Got: {}", traceparent);
+    assert!(traceparent.ends_with("-01"));
+    assert!(TraceContext::from_traceparent(traceparent).is_some(), "Echoed header is valid");
+}
@@ -0,0 +1,150 @@
+// Tests for API Gateway REST (v1), HTTP API (v2), Function URL and ALB events
+// These tests run recorded event JSONs from src/tests/events through the handler
+
+use crate::access_log::client_ip;
+use crate::config::ServerConfig;
+use crate::event_source::{stage, strip_stage};
+use crate::handler::handle_request;
+use crate::tests::support::TempSite;
+use lambda_http::request::from_str;
+use lambda_http::{Body, Request, RequestExt};
+
+/// Bytes of `logo.png`, which are not valid UTF-8
+const LOGO: &[u8] = &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0xff, 0xfe];
+
+/// Parses a recorded event the way the Lambda runtime does
+fn event(json: &str) -> Request {
+    from_str(json).expect("Recorded event should deserialize")
+}
+
+/// Creates a content directory with a page and a binary asset
+fn site(name: &str) -> (TempSite, ServerConfig) {
+    let site = TempSite::new(&format!("events-{}", name));
+    site.file("index.html", "<h1>home</h1>").file("about.html", "<h1>about</h1>").file("logo.png", LOGO);
+    let config = ServerConfig { content_source: site.source(), ..ServerConfig::default() };
+    (site, config)
+}
+
+/// Test that the stage lambda_http adds to API Gateway paths is stripped
+#[tokio::test]
+async fn test_stage_prefix_stripped() {
+    let (_site, config) = site("stage");
+
+    // REST APIs get the stage prepended to the event path
+    let request = event(include_str!("events/apigw_rest_request.json"));
+    assert_eq!(stage(&request), Some("prod"));
+    assert_eq!(request.uri().path(), "/prod/about");
+    let response = handle_request(request, &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body(), &Body::Text("<h1>about</h1>".to_string()));
+
+    // HTTP APIs with a named stage have it in rawPath already; the query survives
+    let mut request = event(include_str!("events/apigw_http_stage_request.json"));
+    assert_eq!(stage(&request), Some("staging"));
+    strip_stage(&mut request);
+    assert_eq!(request.uri().path(), "/about");
+    assert_eq!(request.uri().query(), Some("ref=nav&utm_source=mail"));
+    let response = handle_request(event(include_str!("events/apigw_http_stage_request.json")), &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+/// Test that paths without a stage, or only resembling one, are left alone
+#[test]
+fn test_paths_without_stage_untouched() {
+    for json in [
+        include_str!("events/apigw_http_request.json"),
+        include_str!("events/function_url_request.json"),
+        include_str!("events/alb_request.json"),
+    ] {
+        let mut request = event(json);
+        assert_eq!(stage(&request), None);
+        let before = request.uri().clone();
+        strip_stage(&mut request);
+        assert_eq!(request.uri(), &before);
+    }
+
+    // Only a whole first segment is the stage: /staging -> /, /stagingarea stays
+    let http = include_str!("events/apigw_http_stage_request.json");
+    let mut request = event(&http.replace("\"rawPath\": \"/staging/about\"", "\"rawPath\": \"/staging\""));
+    strip_stage(&mut request);
+    assert_eq!(request.uri().path(), "/");
+    let mut request = event(&http.replace("\"rawPath\": \"/staging/about\"", "\"rawPath\": \"/stagingarea/about\""));
+    strip_stage(&mut request);
+    assert_eq!(request.uri().path(), "/stagingarea/about");
+
+    // REST API event paths never contain the stage, so /prod/about is a site path
+    let rest = include_str!("events/apigw_rest_request.json");
+    let mut request = event(&rest.replace("\"path\": \"/about\"", "\"path\": \"/prod/about\""));
+    strip_stage(&mut request);
+    assert_eq!(request.uri().path(), "/prod/about");
+}
+
+/// Test that pages and binary assets are served the same way for every event source
+///
+/// Binary assets must stay `Body::Binary`: lambda_http base64-encodes them
+/// and sets `isBase64Encoded` in the API Gateway and ALB responses.
+#[tokio::test]
+async fn test_every_source_serves_content() {
+    let (_site, config) = site("sources");
+    let cases = [
+        (include_str!("events/apigw_rest_request.json"), "<h1>about</h1>".as_bytes()),
+        (include_str!("events/apigw_http_request.json"), LOGO),
+        (include_str!("events/apigw_http_stage_request.json"), "<h1>about</h1>".as_bytes()),
+        (include_str!("events/function_url_request.json"), "<h1>about</h1>".as_bytes()),
+        (include_str!("events/alb_request.json"), LOGO),
+        (include_str!("events/alb_multi_value_request.json"), "<h1>about</h1>".as_bytes()),
+    ];
+    for (json, expected) in cases {
+        let request = event(json);
+        let path = request.uri().path().to_string();
+        let response = handle_request(request, &config).await.unwrap();
+        assert_eq!(response.status(), 200, "Status for {}", path);
+        match response.body() {
+            Body::Binary(bytes) => assert_eq!(bytes.as_slice(), expected, "Body for {}", path),
+            Body::Text(text) => assert_eq!(text.as_bytes(), expected, "Body for {}", path),
+            Body::Empty => panic!("Empty body for {}", path),
+        }
+        if expected == LOGO {
+            assert!(matches!(response.body(), Body::Binary(_)), "Binary body for {}", path);
+            assert_eq!(response.headers()["content-type"], "image/png");
+        }
+        assert!(response.headers().contains_key("x-request-id"));
+    }
+}
+
+/// Test ALB multi-value headers and query parameters
+#[test]
+fn test_alb_multi_value_headers() {
+    let request = event(include_str!("events/alb_multi_value_request.json"));
+    let cookies: Vec<_> = request.headers().get_all("cookie").iter().map(|value| value.to_str().unwrap()).collect();
+    assert_eq!(cookies, ["theme=dark", "consent=essential"]);
+    assert_eq!(request.headers().get_all("accept-language").iter().count(), 2);
+
+    // Multi-value query parameters are decoded and kept
+    assert_eq!(request.query_string_parameters_ref().and_then(|query| query.all("tag")), Some(vec!["rust", "lambda"]));
+    assert_eq!(request.query_string_parameters_ref().and_then(|query| query.first("q")), Some("static sites"));
+
+    // ALB events have no source IP; the leftmost X-Forwarded-For address is the client
+    assert_eq!(client_ip(&request, &ServerConfig::default()).as_deref(), Some("192.0.2.45"));
+    let trusted = ServerConfig { trust_forwarded_for: true, ..ServerConfig::default() };
+    assert_eq!(client_ip(&request, &trusted).as_deref(), Some("192.0.2.45"));
+}
+
+/// Test that base64 request bodies are decoded, and rejected like any other POST
+#[tokio::test]
+async fn test_base64_request_body() {
+    let request = event(include_str!("events/alb_base64_post.json"));
+    assert_eq!(request.body(), &Body::Binary(vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]));
+
+    let response = handle_request(request, &ServerConfig::default()).await.unwrap();
+    assert_eq!(response.status(), 405);
+    assert_eq!(response.headers()["allow"], "GET");
+}
+
+/// Test that ALB health checks reach the health probe
+#[tokio::test]
+async fn test_alb_health_check() {
+    let response = handle_request(event(include_str!("events/alb_health_check.json")), &ServerConfig::default()).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+}
@@ -0,0 +1,103 @@
+// Tests for the static site export
+// These tests export small sites to temporary directories and inspect the files
+
+use crate::config::ServerConfig;
+use crate::export::{export_site, headers_file, redirect_page};
+use crate::rules::RuleSet;
+use crate::tests::support::TempSite;
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Builds a small site with nested pages and a listable directory
+fn sample_site(name: &str) -> TempSite {
+    let site = TempSite::new(&format!("export-{}", name));
+    site.file("index.html", "<h1>home</h1>")
+        .file("app.js", "console.log('app');")
+        .file("docs/index.html", "<h1>docs</h1>")
+        .file("docs/guide/intro.html", "intro")
+        .file("files/report.txt", "report");
+    site
+}
+
+/// Test exporting a directory site with rules, listings and the 404 page
+#[tokio::test]
+async fn test_export_directory_site() {
+    let site = sample_site("site");
+    let out = TempSite::new("export-site-out");
+    let config = ServerConfig {
+        content_source: site.source(),
+        redirect_rules: RuleSet::parse("/old /docs/ 301\n/blog/* /news/:splat 302\n/legacy.html /\n").unwrap(),
+        listing_prefixes: vec!["/files".to_string()],
+        spa_exclude: vec!["/api".to_string()],
+        ..ServerConfig::default()
+    };
+
+    let summary = export_site(&config, out.path()).await.unwrap();
+    assert_eq!(summary.files, 6, "Five files plus the /files/ listing");
+    assert_eq!(summary.redirects, 2, "Wildcard rules only go to _redirects");
+    assert!(summary.skipped.is_empty(), "Skipped: {:?}", summary.skipped);
+
+    assert_eq!(fs::read_to_string(out.path().join("docs/guide/intro.html")).unwrap(), "intro");
+    assert_eq!(fs::read_to_string(out.path().join("app.js")).unwrap(), "console.log('app');");
+    assert!(fs::read_to_string(out.path().join("files/index.html")).unwrap().contains("report.txt"));
+
+    let old = fs::read_to_string(out.path().join("old/index.html")).unwrap();
+    assert!(old.contains("<meta http-equiv=\"refresh\" content=\"0; url=/docs/\">"), "Got: {}", old);
+    assert!(fs::read_to_string(out.path().join("legacy.html")).unwrap().contains("url=/\""));
+
+    let not_found = fs::read_to_string(out.path().join("404.html")).unwrap();
+    assert!(!not_found.is_empty());
+
+    let redirects = fs::read_to_string(out.path().join("_redirects")).unwrap();
+    assert!(redirects.contains("/old /docs/ 301\n"), "Got: {}", redirects);
+    assert!(redirects.contains("/blog/* /news/:splat 302\n"));
+    let exclude = redirects.find("/api/* /404.html 404").expect("SPA exclude");
+    let fallback = redirects.find("/* /index.html 200").expect("SPA fallback");
+    assert!(exclude < fallback, "Exclusions must come before the catch-all");
+
+    let headers = fs::read_to_string(out.path().join("_headers")).unwrap();
+    assert!(headers.starts_with("# Generated by static-web-lambda export\n/*\n"), "Got: {}", headers);
+    assert!(headers.contains("  x-frame-options: DENY\n"));
+    assert!(!headers.contains("  etag:"), "Per-body headers are left to the host");
+    assert!(!headers.contains("  content-type:"));
+    assert!(!headers.contains("x-request-id"), "Request IDs are per response");
+
+    // Exporting into the content directory would publish the export itself
+    let error = export_site(&config, &site.path().join("dist")).await.unwrap_err();
+    assert!(error.contains("overlaps CONTENT_DIR"), "Got: {}", error);
+}
+
+/// Test exporting the embedded page, which can't be listed
+#[tokio::test]
+async fn test_export_embedded_site() {
+    let out = TempSite::new("export-embedded-out");
+    let config = ServerConfig { spa_fallback: false, ..ServerConfig::default() };
+
+    let summary = export_site(&config, out.path()).await.unwrap();
+    assert_eq!(summary.files, 1);
+    assert!(fs::read_to_string(out.path().join("index.html")).unwrap().contains("<html"));
+    assert!(out.path().join("404.html").exists());
+    assert_eq!(fs::read_to_string(out.path().join("_redirects")).unwrap(), "# Generated by static-web-lambda export\n");
+}
+
+/// Test that redirect targets are escaped in the meta refresh page
+#[test]
+fn test_redirect_page_escapes_location() {
+    let page = redirect_page("/search?q=\"><script>");
+    assert!(!page.contains("<script>"));
+    assert!(page.contains("url=/search?q=&quot;&gt;&lt;script&gt;"), "Got: {}", page);
+}
+
+/// Test splitting headers into the shared block and per-path blocks
+#[test]
+fn test_headers_file() {
+    let shared = ("x-frame-options".to_string(), "DENY".to_string());
+    let mut routes = BTreeMap::new();
+    routes.insert("/app.js".to_string(), vec![shared.clone(), ("cache-control".to_string(), "max-age=60".to_string())]);
+    routes.insert("/index.html".to_string(), vec![shared.clone()]);
+
+    assert_eq!(
+        headers_file(&routes),
+        "# Generated by static-web-lambda export\n/*\n  x-frame-options: DENY\n/app.js\n  cache-control: max-age=60\n"
+    );
+}
@@ -0,0 +1,98 @@
+// Tests for the /healthz and /readyz probes
+// These tests call the handler with probe requests against healthy and broken content sources
+
+use crate::cache::ContentCache;
+use crate::config::ServerConfig;
+use crate::handler::handle_request;
+use crate::tests::support::{get_with, TempSite};
+use lambda_http::{Body, Response, http};
+use serde_json::Value;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Creates a content directory with a root page
+fn site(name: &str) -> TempSite {
+    let site = TempSite::new(&format!("health-{}", name));
+    site.file("index.html", "<h1>home</h1>");
+    site
+}
+
+/// Sends a GET request with optional extra headers
+async fn get(path: &str, headers: &[(&str, &str)], config: &ServerConfig) -> Response<Body> {
+    handle_request(get_with(path, headers), config).await.unwrap()
+}
+
+/// Parses a JSON response body
+fn json_body(response: &Response<Body>) -> Value {
+    serde_json::from_slice(response.body().as_ref()).unwrap()
+}
+
+/// Test that /healthz answers before host validation and user agent checks
+#[tokio::test]
+async fn test_liveness_bypasses_pipeline() {
+    let config = ServerConfig { allowed_hosts: vec!["example.com".to_string()], ..ServerConfig::default() };
+
+    let response = get("/healthz", &[("host", "10.0.0.12"), ("user-agent", "nmap-probe")], &config).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(json_body(&response)["status"], "ok");
+    assert_eq!(response.headers()["content-type"], "application/json");
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    assert_eq!(response.headers()["x-frame-options"], "DENY");
+    assert!(response.headers().get("x-request-id").is_none(), "Probes skip the request pipeline");
+
+    // The site itself still rejects the unknown host
+    let response = get("/", &[("host", "10.0.0.12")], &config).await;
+    assert_eq!(response.status(), 421);
+}
+
+/// Test readiness of a directory site, including cache warming
+#[tokio::test]
+async fn test_readiness_warms_cache() {
+    let site = site("ready");
+    let cache = Arc::new(ContentCache::new(1 << 20, Duration::from_secs(60), Duration::from_secs(10)));
+    let config = ServerConfig {
+        content_source: site.source(),
+        content_cache: Some(cache.clone()),
+        ..ServerConfig::default()
+    };
+
+    let response = get("/readyz", &[], &config).await;
+    assert_eq!(response.status(), 200);
+    let body = json_body(&response);
+    assert_eq!(body["status"], "ready");
+    assert_eq!(body["checks"]["config"]["ok"], true);
+    assert_eq!(body["checks"]["content"]["detail"], "reachable");
+    assert!(body["checks"].get("cache").is_none(), "Readiness doesn't cover the cache");
+    assert_eq!(cache.stats().entries, 1, "The probe caches the root page");
+}
+
+/// Test that a missing content directory makes the server not ready without leaking its path
+#[tokio::test]
+async fn test_readiness_fails_without_content() {
+    let site = site("missing");
+    let config = ServerConfig { content_source: site.source(), ..ServerConfig::default() };
+    fs::remove_dir_all(site.path()).unwrap();
+
+    let response = get("/readyz", &[], &config).await;
+    assert_eq!(response.status(), 503);
+    let body = json_body(&response);
+    assert_eq!(body["status"], "not_ready");
+    assert_eq!(body["checks"]["content"]["ok"], false);
+    assert!(!body.to_string().contains(site.path().to_str().unwrap()), "Got: {}", body);
+
+    // Liveness is unaffected
+    assert_eq!(get("/healthz", &[], &config).await.status(), 200);
+}
+
+/// Test that the probes can be turned off and only answer GET
+#[tokio::test]
+async fn test_probes_disabled_and_methods() {
+    let config = ServerConfig { health_checks: false, ..ServerConfig::default() };
+    let response = get("/healthz", &[], &config).await;
+    assert_eq!(response.headers()["content-type"], "text/html", "Falls through to the site");
+
+    let request = http::Request::builder().method("POST").uri("/readyz").body(Body::Empty).unwrap();
+    let response = handle_request(request, &ServerConfig::default()).await.unwrap();
+    assert_eq!(response.status(), 405);
+}
@@ -0,0 +1,78 @@
+// Tests for cold start detection and pre-warming
+// These tests check the cold start marker, its EMF metrics and that pre-warming fills the content cache
+
+use crate::cache::ContentCache;
+use crate::config::ServerConfig;
+use crate::context::RequestContext;
+use crate::handler::handle_request;
+use crate::lifecycle::{fresh_container, prewarm, take_cold_start, ColdStart};
+use crate::metrics::{emf_document, RequestMetrics};
+use crate::tests::support::{get, TempSite};
+use serde_json::Value;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Test that only one request per process is a cold start
+#[test]
+fn test_cold_start_taken_once() {
+    // Another test may have handled the process's first request already
+    let _ = take_cold_start();
+    assert_eq!(take_cold_start(), None);
+}
+
+/// Test that a health probe arriving first leaves the cold start to the first real request
+#[tokio::test]
+async fn test_probe_keeps_cold_start() {
+    let config = ServerConfig { server_timing: true, ..ServerConfig::default() };
+    fresh_container(async {
+        let probe = handle_request(get("/healthz"), &config).await.unwrap();
+        assert_eq!(probe.status(), 200);
+
+        let first = handle_request(get("/"), &config).await.unwrap();
+        assert!(first.headers()["server-timing"].to_str().unwrap().starts_with("cold-start"));
+        let second = handle_request(get("/"), &config).await.unwrap();
+        assert!(!second.headers()["server-timing"].to_str().unwrap().contains("cold-start"));
+    })
+    .await;
+}
+
+/// Test the EMF cold start metrics, which only the first request carries
+#[test]
+fn test_cold_start_metrics() {
+    let mut context = RequestContext::local();
+    context.cold_start = Some(ColdStart { init_duration: Some(Duration::from_millis(42)) });
+    let document: Value = serde_json::from_str(&emf_document("Test", "site", &RequestMetrics::default(), 200, Duration::from_millis(3), &context)).unwrap();
+
+    assert_eq!(document["ColdStarts"], 1);
+    assert_eq!(document["InitDuration"], 42.0);
+    let directives = document["_aws"]["CloudWatchMetrics"].as_array().unwrap();
+    let cold = directives.iter().find(|directive| directive["Metrics"][0]["Name"] == "ColdStarts").unwrap();
+    assert_eq!(cold["Dimensions"], serde_json::json!([["FunctionName"]]));
+    assert_eq!(cold["Metrics"][1]["Name"], "InitDuration");
+
+    let warm: Value = serde_json::from_str(&emf_document("Test", "site", &RequestMetrics::default(), 200, Duration::from_millis(3), &RequestContext::local())).unwrap();
+    assert!(warm.get("ColdStarts").is_none());
+    assert_eq!(warm["_aws"]["CloudWatchMetrics"].as_array().unwrap().len(), 1);
+}
+
+/// Test that pre-warming loads the root page and SPA index into the cache
+#[tokio::test]
+async fn test_prewarm_fills_cache() {
+    let site = TempSite::new("prewarm");
+    site.file("index.html", "<h1>home</h1>").file("app.html", "<div id=app></div>");
+    let cache = Arc::new(ContentCache::new(1 << 20, Duration::from_secs(60), Duration::from_secs(10)));
+    let config = ServerConfig {
+        content_source: site.source(),
+        content_cache: Some(cache.clone()),
+        spa_index: "/app.html".to_string(),
+        ..ServerConfig::default()
+    };
+
+    prewarm(&config).await;
+    assert_eq!(cache.stats().entries, 2);
+
+    // A missing content directory is logged, not fatal
+    fs::remove_dir_all(site.path()).unwrap();
+    prewarm(&ServerConfig { content_cache: None, ..config }).await;
+}
@@ -0,0 +1,170 @@
+// Tests for auto-generated directory listings
+// These tests build an artifacts tree under the system temp directory
+
+use crate::config::ServerConfig;
+use crate::content::{resolve, Resolution};
+use crate::handler::handle_request;
+use crate::listing::{escape_html, render_listing, wants_json, DirectoryEntry};
+use crate::tests::support::{get, get_with, TempSite};
+use lambda_http::{Body, http};
+
+/// Creates a fresh artifacts tree with builds, reports and a hidden file
+fn artifacts(name: &str) -> TempSite {
+    let site = TempSite::new(&format!("listing-{}", name));
+    site.file("index.html", "home")
+        .file("artifacts/report.txt", "all green")
+        .file("artifacts/<script>.txt", "name needs escaping")
+        .file("artifacts/build log #1.txt", "name needs encoding")
+        .file("artifacts/build-42/résumé.txt", "not ascii")
+        .file("artifacts/.env", "SECRET=1")
+        .file("private/notes.txt", "not listed");
+    site
+}
+
+/// Builds a configuration listing only `/artifacts`
+fn listing_config(site: &TempSite) -> ServerConfig {
+    ServerConfig {
+        content_source: site.source(),
+        listing_prefixes: vec!["/artifacts".to_string()],
+        spa_fallback: false,
+        ..ServerConfig::default()
+    }
+}
+
+/// Returns a response body as text
+fn body_text(response: &http::Response<Body>) -> String {
+    match response.body() {
+        Body::Text(text) => text.clone(),
+        other => panic!("Expected text body, got {:?}", other),
+    }
+}
+
+/// Test Accept negotiation
+#[test]
+fn test_wants_json() {
+    assert!(wants_json(Some("application/json")));
+    assert!(!wants_json(Some("text/html,application/xhtml+xml,application/json;q=0.9")));
+    assert!(!wants_json(Some("*/*")));
+    assert!(!wants_json(None));
+}
+
+/// Test that names are escaped in HTML and sorted directories-first
+#[test]
+fn test_render_listing_html() {
+    let entries = vec![
+        DirectoryEntry { name: "b.txt".to_string(), is_dir: false, size: 1536, modified: None },
+        DirectoryEntry { name: "\"><img src=x>".to_string(), is_dir: false, size: 10, modified: None },
+        DirectoryEntry { name: "z-dir".to_string(), is_dir: true, size: 0, modified: None },
+    ];
+    let asset = render_listing("/artifacts/", entries, false);
+    let html = String::from_utf8(asset.body).unwrap();
+
+    assert_eq!(asset.content_type, "text/html");
+    assert!(!html.contains("<img"), "Names must be escaped: {}", html);
+    assert!(html.contains("&quot;&gt;&lt;img src=x&gt;"));
+    assert!(html.contains("href=\"%22%3E%3Cimg%20src%3Dx%3E\""));
+    assert!(html.contains("1.5 KB"));
+    assert!(html.find("z-dir/").unwrap() < html.find("b.txt").unwrap(), "Directories come first");
+    assert!(html.contains("href=\"../\""));
+
+    assert_eq!(escape_html("a&b'"), "a&amp;b&#39;");
+}
+
+/// Test that listings are off by default and limited to configured prefixes
+#[tokio::test]
+async fn test_listing_requires_prefix() {
+    let site = artifacts("prefix");
+
+    let disabled = ServerConfig {
+        content_source: site.source(),
+        spa_fallback: false,
+        ..ServerConfig::default()
+    };
+    assert_eq!(resolve("/artifacts/", &disabled, true).await.unwrap(), Resolution::NotFound);
+
+    let config = listing_config(&site);
+    assert!(matches!(resolve("/artifacts/", &config, true).await.unwrap(), Resolution::Listing(_)));
+    assert_eq!(resolve("/private/", &config, true).await.unwrap(), Resolution::NotFound);
+    assert_eq!(
+        resolve("/artifacts/build-42", &config, true).await.unwrap(),
+        Resolution::DirectoryRedirect("/artifacts/build-42/".to_string())
+    );
+}
+
+/// Test the HTML and JSON listings through the handler
+#[tokio::test]
+async fn test_handler_listing() {
+    let site = artifacts("handler");
+    let config = listing_config(&site);
+
+    let response = handle_request(get_with("/artifacts/", &[("accept", "text/html")]), &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/html");
+    assert_eq!(response.headers().get("vary").unwrap(), "Accept");
+    assert_eq!(response.headers().get("x-frame-options").unwrap(), "DENY");
+    let html = body_text(&response);
+    assert!(html.contains("report.txt") && html.contains("build-42/"));
+    assert!(html.contains("&lt;script&gt;.txt") && !html.contains("<script>"));
+    assert!(!html.contains(".env"), "Dotfiles are hidden");
+
+    let response = handle_request(get_with("/artifacts/", &[("accept", "application/json")]), &config).await.unwrap();
+    assert_eq!(response.headers().get("content-type").unwrap(), "application/json");
+    let json: serde_json::Value = serde_json::from_str(&body_text(&response)).unwrap();
+    assert_eq!(json["path"], "/artifacts/");
+    assert_eq!(json["entries"][0]["name"], "build-42");
+    assert_eq!(json["entries"][0]["type"], "directory");
+    let report = json["entries"].as_array().unwrap().iter().find(|e| e["name"] == "report.txt").unwrap();
+    assert_eq!(report["size"], 9);
+    assert!(report["modified"].is_string());
+
+    let response = handle_request(get("/artifacts/report.txt"), &config).await.unwrap();
+    assert_eq!(body_text(&response), "all green", "Files in listed directories are still served");
+}
+
+/// Test that every link in a listing leads to the entry it names
+#[tokio::test]
+async fn test_listing_links_resolve() {
+    let site = artifacts("links");
+    let config = listing_config(&site);
+
+    let mut pending = vec!["/artifacts/".to_string()];
+    let mut followed = 0;
+    while let Some(directory) = pending.pop() {
+        let response = handle_request(get_with(&directory, &[("accept", "text/html")]), &config).await.unwrap();
+        assert_eq!(response.status(), 200, "Listing {}", directory);
+        let html = body_text(&response);
+        for href in html.split("href=\"").skip(1).filter_map(|rest| rest.split('"').next()) {
+            if href == "../" {
+                continue;
+            }
+            let link = format!("{}{}", directory, href);
+            let response = handle_request(get(&link), &config).await.unwrap();
+            assert_eq!(response.status(), 200, "Link {} in {}", href, directory);
+            if link.ends_with('/') {
+                pending.push(link);
+            }
+            followed += 1;
+        }
+    }
+    assert_eq!(followed, 5, "Every file and directory is linked");
+
+    let response = handle_request(get("/artifacts/build-42/r%C3%A9sum%C3%A9.txt"), &config).await.unwrap();
+    assert_eq!(body_text(&response), "not ascii");
+    let response = handle_request(get("/artifacts/build%20log%20%231.txt"), &config).await.unwrap();
+    assert_eq!(body_text(&response), "name needs encoding");
+}
+
+/// Test that traversal attempts are rejected before any listing happens
+#[tokio::test]
+async fn test_handler_listing_traversal() {
+    let site = artifacts("traversal");
+    let config = ServerConfig {
+        listing_prefixes: vec!["/".to_string()],
+        ..listing_config(&site)
+    };
+
+    for path in ["/artifacts/../../", "/artifacts/%2e%2e/", "/artifacts/..%2f..%2f"] {
+        let response = handle_request(get(path), &config).await.unwrap();
+        assert_eq!(response.status(), 400, "Traversal path {} should be rejected", path);
+    }
+}
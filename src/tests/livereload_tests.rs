@@ -0,0 +1,120 @@
+// Tests for local-mode live reload
+// These tests cover script injection, the CSP nonce and the content watcher
+
+use crate::cache::ContentCache;
+use crate::config::ServerConfig;
+use crate::handler::handle_request;
+use crate::livereload::{
+    add_script_nonce, event_stream, generate_nonce, inject_script, snapshot, spawn_watcher, LiveReload,
+};
+use crate::tests::support::{get, TempSite};
+use lambda_http::{Body, http};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Serves a page through the real handler so the production headers are present
+async fn page(html: &str) -> http::Response<Body> {
+    let site = TempSite::new("live-page");
+    site.file("index.html", html);
+    let config = ServerConfig { content_source: site.source(), ..ServerConfig::default() };
+    handle_request(get("/"), &config).await.unwrap()
+}
+
+/// Extracts the nonce attribute of the injected script
+fn script_nonce(html: &str) -> String {
+    let start = html.find("<script nonce=\"").expect("script injected") + "<script nonce=\"".len();
+    html[start..start + 32].to_string()
+}
+
+/// Test that the script is injected before </body> and allowed by the CSP nonce only
+#[tokio::test]
+async fn test_inject_script() {
+    let response = inject_script(page("<html><BODY><h1>Hi</h1></BODY></html>").await);
+
+    let html = match response.body() {
+        Body::Text(text) => text.clone(),
+        other => panic!("Expected text, got {:?}", other),
+    };
+    assert!(html.contains("/__livereload"));
+    assert!(html.find("<script").unwrap() < html.find("</BODY>").unwrap(), "Injected inside the body: {}", html);
+
+    let nonce = script_nonce(&html);
+    let csp = response.headers().get("content-security-policy").unwrap().to_str().unwrap();
+    assert!(csp.contains(&format!("script-src 'self' 'nonce-{}'", nonce)), "Got: {}", csp);
+    let script_src = csp.split(';').find(|d| d.trim().starts_with("script-src")).unwrap();
+    assert!(!script_src.contains("unsafe-inline"), "Inline scripts stay blocked without the nonce");
+    assert!(csp.contains("frame-ancestors 'none'"), "The rest of the policy is unchanged");
+
+    assert!(response.headers().get("etag").is_none());
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+    assert_eq!(response.headers().get("x-frame-options").unwrap(), "DENY");
+}
+
+/// Test that non-HTML responses are left alone and nonces never repeat
+#[tokio::test]
+async fn test_inject_script_skips_other_content() {
+    let response = http::Response::builder()
+        .header("content-type", "application/javascript")
+        .header("etag", "\"abc\"")
+        .body(Body::Text("console.log(1);".to_string()))
+        .unwrap();
+    let response = inject_script(response);
+    assert_eq!(response.headers().get("etag").unwrap(), "\"abc\"");
+    assert!(matches!(response.body(), Body::Text(text) if text == "console.log(1);"));
+
+    let first = generate_nonce();
+    assert_eq!(first.len(), 32);
+    assert!(first.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_ne!(first, generate_nonce());
+}
+
+/// Test adding a nonce to policies with and without script-src
+#[test]
+fn test_add_script_nonce() {
+    assert_eq!(
+        add_script_nonce("default-src 'self'; script-src 'self'; img-src data:", "n1"),
+        "default-src 'self'; script-src 'self' 'nonce-n1'; img-src data:"
+    );
+    assert_eq!(add_script_nonce("default-src 'self';", "n2"), "default-src 'self'; script-src 'self' 'nonce-n2'");
+}
+
+/// Test that the watcher clears the cache and notifies subscribers on change
+#[tokio::test]
+async fn test_watcher_invalidates_cache() {
+    let site = TempSite::new("live-watch");
+    site.file("nested/page.html", "v1");
+    assert_eq!(snapshot(site.path()).len(), 1, "Nested files are tracked");
+
+    let source = site.source();
+    let cache = Arc::new(ContentCache::new(1024 * 1024, Duration::from_secs(600), Duration::from_secs(600)));
+    cache.get(&source, "/nested/page.html").await.unwrap();
+
+    let reload = Arc::new(LiveReload::new());
+    let mut changes = reload.subscribe();
+    spawn_watcher(site.path().to_path_buf(), Some(cache.clone()), reload.clone());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    site.file("nested/page.html", "version two");
+    tokio::time::timeout(Duration::from_secs(5), changes.changed()).await.expect("change detected").unwrap();
+
+    assert_eq!(cache.stats().entries, 0, "Cache cleared on change");
+    let asset = cache.get(&source, "/nested/page.html").await.unwrap().unwrap();
+    assert_eq!(asset.body, b"version two");
+}
+
+/// Test the Server-Sent Events stream
+#[tokio::test]
+async fn test_event_stream() {
+    use hyper::body::HttpBody;
+
+    let reload = LiveReload::new();
+    let mut response = event_stream(&reload);
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+
+    let first = response.body_mut().data().await.unwrap().unwrap();
+    assert_eq!(&first[..], b"retry: 500\n\n");
+
+    reload.notify();
+    let event = response.body_mut().data().await.unwrap().unwrap();
+    assert_eq!(&event[..], b"event: reload\ndata: 1\n\n");
+}
@@ -0,0 +1,150 @@
+// Tests for log sampling
+// These tests check which requests are sampled and when held lines are written or dropped
+
+use crate::logging::{sampled, LogSampling, SamplingLogger, JSON_EVENT_KEY, MAX_HELD_LINES};
+use log::kv::Key;
+use log::{Level, Log, Metadata, Record};
+use std::sync::Mutex;
+
+/// Logger that records `LEVEL message` lines, with an info threshold like `RUST_LOG=info`
+///
+/// Records marked with the `json_event` key get a ` +json_event` suffix.
+#[derive(Default)]
+struct Capture {
+    lines: Mutex<Vec<String>>,
+}
+
+impl Capture {
+    fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+}
+
+impl Log for Capture {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let marker = if record.key_values().get(Key::from_str(JSON_EVENT_KEY)).is_some() { " +json_event" } else { "" };
+            self.lines.lock().unwrap().push(format!("{} {}{}", record.level(), record.args(), marker));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Writes one line through a logger
+fn write(logger: &dyn Log, level: Level, message: &str) {
+    logger.log(&Record::builder().level(level).target("test").args(format_args!("{}", message)).build());
+}
+
+/// Test parsing LOG_SAMPLE_RATE values
+#[test]
+fn test_parse_rate() {
+    assert_eq!(LogSampling::parse_rate("0.01"), Ok(0.01));
+    assert_eq!(LogSampling::parse_rate(" 5% "), Ok(0.05));
+    assert_eq!(LogSampling::parse_rate("1"), Ok(1.0));
+    assert!(LogSampling::parse_rate("1.5").is_err());
+    assert!(LogSampling::parse_rate("-1%").is_err());
+    assert!(LogSampling::parse_rate("some").unwrap_err().contains("'some'"));
+}
+
+/// Test the sampling decision by trace ID and suppressed path
+#[test]
+fn test_keeps() {
+    let low_trace = "4bf92f3577b34da60000000000000001";
+    let high_trace = "4bf92f3577b34da6fffffffffffffff0";
+
+    let half = LogSampling { rate: 0.5, suppress: Vec::new() };
+    assert!(half.keeps("/", low_trace));
+    assert!(!half.keeps("/", high_trace));
+    assert_eq!(half.keeps("/a", low_trace), half.keeps("/b", low_trace), "The decision follows the trace");
+
+    assert!(LogSampling::default().keeps("/", high_trace));
+    assert!(!LogSampling { rate: 0.0, suppress: Vec::new() }.keeps("/", low_trace));
+
+    let suppressed = LogSampling { rate: 1.0, suppress: vec!["/assets".to_string(), "/favicon.ico".to_string()] };
+    assert!(!suppressed.keeps("/assets/app.js", low_trace));
+    assert!(!suppressed.keeps("/favicon.ico", low_trace));
+    assert!(suppressed.keeps("/assets-old/app.js", low_trace));
+}
+
+/// Test that info lines of an unsampled request are dropped when nothing goes wrong
+#[tokio::test]
+async fn test_unsampled_lines_dropped() {
+    let logger = SamplingLogger::new(Capture::default());
+    sampled(false, async {
+        write(&logger, Level::Info, "[REQUEST] path=/");
+        write(&logger, Level::Info, "[RESPONSE] status=200");
+    }, |_| false).await;
+    assert!(logger_lines(&logger).is_empty());
+
+    sampled(true, async {
+        write(&logger, Level::Info, "[REQUEST] path=/");
+    }, |_| false).await;
+    assert_eq!(logger_lines(&logger), vec!["INFO [REQUEST] path=/"]);
+
+    // Outside a request nothing is held
+    write(&logger, Level::Info, "Starting");
+    assert_eq!(logger_lines(&logger).len(), 2);
+}
+
+/// Test that a warning releases the held lines in order, then logs directly
+#[tokio::test]
+async fn test_warning_releases_held_lines() {
+    let logger = SamplingLogger::new(Capture::default());
+    sampled(false, async {
+        write(&logger, Level::Info, "[REQUEST] path=/x");
+        write(&logger, Level::Debug, "Sanitizing request path");
+        write(&logger, Level::Warn, "[SECURITY] Suspicious user agent");
+        write(&logger, Level::Info, "[RESPONSE] status=200");
+    }, |_| false).await;
+    assert_eq!(logger_lines(&logger), vec![
+        "INFO [REQUEST] path=/x",
+        "WARN [SECURITY] Suspicious user agent",
+        "INFO [RESPONSE] status=200",
+    ]);
+}
+
+/// Test that released lines keep the JSON event marker
+#[tokio::test]
+async fn test_released_json_events_keep_marker() {
+    let logger = SamplingLogger::new(Capture::default());
+    sampled(false, async {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("test")
+                .args(format_args!("{}", r#"{"event":"request"}"#))
+                .key_values(&(JSON_EVENT_KEY, true))
+                .build(),
+        );
+        write(&logger, Level::Info, r#"{"not":"an event"}"#);
+        write(&logger, Level::Warn, "[SECURITY] Suspicious user agent");
+    }, |_| false).await;
+    assert_eq!(logger_lines(&logger), vec![
+        r#"INFO {"event":"request"} +json_event"#,
+        r#"INFO {"not":"an event"}"#,
+        "WARN [SECURITY] Suspicious user agent",
+    ]);
+}
+
+/// Test that a request holds a bounded number of lines
+#[tokio::test]
+async fn test_held_lines_bounded() {
+    let logger = SamplingLogger::new(Capture::default());
+    sampled(false, async {
+        for n in 0..MAX_HELD_LINES + 10 {
+            write(&logger, Level::Info, &format!("line {}", n));
+        }
+        write(&logger, Level::Error, "failed");
+    }, |_| false).await;
+    assert_eq!(logger_lines(&logger).len(), MAX_HELD_LINES + 1);
+}
+
+/// Lines written by the wrapped capture logger
+fn logger_lines(logger: &SamplingLogger<Capture>) -> Vec<String> {
+    logger.inner().lines()
+}
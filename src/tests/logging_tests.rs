@@ -0,0 +1,87 @@
+// Tests for the JSON log format
+// These tests check LOG_FORMAT parsing and the fields of each JSON event
+
+use crate::logging::{json_event, json_line, log_format, LogFormat, JSON_EVENT_KEY};
+use crate::response::{error_log_fields, ApplicationError};
+use log::{Level, Record};
+use serde_json::{json, Value};
+
+/// Test parsing LOG_FORMAT values
+#[test]
+fn test_parse_log_format() {
+    assert_eq!(LogFormat::parse("json"), Ok(LogFormat::Json));
+    assert_eq!(LogFormat::parse(" JSON "), Ok(LogFormat::Json));
+    assert_eq!(LogFormat::parse("text"), Ok(LogFormat::Text));
+    assert_eq!(LogFormat::parse(""), Ok(LogFormat::Text));
+    let error = LogFormat::parse("logfmt").unwrap_err();
+    assert!(error.contains("'logfmt'"), "Got: {}", error);
+
+    // Tests never call logging::init, so the text format stays active
+    assert_eq!(log_format(), LogFormat::Text);
+}
+
+/// Test that events are single-line JSON with typed fields
+#[test]
+fn test_json_event() {
+    let line = json_event(Level::Info, "response", json!({
+        "status": 404,
+        "duration_ms": 12,
+        "path": "/a\nb",
+        "rule": Option::<&str>::None,
+    }));
+    assert!(!line.contains('\n'), "Newlines in fields are escaped: {}", line);
+
+    let event: Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(event["event"], "response");
+    assert_eq!(event["level"], "INFO");
+    assert_eq!(event["status"], 404);
+    assert_eq!(event["duration_ms"], 12);
+    assert_eq!(event["path"], "/a\nb");
+    assert!(event.get("rule").is_none(), "Null fields are dropped");
+    assert!(event["timestamp"].as_str().unwrap().ends_with('Z'));
+}
+
+/// Test that only records marked as events are written unwrapped in JSON mode
+#[test]
+fn test_json_line_wraps_unmarked_messages() {
+    let event = json_event(Level::Info, "request", json!({"path": "/"}));
+    let marked = json_line(
+        &Record::builder()
+            .level(Level::Info)
+            .target("static_web_lambda::handler")
+            .args(format_args!("{}", event))
+            .key_values(&(JSON_EVENT_KEY, true))
+            .build(),
+    );
+    assert_eq!(marked, event);
+
+    // A message that only looks like JSON is wrapped like any other
+    let line = json_line(
+        &Record::builder()
+            .level(Level::Warn)
+            .target("some_dependency")
+            .args(format_args!("{}", r#"{"event":"response","status":200}"#))
+            .build(),
+    );
+    let wrapped: Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(wrapped["level"], "WARN");
+    assert_eq!(wrapped["target"], "some_dependency");
+    assert_eq!(wrapped["message"], r#"{"event":"response","status":200}"#);
+    assert!(wrapped["timestamp"].as_str().unwrap().ends_with('Z'));
+}
+
+/// Test the fields of the error event
+#[test]
+fn test_error_log_fields() {
+    let error = ApplicationError::ServiceUnavailable { reason: "S3 throttled".to_string(), retry_after: Some(5) };
+    let fields = error_log_fields(&error, "req-1");
+    assert_eq!(fields["request_id"], "req-1");
+    assert_eq!(fields["status"], 503);
+    assert_eq!(fields["error_type"], "ServiceUnavailable");
+    assert_eq!(fields["retry_after_seconds"], 5);
+    assert!(fields["detailed_error"].as_str().unwrap().contains("S3 throttled"));
+
+    let fields = error_log_fields(&ApplicationError::NotFound { path: "/missing".to_string() }, "req-2");
+    assert_eq!(fields["status"], 404);
+    assert_eq!(fields["path"], "/missing");
+}
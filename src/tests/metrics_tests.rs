@@ -0,0 +1,98 @@
+// Tests for the CloudWatch EMF metrics
+// These tests check the EMF document layout and the route and rejection the handler records
+
+use crate::config::ServerConfig;
+use crate::context::RequestContext;
+use crate::handler::handle_request;
+use crate::metrics::{collect, content_route, emf_document, status_class, RequestMetrics};
+use crate::rules::RuleSet;
+use crate::tests::support::TempSite;
+use lambda_http::{Body, http};
+use serde_json::Value;
+use std::time::Duration;
+
+/// Test route labels for content paths and status classes
+#[test]
+fn test_route_and_status_class() {
+    assert_eq!(content_route("/docs/guide/intro.html"), "/docs");
+    assert_eq!(content_route("/docs/"), "/docs");
+    assert_eq!(content_route("/app.js"), "/");
+    assert_eq!(content_route("/"), "/");
+    assert_eq!(status_class(204), "2xx");
+    assert_eq!(status_class(503), "5xx");
+}
+
+/// Test the EMF document for a normal request and a security rejection
+#[test]
+fn test_emf_document() {
+    let context = RequestContext::local();
+    let served = RequestMetrics { route: "/docs".to_string(), security_error: None };
+    let document: Value =
+        serde_json::from_str(&emf_document("Site", "site-prod", &served, 200, Duration::from_micros(1500), &context)).unwrap();
+
+    assert_eq!(document["FunctionName"], "site-prod");
+    assert_eq!(document["Route"], "/docs");
+    assert_eq!(document["StatusClass"], "2xx");
+    assert_eq!(document["Requests"], 1);
+    assert_eq!(document["Latency"], 1.5);
+    assert_eq!(document["request_id"], context.request_id.as_str());
+    assert!(document["_aws"]["Timestamp"].is_i64());
+
+    let directives = document["_aws"]["CloudWatchMetrics"].as_array().unwrap();
+    assert_eq!(directives.len(), 1, "No security directive without a rejection");
+    assert_eq!(directives[0]["Namespace"], "Site");
+    assert_eq!(directives[0]["Dimensions"][0], serde_json::json!(["FunctionName", "Route", "StatusClass"]));
+    assert!(document.get("SecurityRejections").is_none());
+
+    let rejected = RequestMetrics { route: "other".to_string(), security_error: Some("MaliciousPath") };
+    let document: Value =
+        serde_json::from_str(&emf_document("Site", "site-prod", &rejected, 400, Duration::ZERO, &context)).unwrap();
+    let directives = document["_aws"]["CloudWatchMetrics"].as_array().unwrap();
+    assert_eq!(directives.len(), 2);
+    assert_eq!(directives[1]["Dimensions"][0], serde_json::json!(["FunctionName", "SecurityError"]));
+    assert_eq!(document["SecurityError"], "MaliciousPath");
+    assert_eq!(document["SecurityRejections"], 1);
+    assert_eq!(document["StatusClass"], "4xx");
+}
+
+/// Test the route and security rejection recorded by the handler
+#[tokio::test]
+async fn test_handler_records_metrics() {
+    let site = TempSite::new("metrics");
+    site.file("index.html", "<h1>home</h1>").file("docs/intro.html", "intro");
+    let config = ServerConfig {
+        content_source: site.source(),
+        redirect_rules: RuleSet::parse("/blog/* /news/:splat 301\n").unwrap(),
+        metrics_namespace: Some("Test".to_string()),
+        ..ServerConfig::default()
+    };
+
+    let record = |method: &str, path: &str| {
+        let request = http::Request::builder().method(method).uri(path).body(Body::Empty).unwrap();
+        let config = config.clone();
+        async move {
+            let (response, recorded) = collect(handle_request(request, &config)).await;
+            (response.unwrap().status().as_u16(), recorded)
+        }
+    };
+
+    let (status, recorded) = record("GET", "/docs/intro.html").await;
+    assert_eq!((status, recorded.route.as_str(), recorded.security_error), (200, "/docs", None));
+
+    let (status, recorded) = record("GET", "/blog/hello").await;
+    assert_eq!((status, recorded.route.as_str()), (301, "/blog/*"));
+
+    let (status, recorded) = record("GET", "/some/client/route").await;
+    assert_eq!((status, recorded.route.as_str()), (200, "spa"));
+
+    // Attacker-controlled paths never become route values
+    let (status, recorded) = record("GET", "/../etc/passwd").await;
+    assert_eq!((status, recorded.route.as_str(), recorded.security_error), (400, "other", Some("MaliciousPath")));
+
+    let (status, recorded) = record("POST", "/").await;
+    assert_eq!((status, recorded.security_error), (405, Some("InvalidMethod")));
+
+    // A rejection doesn't leak into the next request handled by the same task
+    let (_, recorded) = record("GET", "/").await;
+    assert_eq!((recorded.route.as_str(), recorded.security_error), ("/", None));
+}
@@ -4,4 +4,28 @@
 pub mod unit_tests;
 pub mod integration_tests;
 pub mod property_tests;
-pub mod enhanced_logging_tests;
\ No newline at end of file
+pub mod enhanced_logging_tests;
+pub mod canonical_tests;
+pub mod rules_tests;
+pub mod spa_tests;
+pub mod clean_url_tests;
+pub mod listing_tests;
+pub mod s3_tests;
+pub mod cache_tests;
+pub mod archive_tests;
+pub mod livereload_tests;
+pub mod export_tests;
+pub mod logging_tests;
+pub mod context_tests;
+pub mod telemetry_tests;
+pub mod metrics_tests;
+pub mod prometheus_tests;
+pub mod health_tests;
+pub mod access_log_tests;
+pub mod log_sampling_tests;
+pub mod redact_tests;
+pub mod server_timing_tests;
+pub mod lifecycle_tests;
+pub mod slo_tests;
+pub mod event_source_tests;
+pub mod support;
@@ -0,0 +1,44 @@
+// Tests for the Prometheus metrics of the local server
+// These tests record observations into a private registry and check the rendered exposition text
+
+use crate::metrics::RequestMetrics;
+use crate::prometheus::Registry;
+use std::time::Duration;
+
+fn route(route: &str, security_error: Option<&'static str>) -> RequestMetrics {
+    RequestMetrics { route: route.to_string(), security_error }
+}
+
+/// Test counters, histogram buckets and byte totals in the rendered output
+#[test]
+fn test_render_series() {
+    let registry = Registry::default();
+    registry.observe(&route("/docs", None), 200, Duration::from_millis(3), 1000);
+    registry.observe(&route("/docs", None), 200, Duration::from_millis(300), 24);
+    registry.observe(&route("other", Some("MaliciousPath")), 400, Duration::from_secs(10), 80);
+
+    let text = registry.render();
+    assert!(text.contains("# TYPE static_web_requests_total counter\n"), "Got: {}", text);
+    assert!(text.contains("static_web_requests_total{route=\"/docs\",status=\"200\"} 2\n"));
+    assert!(text.contains("static_web_requests_total{route=\"other\",status=\"400\"} 1\n"));
+
+    // Buckets are cumulative; 3 ms lands in le=0.005, 300 ms in le=0.5
+    assert!(text.contains("static_web_request_duration_seconds_bucket{route=\"/docs\",le=\"0.001\"} 0\n"));
+    assert!(text.contains("static_web_request_duration_seconds_bucket{route=\"/docs\",le=\"0.005\"} 1\n"));
+    assert!(text.contains("static_web_request_duration_seconds_bucket{route=\"/docs\",le=\"0.5\"} 2\n"));
+    assert!(text.contains("static_web_request_duration_seconds_bucket{route=\"/docs\",le=\"+Inf\"} 2\n"));
+    assert!(text.contains("static_web_request_duration_seconds_count{route=\"/docs\"} 2\n"));
+    assert!(text.contains("static_web_request_duration_seconds_bucket{route=\"other\",le=\"5\"} 0\n"));
+    assert!(text.contains("static_web_request_duration_seconds_bucket{route=\"other\",le=\"+Inf\"} 1\n"));
+
+    assert!(text.contains("static_web_security_rejections_total{type=\"MaliciousPath\"} 1\n"));
+    assert!(text.contains("static_web_response_bytes_total{route=\"/docs\"} 1024\n"));
+}
+
+/// Test that rule patterns are escaped as label values
+#[test]
+fn test_label_escaping() {
+    let registry = Registry::default();
+    registry.observe(&route("/say/\"hi\"\\", None), 301, Duration::ZERO, 0);
+    assert!(registry.render().contains("route=\"/say/\\\"hi\\\"\\\\\",status=\"301\""));
+}
@@ -289,7 +289,7 @@ proptest! {
         
         // Create the HTTP request
         let request_result = http::Request::builder()
-            .method(&method[..])
+            .method(method)
             .uri(&test_path);
         
         // Add oversized body for some requests to test size validation
@@ -381,12 +381,13 @@ proptest! {
             );
             
             // Verify that security headers are present regardless of response status
-            // This ensures that both successful (200) and error responses (400, 405, 413)
-            // include the same security protections
+            // This ensures that both successful (200) and error responses (400, 404, 405, 413)
+            // include the same security protections; missing file-like paths such as
+            // `/app.js` or `/.` are a 404 now that static assets are served
             let status_code = response.status().as_u16();
             prop_assert!(
-                status_code == 200 || status_code == 400 || status_code == 405 || status_code == 413,
-                "Response should have valid status code (200, 400, 405, or 413), got {}",
+                status_code == 200 || status_code == 400 || status_code == 404 || status_code == 405 || status_code == 413,
+                "Response should have valid status code (200, 400, 404, 405, or 413), got {}",
                 status_code
             );
             
@@ -444,7 +445,7 @@ proptest! {
         };
         
         let request = http::Request::builder()
-            .method(&method[..])
+            .method(method)
             .uri(path)
             .body(body)
             .unwrap();
@@ -585,7 +586,7 @@ proptest! {
         // Attempt to create the HTTP request
         // Some malformed requests might fail at the HTTP parsing level
         let request_result = http::Request::builder()
-            .method(&method[..])
+            .method(method)
             .uri(&path);
         
         let request_result = request_result.body(body);
@@ -832,7 +833,7 @@ proptest! {
         
         // Attempt to create and process the malicious request
         let request_result = http::Request::builder()
-            .method(&method[..])
+            .method(method)
             .uri(&path)
             .body(body);
         
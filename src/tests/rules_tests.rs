@@ -0,0 +1,172 @@
+// Tests for the redirect rules file parser and matcher
+// These tests cover the Netlify `_redirects` syntax and the handler integration
+
+use crate::config::ServerConfig;
+use crate::handler::handle_request;
+use crate::rules::RuleSet;
+use crate::tests::support::{get, TempSite};
+use lambda_http::Body;
+use hyper::body::to_bytes;
+
+/// Sample rules file exercising every supported feature
+const SAMPLE_RULES: &str = "
+# Comments and blank lines are ignored
+
+/home                  /                      301
+/blog/*                /posts/:splat          301
+/news/:year/:month     /archive/:year/:month
+/store    id=:id       /products/:id          302
+/docs     v=1          /docs/v1               307
+/app/*                 /index.html            200
+/missing               /index.html            404
+/old-campaign          /                      410
+/external              https://example.org/x  308!
+";
+
+/// Builds a configuration using the sample rules
+fn rules_config() -> ServerConfig {
+    ServerConfig {
+        redirect_rules: RuleSet::parse(SAMPLE_RULES).expect("Sample rules should parse"),
+        ..ServerConfig::default()
+    }
+}
+
+/// Test that the sample file parses with the expected defaults
+#[test]
+fn test_parse_rules() {
+    let rules = RuleSet::parse(SAMPLE_RULES).unwrap();
+    assert_eq!(rules.rules().len(), 9);
+
+    let news = &rules.rules()[2];
+    assert_eq!(news.status, 301, "Status should default to 301");
+    assert_eq!(news.line, 6, "Line numbers should be 1-based file lines");
+
+    let external = rules.rules().last().unwrap();
+    assert_eq!(external.status, 308);
+    assert!(external.force, "Trailing ! should be recorded");
+}
+
+/// Test that every invalid line is reported with its line number
+#[test]
+fn test_parse_rules_errors() {
+    let content = "/ok /fine\nnot-a-path /x\n/a /b 999\n/c\n/d/:id /e/:other\n/f/*/g /h\n/i https://example.org 200\n/j /k 301 extra";
+    let errors = RuleSet::parse(content).unwrap_err();
+
+    let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, vec![2, 3, 4, 5, 6, 7, 8]);
+    assert!(errors[3].message.contains(":other"), "Undefined placeholder should be named: {}", errors[3]);
+}
+
+/// Test splat, placeholder and query matching
+#[test]
+fn test_find_match() {
+    let rules = RuleSet::parse(SAMPLE_RULES).unwrap();
+
+    let splat = rules.find_match("/blog/2024/hello-world", None).unwrap();
+    assert_eq!(splat.target, "/posts/2024/hello-world");
+    assert_eq!(splat.label(), "5:/blog/*");
+
+    let placeholders = rules.find_match("/news/2023/10", None).unwrap();
+    assert_eq!(placeholders.target, "/archive/2023/10");
+
+    let query = rules.find_match("/store", Some("id=42&ref=mail")).unwrap();
+    assert_eq!(query.target, "/products/42", "Query-matched rules don't pass the query through");
+    assert_eq!(query.status, 302);
+
+    assert!(rules.find_match("/store", None).is_none(), "Missing query parameter should not match");
+    assert!(rules.find_match("/docs", Some("v=2")).is_none(), "Literal query value must match");
+    assert_eq!(rules.find_match("/docs", Some("v=1")).unwrap().target, "/docs/v1");
+
+    // Trailing slashes are ignored and unconditioned redirects keep the query string
+    let home = rules.find_match("/home/", Some("utm_source=x")).unwrap();
+    assert_eq!(home.target, "/?utm_source=x");
+
+    assert!(rules.find_match("/unrelated", None).is_none());
+}
+
+/// Test that captured values cannot produce protocol-relative redirects
+#[test]
+fn test_find_match_prevents_open_redirect() {
+    let rules = RuleSet::parse("/go/* /:splat 302\n/p id=:id /:id 302").unwrap();
+
+    let splat = rules.find_match("/go//evil.example/path", None).unwrap();
+    assert_eq!(splat.target, "/evil.example/path");
+
+    let query = rules.find_match("/p", Some("id=/evil.example")).unwrap();
+    assert_eq!(query.target, "/evil.example");
+}
+
+/// Test redirects, rewrites and 410 through the handler
+#[tokio::test]
+async fn test_handler_applies_rules() {
+    let config = rules_config();
+
+    let response = handle_request(get("/blog/first-post"), &config).await.unwrap();
+    assert_eq!(response.status(), 301);
+    assert_eq!(response.headers().get("location").unwrap(), "/posts/first-post");
+
+    let response = handle_request(get("/external"), &config).await.unwrap();
+    assert_eq!(response.status(), 308);
+    assert_eq!(response.headers().get("location").unwrap(), "https://example.org/x");
+
+    let response = handle_request(get("/app/dashboard/settings"), &config).await.unwrap();
+    assert_eq!(response.status(), 200, "200 rules rewrite without redirecting");
+    assert!(response.headers().get("location").is_none());
+
+    let response = handle_request(get("/missing"), &config).await.unwrap();
+    assert_eq!(response.status(), 404, "404 rules rewrite with a 404 status");
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/html");
+
+    let response = handle_request(get("/old-campaign"), &config).await.unwrap();
+    assert_eq!(response.status(), 410);
+    let body = to_bytes(response.into_body()).await.unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.starts_with("Gone. The requested resource is no longer available."), "Got: {}", body);
+}
+
+/// Test that rewrite targets built from request data are re-sanitized
+#[tokio::test]
+async fn test_handler_sanitizes_rewrite_targets() {
+    let config = ServerConfig {
+        redirect_rules: RuleSet::parse("/files f=:name /files/:name 200").unwrap(),
+        ..ServerConfig::default()
+    };
+
+    let response = handle_request(get("/files?f=..%2f..%2fetc"), &config).await.unwrap();
+    assert_eq!(response.status(), 400, "Traversal in a rewrite target should be rejected");
+}
+
+/// Test that existing content shadows rules unless they are forced
+#[tokio::test]
+async fn test_content_shadows_rules() {
+    let site = TempSite::new("rules-shadow");
+    site.file("index.html", "<h1>app</h1>")
+        .file("app.js", "console.log('app');")
+        .file("docs/index.html", "<h1>docs</h1>")
+        .file("legacy.html", "<h1>legacy</h1>");
+    let config = ServerConfig {
+        content_source: site.source(),
+        redirect_rules: RuleSet::parse("/legacy /docs/ 301!\n/docs /elsewhere 302\n/* /index.html 200").unwrap(),
+        ..ServerConfig::default()
+    };
+
+    // The catch-all rewrite only applies where nothing exists
+    let response = handle_request(get("/app.js"), &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "application/javascript");
+    assert_eq!(response.body(), &Body::Text("console.log('app');".to_string()));
+
+    let response = handle_request(get("/dashboard/settings"), &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body(), &Body::Text("<h1>app</h1>".to_string()));
+
+    // A directory is content too: /docs gets its trailing slash, not the rule
+    let response = handle_request(get("/docs"), &config).await.unwrap();
+    assert_eq!(response.status(), 301);
+    assert_eq!(response.headers()["location"], "/docs/");
+
+    // Forced rules win over existing files
+    let response = handle_request(get("/legacy"), &config).await.unwrap();
+    assert_eq!(response.status(), 301);
+    assert_eq!(response.headers()["location"], "/docs/");
+}
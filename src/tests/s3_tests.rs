@@ -0,0 +1,217 @@
+// Tests for the S3 content source
+// These tests run a minimal S3-compatible HTTP server in-process and point the
+// SDK at it through a custom endpoint, the same way a local MinIO would be used
+
+use crate::cache::ContentCache;
+use crate::config::ServerConfig;
+use crate::content::{ContentError, ContentSource, Revalidation};
+use crate::handler::handle_request;
+use crate::s3::S3Source;
+use crate::tests::support::get;
+use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Server, StatusCode};
+use lambda_http::Body;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+const LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+/// Answers the handful of S3 calls the content source makes
+///
+/// Objects live under `/site-bucket/site/`. `broken.html` simulates an S3
+/// outage and `big.bin` an object larger than the configured limit.
+/// Conditional requests with a matching `If-None-Match` get 304; for
+/// `slow.js` that answer takes 200 ms.
+async fn fake_s3(request: hyper::Request<hyper::Body>) -> Result<hyper::Response<hyper::Body>, Infallible> {
+    let path = request.uri().path().to_string();
+    let query = request.uri().query().unwrap_or("").to_string();
+    let is_head = request.method() == hyper::Method::HEAD;
+    let if_none_match = request.headers().get("if-none-match").map(|v| v.to_str().unwrap().to_string());
+    if path.ends_with("/slow.js") && if_none_match.is_some() {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    let object = |body: &'static str, etag: &'static str| {
+        if if_none_match.as_deref() == Some(etag) {
+            return hyper::Response::builder().status(StatusCode::NOT_MODIFIED).body(hyper::Body::empty()).unwrap();
+        }
+        hyper::Response::builder()
+            .status(StatusCode::OK)
+            .header("etag", etag)
+            .header("last-modified", LAST_MODIFIED)
+            .header("content-length", body.len())
+            .body(if is_head { hyper::Body::empty() } else { hyper::Body::from(body) })
+            .unwrap()
+    };
+    let error = |status: StatusCode, code: &str| {
+        let xml = format!("<?xml version=\"1.0\"?><Error><Code>{}</Code><Message>test</Message></Error>", code);
+        hyper::Response::builder()
+            .status(status)
+            .header("content-type", "application/xml")
+            .body(if is_head { hyper::Body::empty() } else { hyper::Body::from(xml) })
+            .unwrap()
+    };
+
+    let response = match path.as_str() {
+        "/site-bucket/site/index.html" => object("<h1>from s3</h1>", "\"etag-index\""),
+        "/site-bucket/site/app.js" => object("console.log('s3');", "\"etag-app\""),
+        "/site-bucket/site/slow.js" => object("console.log('slow');", "\"etag-slow\""),
+        "/site-bucket/site/big.bin" => object("0123456789abcdef0123456789abcdef", "\"etag-big\""),
+        "/site-bucket/site/broken.html" => error(StatusCode::SERVICE_UNAVAILABLE, "SlowDown"),
+        "/site-bucket" | "/site-bucket/" if query.contains("list-type=2") => {
+            let xml = "<?xml version=\"1.0\"?><ListBucketResult><Name>site-bucket</Name>\
+                <Prefix>site/reports/</Prefix><IsTruncated>false</IsTruncated>\
+                <Contents><Key>site/reports/summary.txt</Key><Size>42</Size>\
+                <LastModified>2015-10-21T07:28:00.000Z</LastModified></Contents>\
+                <CommonPrefixes><Prefix>site/reports/2024/</Prefix></CommonPrefixes>\
+                </ListBucketResult>";
+            hyper::Response::builder().status(StatusCode::OK).body(hyper::Body::from(xml)).unwrap()
+        }
+        _ => error(StatusCode::NOT_FOUND, "NoSuchKey"),
+    };
+    Ok(response)
+}
+
+/// Starts the fake S3 server on an ephemeral port and returns its endpoint URL
+fn start_fake_s3() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address: SocketAddr = listener.local_addr().unwrap();
+    let server = Server::from_tcp(listener)
+        .unwrap()
+        .serve(make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(fake_s3)) }));
+    tokio::spawn(server);
+    format!("http://{}", address)
+}
+
+/// Builds a configuration serving `site/` from the fake bucket
+fn s3_config(endpoint: &str, max_object_bytes: u64) -> ServerConfig {
+    let client_config = aws_sdk_s3::Config::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+        .region(Region::new("us-east-1"))
+        .endpoint_url(endpoint)
+        .force_path_style(true)
+        .build();
+    let source = S3Source::new("site-bucket", "/site/", Some(endpoint.to_string()), max_object_bytes)
+        .with_client(aws_sdk_s3::Client::from_conf(client_config));
+
+    ServerConfig {
+        content_source: ContentSource::S3(source),
+        listing_prefixes: vec!["/reports".to_string()],
+        ..ServerConfig::default()
+    }
+}
+
+/// Test that request paths map to keys under the prefix and never escape it
+#[test]
+fn test_key_for() {
+    let source = S3Source::new("bucket", "/site/", None, 1024);
+    assert_eq!(source.prefix, "site/");
+    assert_eq!(source.key_for("/assets/app.js"), "site/assets/app.js");
+    assert_eq!(source.key_for("/docs/"), "site/docs/");
+    assert_eq!(source.key_for("/../../other/secret"), "site/other/secret");
+    assert_eq!(S3Source::new("bucket", "", None, 1024).key_for("/index.html"), "index.html");
+}
+
+/// Test GetObject with ETag and Last-Modified propagation
+#[tokio::test]
+async fn test_s3_serves_objects() {
+    let config = s3_config(&start_fake_s3(), 1024);
+
+    let response = handle_request(get("/"), &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("etag").unwrap(), "\"etag-index\"");
+    assert_eq!(response.headers().get("last-modified").unwrap(), LAST_MODIFIED);
+    assert!(matches!(response.body(), Body::Text(text) if text == "<h1>from s3</h1>"));
+
+    let response = handle_request(get("/app.js"), &config).await.unwrap();
+    assert_eq!(response.headers().get("content-type").unwrap(), "application/javascript");
+
+    // Missing keys are ordinary misses: 404 for files, SPA fallback for routes
+    let response = handle_request(get("/missing.js"), &config).await.unwrap();
+    assert_eq!(response.status(), 404);
+    let response = handle_request(get("/dashboard"), &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+/// Test that HeadObject answers directory probes and ListObjectsV2 feeds listings
+#[tokio::test]
+async fn test_s3_head_and_list() {
+    let config = s3_config(&start_fake_s3(), 1024);
+
+    assert!(config.content_source.exists("/index.html").await.unwrap());
+    assert!(!config.content_source.exists("/nope.html").await.unwrap());
+
+    let entries = config.content_source.list("/reports/").await.unwrap().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().any(|e| e.name == "2024" && e.is_dir));
+    assert!(entries.iter().any(|e| e.name == "summary.txt" && e.size == 42 && e.modified.is_some()));
+}
+
+/// Test that S3 failures become 503 with Retry-After instead of 404 or SPA fallback
+#[tokio::test]
+async fn test_s3_errors_are_service_unavailable() {
+    let config = s3_config(&start_fake_s3(), 1024);
+
+    match config.content_source.get("/broken.html").await {
+        Err(ContentError::Unavailable { retry_after, reason, .. }) => {
+            assert_eq!(retry_after, Some(5));
+            assert!(reason.contains("SlowDown"), "Reason should carry the S3 error code: {}", reason);
+        }
+        other => panic!("Expected Unavailable, got {:?}", other),
+    }
+
+    let response = handle_request(get("/broken.html"), &config).await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.headers().get("retry-after").unwrap(), "5");
+}
+
+/// Test that objects over the size limit are handed off to a presigned URL
+#[tokio::test]
+async fn test_s3_large_objects_use_presigned_redirect() {
+    let endpoint = start_fake_s3();
+    let config = s3_config(&endpoint, 16);
+
+    let response = handle_request(get("/big.bin"), &config).await.unwrap();
+    assert_eq!(response.status(), 302);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+    let location = response.headers().get("location").unwrap().to_str().unwrap();
+    assert!(location.starts_with(&format!("{}/site-bucket/site/big.bin?", endpoint)), "Got: {}", location);
+    assert!(location.contains("X-Amz-Signature="));
+}
+
+/// Test conditional GetObject used by the content cache
+#[tokio::test]
+async fn test_s3_revalidate() {
+    let config = s3_config(&start_fake_s3(), 1024);
+
+    let revalidation = config.content_source.revalidate("/index.html", "\"etag-index\"").await.unwrap();
+    assert_eq!(revalidation, Revalidation::NotModified);
+
+    match config.content_source.revalidate("/index.html", "\"stale\"").await.unwrap() {
+        Revalidation::Modified(Some(asset)) => assert_eq!(asset.etag.as_deref(), Some("\"etag-index\"")),
+        other => panic!("Expected a fresh download, got {:?}", other),
+    }
+
+    let revalidation = config.content_source.revalidate("/deleted.html", "\"old\"").await.unwrap();
+    assert_eq!(revalidation, Revalidation::Modified(None));
+}
+
+/// Test that an asset confirmed unchanged is served even if its entry was dropped meanwhile
+#[tokio::test]
+async fn test_s3_revalidated_entry_cleared_meanwhile() {
+    let config = s3_config(&start_fake_s3(), 1024);
+    let cache = ContentCache::new(1 << 20, Duration::ZERO, Duration::ZERO);
+    cache.get(&config.content_source, "/slow.js").await.unwrap();
+
+    // The hot-reload watcher clears the cache while the conditional request is in flight
+    let (asset, ()) = tokio::join!(cache.get(&config.content_source, "/slow.js"), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cache.clear();
+    });
+    assert_eq!(asset.unwrap().expect("Unchanged asset is served").body, b"console.log('slow');");
+    assert_eq!(cache.stats().revalidations, 1);
+    assert_eq!(cache.stats().entries, 0, "A cleared entry is not brought back");
+}
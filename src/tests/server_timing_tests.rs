@@ -0,0 +1,68 @@
+// Tests for the Server-Timing header
+// These tests check stage timing collection, header formatting and the SERVER_TIMING toggle
+
+use crate::config::ServerConfig;
+use crate::handler::handle_request;
+use crate::lifecycle::ColdStart;
+use crate::server_timing::{collect, header_value, record};
+use lambda_http::{Body, http};
+use std::time::Duration;
+
+/// Test the header format with and without the cold start marker
+#[test]
+fn test_header_value() {
+    let timings = [("validate", Duration::from_micros(120)), ("lookup", Duration::from_micros(304_500))];
+    assert_eq!(
+        header_value(&timings, Duration::from_micros(498_000), None),
+        "validate;dur=0.120, lookup;dur=304.500, total;dur=498.000"
+    );
+    let cold = |init_duration| Some(ColdStart { init_duration });
+    assert_eq!(header_value(&[], Duration::from_millis(2), cold(None)), "cold-start, total;dur=2.000");
+    assert_eq!(
+        header_value(&[], Duration::from_millis(2), cold(Some(Duration::from_millis(41)))),
+        "cold-start;dur=41.000, total;dur=2.000"
+    );
+}
+
+/// Test that stages are summed per metric in first-run order, and ignored outside a request
+#[tokio::test]
+async fn test_collect_sums_stages() {
+    let ((), timings) = collect(async {
+        record("size_check", Duration::from_micros(10));
+        record("content_lookup", Duration::from_micros(300));
+        record("method_check", Duration::from_micros(5));
+        record("sanitize_path", Duration::from_micros(20));
+        record("response_building", Duration::from_micros(40));
+    })
+    .await;
+    assert_eq!(timings, vec![
+        ("validate", Duration::from_micros(35)),
+        ("lookup", Duration::from_micros(300)),
+        ("render", Duration::from_micros(40)),
+    ]);
+
+    record("size_check", Duration::from_micros(10));
+}
+
+/// Test that the handler adds the header only when SERVER_TIMING is on
+#[tokio::test]
+async fn test_handler_header() {
+    let request = || http::Request::builder().uri("/").body(Body::Empty).unwrap();
+
+    let config = ServerConfig { server_timing: true, ..ServerConfig::default() };
+    let response = handle_request(request(), &config).await.unwrap();
+    let value = response.headers()["server-timing"].to_str().unwrap();
+    for metric in ["validate;dur=", "lookup;dur=", "render;dur=", "total;dur="] {
+        assert!(value.contains(metric), "Missing {} in: {}", metric, value);
+    }
+
+    let response = handle_request(request(), &ServerConfig::default()).await.unwrap();
+    assert!(response.headers().get("server-timing").is_none());
+
+    // Rejected requests are timed up to the rejection
+    let post = http::Request::builder().method("POST").uri("/").body(Body::Empty).unwrap();
+    let response = handle_request(post, &config).await.unwrap();
+    assert_eq!(response.status(), 405);
+    let value = response.headers()["server-timing"].to_str().unwrap();
+    assert!(value.contains("validate;dur=") && !value.contains("lookup"), "Got: {}", value);
+}
@@ -0,0 +1,76 @@
+// Tests for slow request thresholds and the rolling latency summary
+// These tests check SLOW_REQUEST_ROUTES parsing, longest-prefix matching and the window's percentiles
+
+use crate::slo::{LatencyWindow, SlowRequests};
+use std::time::Duration;
+
+/// Test parsing of SLOW_REQUEST_ROUTES values
+#[test]
+fn test_parse_routes() {
+    assert_eq!(
+        SlowRequests::parse_routes(" /assets=20, /search/*=250 ,").unwrap(),
+        vec![
+            ("/assets".to_string(), Duration::from_millis(20)),
+            ("/search/*".to_string(), Duration::from_millis(250)),
+        ]
+    );
+    for invalid in ["/assets", "assets=20", "/assets=fast", "/assets=-1"] {
+        let error = SlowRequests::parse_routes(invalid).unwrap_err();
+        assert!(error.contains(invalid), "Got: {}", error);
+    }
+}
+
+/// Test that the longest matching prefix decides the threshold
+#[test]
+fn test_threshold_longest_prefix() {
+    let slow = SlowRequests {
+        default: Duration::from_millis(1000),
+        routes: SlowRequests::parse_routes("/=50,/assets=10,/assets/video/=500").unwrap(),
+    };
+    assert_eq!(slow.threshold("/about"), Duration::from_millis(50));
+    assert_eq!(slow.threshold("/assets/app.js"), Duration::from_millis(10));
+    assert_eq!(slow.threshold("/assets/video/intro.mp4"), Duration::from_millis(500));
+    assert_eq!(slow.threshold("/assets2/app.js"), Duration::from_millis(50));
+
+    assert_eq!(SlowRequests::default().threshold("/anything"), Duration::from_millis(1000));
+}
+
+/// Test percentiles over a full window, eviction of old samples and the slow count
+#[test]
+fn test_window_summary() {
+    let window = LatencyWindow::new(100);
+    assert_eq!(window.summary(), None);
+    assert!(!window.has_new());
+
+    // Samples that are evicted once 100 newer ones arrive
+    for _ in 0..50 {
+        window.record(Duration::from_secs(5), true);
+    }
+    for ms in 1..=100 {
+        window.record(Duration::from_millis(ms), ms > 97);
+    }
+    let summary = window.summary().unwrap();
+    assert_eq!(summary.count, 100);
+    assert_eq!(summary.new, 150);
+    assert_eq!(summary.p50, Duration::from_millis(50));
+    assert_eq!(summary.p95, Duration::from_millis(95));
+    assert_eq!(summary.p99, Duration::from_millis(99));
+    assert_eq!(summary.max, Duration::from_millis(100));
+    assert_eq!(summary.slow, 3);
+
+    // Logging resets the new request count but keeps the window
+    window.log_summary("interval");
+    assert!(!window.has_new());
+    assert_eq!(window.summary().unwrap().count, 100);
+}
+
+/// Test nearest-rank percentiles of a single request
+#[test]
+fn test_window_single_sample() {
+    let window = LatencyWindow::new(0);
+    window.record(Duration::from_millis(3), false);
+    window.record(Duration::from_millis(7), false);
+    let summary = window.summary().unwrap();
+    assert_eq!(summary.count, 1, "Capacity is at least one");
+    assert_eq!((summary.p50, summary.p99, summary.max), (Duration::from_millis(7), Duration::from_millis(7), Duration::from_millis(7)));
+}
@@ -0,0 +1,117 @@
+// Tests for asset serving and single-page-application fallback
+// These tests build a small content directory under the system temp directory
+
+use crate::config::ServerConfig;
+use crate::content::{content_type_for, resolve, spa_fallback_applies, Resolution};
+use crate::handler::handle_request;
+use crate::tests::support::{get, TempSite};
+use lambda_http::Body;
+
+/// Creates a fresh content directory shaped like a React build output
+fn build_output(name: &str) -> TempSite {
+    let site = TempSite::new(&format!("spa-{}", name));
+    site.file("index.html", "<!doctype html><div id=\"root\"></div>")
+        .file("assets/app.js", "console.log('app');")
+        .file("assets/logo.png", [0x89, b'P', b'N', b'G', 0xff]);
+    site
+}
+
+/// Builds a configuration serving the given directory
+fn spa_config(site: &TempSite) -> ServerConfig {
+    ServerConfig {
+        content_source: site.source(),
+        spa_exclude: vec!["/api".to_string()],
+        ..ServerConfig::default()
+    }
+}
+
+/// Test MIME types for common front-end build outputs
+#[test]
+fn test_content_type_for() {
+    assert_eq!(content_type_for("/index.html"), "text/html");
+    assert_eq!(content_type_for("/assets/app.JS"), "application/javascript");
+    assert_eq!(content_type_for("/assets/style.css"), "text/css");
+    assert_eq!(content_type_for("/fonts/inter.woff2"), "font/woff2");
+    assert_eq!(content_type_for("/releases.v2/download"), "application/octet-stream");
+}
+
+/// Test which paths are eligible for SPA fallback
+#[test]
+fn test_spa_fallback_applies() {
+    let config = ServerConfig {
+        spa_exclude: vec!["/api".to_string(), "/static/*".to_string()],
+        ..ServerConfig::default()
+    };
+
+    assert!(spa_fallback_applies("/dashboard/settings", &config));
+    assert!(spa_fallback_applies("/apis", &config), "Exclusions match whole segments");
+    assert!(!spa_fallback_applies("/missing.js", &config), "File-like paths never fall back");
+    assert!(!spa_fallback_applies("/api", &config));
+    assert!(!spa_fallback_applies("/api/users", &config));
+    assert!(!spa_fallback_applies("/static/chunk", &config));
+
+    let disabled = ServerConfig { spa_fallback: false, ..ServerConfig::default() };
+    assert!(!spa_fallback_applies("/dashboard", &disabled));
+}
+
+/// Test resolution against a content directory
+#[tokio::test]
+async fn test_resolve_directory() {
+    let site = build_output("resolve");
+    let config = spa_config(&site);
+
+    match resolve("/assets/app.js", &config, true).await.unwrap() {
+        Resolution::Found(asset) => assert_eq!(asset.content_type, "application/javascript"),
+        other => panic!("Expected asset, got {:?}", other),
+    }
+    match resolve("/dashboard/settings", &config, true).await.unwrap() {
+        Resolution::SpaFallback(asset) => assert_eq!(asset.path, "/index.html"),
+        other => panic!("Expected SPA fallback, got {:?}", other),
+    }
+    assert!(
+        matches!(resolve("/assets", &config, true).await.unwrap(), Resolution::SpaFallback(_)),
+        "Directories are not served as files"
+    );
+    assert_eq!(resolve("/missing.js", &config, true).await.unwrap(), Resolution::NotFound);
+    assert_eq!(resolve("/api/users", &config, true).await.unwrap(), Resolution::NotFound);
+}
+
+/// Test SPA behavior end to end through the handler
+#[tokio::test]
+async fn test_handler_spa_fallback() {
+    let site = build_output("handler");
+    let config = spa_config(&site);
+
+    let response = handle_request(get("/dashboard/settings"), &config).await.unwrap();
+    assert_eq!(response.status(), 200, "Client-side routes get the SPA entry point");
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/html");
+    assert!(matches!(response.body(), Body::Text(text) if text.contains("id=\"root\"")));
+
+    let response = handle_request(get("/assets/app.js"), &config).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "application/javascript");
+    assert_eq!(response.headers().get("x-content-type-options").unwrap(), "nosniff");
+
+    let response = handle_request(get("/assets/logo.png"), &config).await.unwrap();
+    assert_eq!(response.headers().get("content-type").unwrap(), "image/png");
+    assert!(matches!(response.body(), Body::Binary(bytes) if bytes.len() == 5));
+
+    let response = handle_request(get("/missing.js"), &config).await.unwrap();
+    assert_eq!(response.status(), 404, "Missing assets must not be answered with HTML");
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/plain");
+
+    let response = handle_request(get("/api/users"), &config).await.unwrap();
+    assert_eq!(response.status(), 404, "Excluded prefixes get 404");
+}
+
+/// Test that disabling SPA fallback turns unknown routes into 404s
+#[tokio::test]
+async fn test_handler_spa_fallback_disabled() {
+    let config = ServerConfig { spa_fallback: false, ..ServerConfig::default() };
+
+    let response = handle_request(get("/dashboard"), &config).await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    let response = handle_request(get("/"), &config).await.unwrap();
+    assert_eq!(response.status(), 200, "The embedded index is still served");
+}
@@ -0,0 +1,63 @@
+// Helpers shared by the test modules
+// This module builds throwaway content directories and the GET requests sent to them
+
+use crate::content::ContentSource;
+use lambda_http::{Body, http};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A content directory under the system temp directory, removed when dropped
+///
+/// Names only need to be unique among the tests: the process ID is added,
+/// and anything a crashed earlier run left behind is removed first.
+pub struct TempSite {
+    root: PathBuf,
+}
+
+impl TempSite {
+    /// Creates an empty `static-web-lambda-<name>-<pid>` directory
+    pub fn new(name: &str) -> TempSite {
+        let root = std::env::temp_dir().join(format!("static-web-lambda-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        TempSite { root }
+    }
+
+    /// Writes a file at a root-relative path, creating its parent directories
+    pub fn file(&self, path: &str, contents: impl AsRef<[u8]>) -> &TempSite {
+        let file = self.root.join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, contents).unwrap();
+        self
+    }
+
+    /// Returns the directory on disk
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns a content source serving the directory
+    pub fn source(&self) -> ContentSource {
+        ContentSource::Directory(self.root.clone())
+    }
+}
+
+impl Drop for TempSite {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Builds a GET request for the given URI
+pub fn get(uri: &str) -> http::Request<Body> {
+    get_with(uri, &[])
+}
+
+/// Builds a GET request for the given URI with extra headers
+pub fn get_with(uri: &str, headers: &[(&str, &str)]) -> http::Request<Body> {
+    let mut builder = http::Request::builder().method("GET").uri(uri);
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    builder.body(Body::Empty).expect("Failed to build request")
+}
@@ -0,0 +1,126 @@
+// Tests for the OpenTelemetry instrumentation
+// The export tests run a minimal OTLP/HTTP collector in-process and checks the spans it receives
+
+use crate::telemetry::{stage, stage_async};
+
+/// Test that stages pass their results through, with or without the feature
+#[tokio::test]
+async fn test_stages_return_their_values() {
+    assert_eq!(stage("size_check", || 41 + 1), 42);
+    assert_eq!(stage_async("content_lookup", async { "found" }).await, "found");
+}
+
+#[cfg(feature = "otel")]
+mod otlp {
+    use crate::config::ServerConfig;
+    use crate::handler::handle_request;
+    use crate::telemetry::tracer_provider;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Server, StatusCode};
+    use lambda_http::{Body, http};
+    use opentelemetry::trace::TracerProvider;
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Export request bodies received by the fake collector
+    type Received = Arc<Mutex<Vec<Vec<u8>>>>;
+
+    /// Starts a collector that records every `POST /v1/traces` body
+    fn start_fake_collector(received: Received) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address: SocketAddr = listener.local_addr().unwrap();
+        let server = Server::from_tcp(listener).unwrap().serve(make_service_fn(move |_| {
+            let received = received.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: hyper::Request<hyper::Body>| {
+                    let received = received.clone();
+                    async move {
+                        let is_traces = request.method() == hyper::Method::POST && request.uri().path() == "/v1/traces";
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        if is_traces {
+                            received.lock().unwrap().push(body.to_vec());
+                        }
+                        let status = if is_traces { StatusCode::OK } else { StatusCode::NOT_FOUND };
+                        Ok::<_, Infallible>(hyper::Response::builder().status(status).body(hyper::Body::empty()).unwrap())
+                    }
+                }))
+            }
+        }));
+        tokio::spawn(server);
+        format!("http://{}/v1/traces", address)
+    }
+
+    /// Sends a request through the handler with a tracer exporting to a fake collector
+    ///
+    /// Returns the response and the concatenated export bodies.
+    async fn export(request: http::Request<Body>) -> (lambda_http::Response<Body>, Vec<u8>) {
+        let received = Received::default();
+        let endpoint = start_fake_collector(received.clone());
+        let provider = tracer_provider(Some(&endpoint)).unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("telemetry-tests")));
+        let guard = tracing::subscriber::set_default(subscriber);
+        let response = handle_request(request, &ServerConfig::default()).await.unwrap();
+        drop(guard);
+
+        // The exporter blocks on its own HTTP client, so flush off the runtime thread
+        let provider = tokio::task::spawn_blocking(move || {
+            provider.force_flush().unwrap();
+            provider
+        })
+        .await
+        .unwrap();
+        let _ = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+
+        let bodies = received.lock().unwrap();
+        assert!(!bodies.is_empty(), "The collector received no spans");
+        (response, bodies.concat())
+    }
+
+    /// Returns true when the export contains the given hex ID as raw bytes, as protobuf encodes it
+    fn contains_id(exported: &[u8], hex: &str) -> bool {
+        let bytes: Vec<u8> = (0..hex.len() / 2).map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap()).collect();
+        exported.windows(bytes.len()).any(|window| window == bytes.as_slice())
+    }
+
+    /// Test that a request exports its root span and stage spans under the inbound trace
+    #[tokio::test]
+    async fn test_request_spans_are_exported() {
+        let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+        let request = http::Request::builder()
+            .uri("/")
+            .header("traceparent", format!("00-{}-00f067aa0ba902b7-01", trace_id))
+            .body(Body::Empty)
+            .unwrap();
+        let (response, exported) = export(request).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let traceparent = response.headers().get("traceparent").unwrap().to_str().unwrap();
+        assert!(traceparent.starts_with(&format!("00-{}-", trace_id)), "Got: {}", traceparent);
+
+        let contains = |needle: &str| exported.windows(needle.len()).any(|window| window == needle.as_bytes());
+        for name in ["request", "size_check", "method_check", "sanitize_path", "content_lookup", "response_building"] {
+            assert!(contains(name), "Missing span {}", name);
+        }
+        assert!(contains("static-web-lambda"), "Missing service name");
+        assert!(contains_id(&exported, trace_id), "Spans must continue the inbound trace");
+    }
+
+    /// Test that a request with only an X-Ray header keeps the X-Ray trace
+    #[tokio::test]
+    async fn test_xray_trace_is_continued() {
+        let request = http::Request::builder()
+            .uri("/")
+            .header("x-amzn-trace-id", "Root=1-5e1b4151-5ac6c58f5b5dcc1e1e0a7e1c;Parent=53995c3f42cd8ad8;Sampled=1")
+            .body(Body::Empty)
+            .unwrap();
+        let (response, exported) = export(request).await;
+
+        let traceparent = response.headers().get("traceparent").unwrap().to_str().unwrap();
+        assert!(traceparent.starts_with("00-5e1b41515ac6c58f5b5dcc1e1e0a7e1c-"), "Got: {}", traceparent);
+        assert!(contains_id(&exported, "5e1b41515ac6c58f5b5dcc1e1e0a7e1c"), "Spans must continue the X-Ray trace");
+        assert!(contains_id(&exported, "53995c3f42cd8ad8"), "The X-Ray segment must be the parent span");
+    }
+}
@@ -2,7 +2,7 @@
 // These tests focus on testing specific functions in isolation
 
 use crate::response::{create_html_response, create_error_response};
-use crate::security::{sanitize_path, validate_http_method};
+use crate::security::{decode_path, sanitize_path, validate_http_method};
 
 /// Test the create_html_response function directly
 /// 
@@ -246,6 +246,37 @@ fn test_sanitize_path_long_paths() {
     );
 }
 
+/// Test the decode_path function
+/// 
+/// This test verifies that percent-encoded names are decoded for the content
+/// lookup, and that decoding can't produce what sanitize_path rejects
+#[test]
+fn test_decode_path() {
+    assert_eq!(decode_path("/").unwrap(), "/");
+    assert_eq!(decode_path("/docs/intro").unwrap(), "/docs/intro");
+    assert_eq!(decode_path("/build%20log.txt").unwrap(), "/build log.txt");
+    assert_eq!(decode_path("/caf%C3%A9/%3Cscript%3E.txt").unwrap(), "/café/<script>.txt");
+    assert_eq!(decode_path("/100%25.txt").unwrap(), "/100%.txt", "Only one round of decoding");
+    
+    // Encoded slashes, control characters and directory references
+    let rejected_paths = vec![
+        "/a%2Fb",
+        "/a%2f..%2f..%2fetc",
+        "/file%00.txt",
+        "/log%0Ainjected",
+        "/%2e%2e/etc",
+        "/docs/%2E",
+        "/bad%zz",
+        "/truncated%2",
+        "/sign%+1",
+        "/invalid%FF",
+    ];
+    
+    for path in rejected_paths {
+        assert!(decode_path(path).is_err(), "Path {} should be rejected", path);
+    }
+}
+
 /// Test the validate_http_method function
 /// 
 /// This test verifies that only GET methods are allowed
@@ -28,6 +28,99 @@ resource "aws_cloudwatch_log_group" "lambda_logs" {
   })
 }
 
+# Request Metric Alarms
+# The function writes CloudWatch Embedded Metric Format (EMF) documents to its
+# log group when EMF_METRICS is on (var.enable_metrics), so these alarm on
+# native metrics instead of log metric filters. Every alarm treats missing
+# data as "not breaching": no traffic is not an outage
+locals {
+  metric_alarm_count = var.enable_metrics ? 1 : 0
+}
+
+# Server errors (5xx responses), e.g. S3 outages or internal failures
+resource "aws_cloudwatch_metric_alarm" "server_errors" {
+  count = local.metric_alarm_count
+
+  alarm_name          = "${var.function_name}-5xx-responses"
+  alarm_description   = "More than ${var.alarm_5xx_threshold} 5xx responses in 5 minutes"
+  namespace           = var.metrics_namespace
+  metric_name         = "Requests"
+  statistic           = "Sum"
+  period              = 300
+  evaluation_periods  = 1
+  threshold           = var.alarm_5xx_threshold
+  comparison_operator = "GreaterThanThreshold"
+  treat_missing_data  = "notBreaching"
+  alarm_actions       = var.alarm_sns_topic_arns
+  ok_actions          = var.alarm_sns_topic_arns
+
+  dimensions = {
+    FunctionName = var.function_name
+    StatusClass  = "5xx"
+  }
+
+  tags = merge(var.tags, {
+    Component   = "monitoring"
+    Environment = var.environment
+  })
+}
+
+# Slow successful responses (p99 over three consecutive 5 minute periods)
+resource "aws_cloudwatch_metric_alarm" "latency_p99" {
+  count = local.metric_alarm_count
+
+  alarm_name          = "${var.function_name}-latency-p99"
+  alarm_description   = "p99 latency of 2xx responses above ${var.alarm_latency_p99_ms} ms for 15 minutes"
+  namespace           = var.metrics_namespace
+  metric_name         = "Latency"
+  extended_statistic  = "p99"
+  period              = 300
+  evaluation_periods  = 3
+  threshold           = var.alarm_latency_p99_ms
+  comparison_operator = "GreaterThanThreshold"
+  treat_missing_data  = "notBreaching"
+  alarm_actions       = var.alarm_sns_topic_arns
+  ok_actions          = var.alarm_sns_topic_arns
+
+  dimensions = {
+    FunctionName = var.function_name
+    StatusClass  = "2xx"
+  }
+
+  tags = merge(var.tags, {
+    Component   = "monitoring"
+    Environment = var.environment
+  })
+}
+
+# Bursts of requests rejected by security checks (scans, traversal attempts);
+# the SecurityError dimension of the same metric shows which check fired
+resource "aws_cloudwatch_metric_alarm" "security_rejections" {
+  count = local.metric_alarm_count
+
+  alarm_name          = "${var.function_name}-security-rejections"
+  alarm_description   = "More than ${var.alarm_security_rejections_threshold} requests rejected by security checks in 5 minutes"
+  namespace           = var.metrics_namespace
+  metric_name         = "SecurityRejections"
+  statistic           = "Sum"
+  period              = 300
+  evaluation_periods  = 1
+  threshold           = var.alarm_security_rejections_threshold
+  comparison_operator = "GreaterThanThreshold"
+  treat_missing_data  = "notBreaching"
+  alarm_actions       = var.alarm_sns_topic_arns
+  ok_actions          = var.alarm_sns_topic_arns
+
+  dimensions = {
+    FunctionName = var.function_name
+  }
+
+  tags = merge(var.tags, {
+    Component   = "monitoring"
+    Environment = var.environment
+  })
+}
+
 # Note: Outputs have been moved to outputs.tf for centralized management
 # This keeps all outputs in one place for better organization
 
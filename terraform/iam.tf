@@ -83,6 +83,31 @@ resource "aws_iam_role_policy_attachment" "lambda_custom_policy_attachment" {
   policy_arn = aws_iam_policy.lambda_custom_policy.arn
 }
 
+# Read access to the content bucket, only when serving from S3
+# ListBucket lets S3 answer 404 instead of 403 for missing keys
+resource "aws_iam_role_policy" "lambda_content_bucket" {
+  count = var.content_s3_bucket == "" ? 0 : 1
+
+  name = "${var.function_name}-content-bucket"
+  role = aws_iam_role.lambda_execution_role.id
+
+  policy = jsonencode({
+    Version = "2012-10-17"
+    Statement = [
+      {
+        Effect   = "Allow"
+        Action   = ["s3:GetObject"]
+        Resource = ["arn:aws:s3:::${var.content_s3_bucket}/${var.content_s3_prefix}*"]
+      },
+      {
+        Effect   = "Allow"
+        Action   = ["s3:ListBucket"]
+        Resource = ["arn:aws:s3:::${var.content_s3_bucket}"]
+      }
+    ]
+  })
+}
+
 # Output the IAM role ARN for use by other resources
 # This will be referenced by the Lambda function resource
 output "lambda_execution_role_arn" {
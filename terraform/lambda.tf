@@ -36,11 +36,28 @@ resource "aws_lambda_function" "static_web_lambda" {
   # References the role created in iam.tf
   role = aws_iam_role.lambda_execution_role.arn
 
-  # Environment variables (if needed)
-  # Currently empty as static content doesn't require configuration
+  # Environment variables
+  # Server options are read by src/config.rs; see the README for the full list
   environment {
     variables = {
-      RUST_LOG = "info"  # Set log level for Rust applications
+      RUST_LOG                = "info" # Set log level for Rust applications
+      ALLOWED_HOSTS           = join(",", var.allowed_hosts)
+      CANONICAL_HOST          = var.canonical_host
+      REDIRECTS_FILE          = var.redirects_file
+      CONTENT_DIR             = var.content_dir
+      SPA_EXCLUDE             = join(",", var.spa_exclude)
+      CONTENT_S3_BUCKET       = var.content_s3_bucket
+      CONTENT_S3_PREFIX       = var.content_s3_prefix
+      EMF_METRICS             = var.enable_metrics ? "true" : "false"
+      METRICS_NAMESPACE       = var.metrics_namespace
+      LOG_SAMPLE_RATE         = tostring(var.log_sample_rate)
+      LOG_SUPPRESS_PATHS      = join(",", var.log_suppress_paths)
+      LOG_REDACT_QUERY_PARAMS = join(",", var.log_redact_query_params)
+      LOG_CLIENT_IP           = var.log_client_ip
+      SERVER_TIMING           = var.server_timing ? "true" : "false"
+      PREWARM                 = var.prewarm ? "true" : "false"
+      SLOW_REQUEST_MS         = tostring(var.slow_request_ms)
+      SLOW_REQUEST_ROUTES     = join(",", [for prefix, ms in var.slow_request_routes : "${prefix}=${ms}"])
       # Add other environment variables here if needed
     }
   }
@@ -121,4 +121,145 @@ variable "reserved_concurrency" {
     condition     = var.reserved_concurrency == -1 || var.reserved_concurrency >= 0
     error_message = "Reserved concurrency must be -1 (unreserved) or a non-negative number."
   }
-}
\ No newline at end of file
+}
+
+variable "allowed_hosts" {
+  description = "Hosts the Lambda function serves (ALLOWED_HOSTS). Requests for other hosts are rejected with 421, or redirected when canonical_host is set. Leave empty to accept any host."
+  type        = list(string)
+  default     = []
+}
+
+variable "canonical_host" {
+  description = "Primary domain (CANONICAL_HOST). Requests for any other host, such as the raw Function URL, are redirected here with 301. Leave empty to disable."
+  type        = string
+  default     = ""
+}
+
+variable "redirects_file" {
+  description = "Path of the redirect rules file inside the Lambda package (REDIRECTS_FILE), e.g. /var/task/_redirects. scripts/build-lambda.sh packages ./_redirects when present. Leave empty to disable."
+  type        = string
+  default     = ""
+}
+
+variable "content_dir" {
+  description = "Directory of static assets inside the Lambda package (CONTENT_DIR), e.g. /var/task/public. scripts/build-lambda.sh packages ./public when present. Leave empty to serve only the embedded page."
+  type        = string
+  default     = ""
+}
+
+variable "spa_exclude" {
+  description = "Path prefixes that return 404 instead of the single-page-application fallback (SPA_EXCLUDE), e.g. [\"/api\"]"
+  type        = list(string)
+  default     = []
+}
+
+variable "content_s3_bucket" {
+  description = "S3 bucket to serve static assets from (CONTENT_S3_BUCKET). Grants the function s3:GetObject and s3:ListBucket on it. Leave empty to serve from the package."
+  type        = string
+  default     = ""
+}
+
+variable "content_s3_prefix" {
+  description = "Key prefix inside content_s3_bucket (CONTENT_S3_PREFIX), e.g. site/"
+  type        = string
+  default     = ""
+}
+
+variable "enable_metrics" {
+  description = "Write CloudWatch Embedded Metric Format documents for every request (EMF_METRICS) and create the alarms in cloudwatch.tf"
+  type        = bool
+  default     = false
+}
+
+variable "metrics_namespace" {
+  description = "CloudWatch namespace of the request metrics (METRICS_NAMESPACE)"
+  type        = string
+  default     = "StaticWebLambda"
+}
+
+variable "log_sample_rate" {
+  description = "Fraction of successful requests whose info-level log lines are written (LOG_SAMPLE_RATE); 4xx/5xx and security events are always logged"
+  type        = number
+  default     = 1
+
+  validation {
+    condition     = var.log_sample_rate >= 0 && var.log_sample_rate <= 1
+    error_message = "log_sample_rate must be between 0 and 1."
+  }
+}
+
+variable "log_suppress_paths" {
+  description = "Path prefixes whose info-level log lines are never written (LOG_SUPPRESS_PATHS), e.g. [\"/favicon.ico\", \"/assets\"]"
+  type        = list(string)
+  default     = []
+}
+
+variable "log_redact_query_params" {
+  description = "Query parameters redacted from logs in addition to the built-in list (LOG_REDACT_QUERY_PARAMS)"
+  type        = list(string)
+  default     = []
+}
+
+variable "log_client_ip" {
+  description = "How client IPs appear in the access log (LOG_CLIENT_IP): truncate, hash or full"
+  type        = string
+  default     = "truncate"
+
+  validation {
+    condition     = contains(["truncate", "hash", "full"], var.log_client_ip)
+    error_message = "log_client_ip must be truncate, hash or full."
+  }
+}
+
+variable "server_timing" {
+  description = "Add a Server-Timing header with stage durations to every response (SERVER_TIMING)"
+  type        = bool
+  default     = false
+}
+
+variable "prewarm" {
+  description = "Load the root page and SPA index during the init phase instead of on the first request (PREWARM)"
+  type        = bool
+  default     = false
+}
+
+variable "slow_request_ms" {
+  description = "Requests slower than this many milliseconds are logged as slow (SLOW_REQUEST_MS)"
+  type        = number
+  default     = 1000
+
+  validation {
+    condition     = var.slow_request_ms >= 0 && floor(var.slow_request_ms) == var.slow_request_ms
+    error_message = "slow_request_ms must be a whole number of milliseconds."
+  }
+}
+
+variable "slow_request_routes" {
+  description = "Slow request thresholds in milliseconds per path prefix (SLOW_REQUEST_ROUTES), e.g. { \"/assets\" = 5, \"/\" = 20 }"
+  type        = map(number)
+  default     = {}
+}
+
+variable "alarm_sns_topic_arns" {
+  description = "SNS topics notified when a metrics alarm changes state. Leave empty to only show alarms in the console."
+  type        = list(string)
+  default     = []
+}
+
+variable "alarm_5xx_threshold" {
+  description = "Number of 5xx responses in 5 minutes that raises the server error alarm"
+  type        = number
+  default     = 5
+}
+
+variable "alarm_latency_p99_ms" {
+  description = "p99 request latency in milliseconds that raises the latency alarm"
+  type        = number
+  default     = 1000
+}
+
+variable "alarm_security_rejections_threshold" {
+  description = "Number of requests rejected by security checks in 5 minutes that raises the security alarm"
+  type        = number
+  default     = 100
+}